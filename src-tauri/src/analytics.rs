@@ -1,136 +1,363 @@
-use chrono::NaiveDate;
+use std::sync::Arc;
 
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::analytics::incremental::{
+    column_description, AdvancedAnalytics, ColumnInfo, IncrementalAnalytics, DEFAULT_REGION_LIMIT,
+};
+use crate::analytics::processors::GridBinning;
+use crate::error::{QuakeTrackerError, Result as QuakeResult};
+use crate::seismic::{MissingMagnitude, SeismicEvent};
 use crate::AppState;
 
 pub mod incremental;
-mod processors;
+pub(crate) mod processors;
 
 /// Get magnitude distribution using incremental analytics
 pub(crate) fn get_magnitude_distribution_internal(
     state: &AppState,
 ) -> Result<Vec<(String, u32)>, String> {
+    let analytics = analytics_handle(state)?;
+    analytics.get_magnitude_distribution()
+}
+
+/// Get magnitude distribution restricted to complete bins
+pub(crate) fn get_magnitude_distribution_above_internal(
+    state: &AppState,
+    min_magnitude: Option<f64>,
+) -> Result<Vec<(String, u32)>, String> {
+    let analytics = analytics_handle(state)?;
+    analytics.get_magnitude_distribution_above(min_magnitude)
+}
+
+/// Toggle automatic bin sizing for the magnitude distribution
+pub(crate) fn set_auto_magnitude_bins_internal(
+    state: &AppState,
+    enabled: bool,
+) -> Result<(), String> {
     let state = state
         .lock()
         .map_err(|e| format!("Failed to acquire state lock: {}", e))?;
-    state.get_analytics().get_magnitude_distribution()
+    state.get_analytics().set_auto_magnitude_bins(enabled);
+    Ok(())
 }
 
-/// Get count by date using incremental analytics
-pub(crate) fn get_count_by_year_internal(
+/// Select the grid used for coordinate clusters and the energy grid
+pub(crate) fn set_grid_binning_internal(
     state: &AppState,
-) -> Result<Vec<(NaiveDate, u32)>, String> {
+    binning: GridBinning,
+) -> Result<(), String> {
     let state = state
         .lock()
         .map_err(|e| format!("Failed to acquire state lock: {}", e))?;
-    Ok(state.get_analytics().get_count_by_date())
+    state.get_analytics().set_grid_binning(binning);
+    Ok(())
+}
+
+/// Get count by date using incremental analytics
+pub(crate) fn get_count_by_year_internal(
+    state: &AppState,
+) -> Result<Vec<(NaiveDate, u32)>, String> {
+    let analytics = analytics_handle(state)?;
+    Ok(analytics.get_count_by_date())
 }
 
 /// Get magnitude-depth pairs using incremental analytics
 pub(crate) fn get_mag_depth_pairs_internal(state: &AppState) -> Result<Vec<(f64, f64)>, String> {
-    let state = state
-        .lock()
-        .map_err(|e| format!("Failed to acquire state lock: {}", e))?;
-    Ok(state.get_analytics().get_mag_depth_pairs())
+    let analytics = analytics_handle(state)?;
+    Ok(analytics.get_mag_depth_pairs())
 }
 
 /// Get advanced analytics using Polars
 pub(crate) fn get_advanced_analytics_internal(
     state: &AppState,
 ) -> Result<serde_json::Value, String> {
-    let state = state
-        .lock()
-        .map_err(|e| format!("Failed to acquire state lock: {}", e))?;
-    match state.get_analytics().get_advanced_analytics() {
-        Ok(analytics) => analytics.to_json().map_err(|e| e.to_string()),
+    get_advanced_analytics_with_internal(state, DEFAULT_REGION_LIMIT)
+}
+
+/// Get advanced analytics with a custom number of regions in the regional
+/// analysis
+pub(crate) fn get_advanced_analytics_with_internal(
+    state: &AppState,
+    region_limit: usize,
+) -> Result<serde_json::Value, String> {
+    if region_limit == 0 {
+        return Err("Region limit must be at least 1".to_string());
+    }
+    let analytics = analytics_handle(state)?;
+    match analytics.get_advanced_analytics_with(region_limit) {
+        Ok(analytics) => analytics.to_json(),
         Err(e) => Err(e.to_string()),
     }
 }
 
+/// Compute advanced analytics for a GeoJSON FeatureCollection in a throwaway
+/// analytics instance, leaving the stored catalog untouched
+pub(crate) fn analyze_geojson_internal(geojson: &str) -> QuakeResult<AdvancedAnalytics> {
+    let value: serde_json::Value = serde_json::from_str(geojson)?;
+    if value.get("type").and_then(serde_json::Value::as_str) != Some("FeatureCollection") {
+        return Err(QuakeTrackerError::validation(
+            "geojson",
+            "Expected a GeoJSON FeatureCollection",
+        ));
+    }
+
+    let events: Vec<SeismicEvent> =
+        geojson::de::deserialize_feature_collection_str_to_vec(geojson)?;
+    // Nothing is stored, so events without a magnitude are left out
+    let events = MissingMagnitude::Skip.apply(events);
+
+    let analytics = IncrementalAnalytics::new();
+    analytics.add_events(&events)?;
+    analytics.recompute_all()?;
+    Ok(analytics.get_advanced_analytics()?)
+}
+
 /// Get hourly frequency distribution
 pub(crate) fn get_hourly_frequency_internal(state: &AppState) -> Result<Vec<(u32, u32)>, String> {
-    let state = state
-        .lock()
-        .map_err(|e| format!("Failed to acquire state lock: {}", e))?;
-    Ok(state.get_analytics().get_hourly_frequency())
+    let analytics = analytics_handle(state)?;
+    Ok(analytics.get_hourly_frequency())
 }
 
 /// Get monthly frequency distribution
 pub(crate) fn get_monthly_frequency_internal(state: &AppState) -> Result<Vec<(u32, u32)>, String> {
-    let state = state
-        .lock()
-        .map_err(|e| format!("Failed to acquire state lock: {}", e))?;
-    Ok(state.get_analytics().get_monthly_frequency())
+    let analytics = analytics_handle(state)?;
+    Ok(analytics.get_monthly_frequency())
 }
 
 /// Get geographic hotspots by region
 pub(crate) fn get_region_hotspots_internal(state: &AppState) -> Result<Vec<(String, u32)>, String> {
-    let state = state
-        .lock()
-        .map_err(|e| format!("Failed to acquire state lock: {}", e))?;
-    Ok(state.get_analytics().get_region_hotspots())
+    let analytics = analytics_handle(state)?;
+    Ok(analytics.get_region_hotspots())
+}
+
+/// Get regions ranked by activity with exponential decay by event age
+pub(crate) fn get_weighted_region_hotspots_internal(
+    state: &AppState,
+    half_life_days: f64,
+) -> Result<Vec<(String, f64)>, String> {
+    if !half_life_days.is_finite() || half_life_days <= 0.0 {
+        return Err(format!(
+            "Half-life must be a positive number of days, got {}",
+            half_life_days
+        ));
+    }
+    let analytics = analytics_handle(state)?;
+    analytics
+        .get_weighted_region_hotspots(half_life_days)
+        .map_err(|e| e.to_string())
+}
+
+/// Get the newest events, newest first, without sorting the dataframe
+pub(crate) fn get_recent_events_internal(
+    state: &AppState,
+    count: usize,
+) -> Result<Vec<SeismicEvent>, String> {
+    let analytics = analytics_handle(state)?;
+    Ok(analytics.get_recent_events(count))
 }
 
 /// Get coordinate clusters for mapping
 pub(crate) fn get_coordinate_clusters_internal(
     state: &AppState,
 ) -> Result<Vec<(f64, f64, u32)>, String> {
-    let state = state
-        .lock()
-        .map_err(|e| format!("Failed to acquire state lock: {}", e))?;
-    Ok(state.get_analytics().get_coordinate_clusters())
+    let analytics = analytics_handle(state)?;
+    Ok(analytics.get_coordinate_clusters())
+}
+
+/// Get seismic energy released per grid cell
+pub(crate) fn get_energy_grid_internal(state: &AppState) -> Result<Vec<(f64, f64, f64)>, String> {
+    let analytics = analytics_handle(state)?;
+    Ok(analytics.get_energy_grid())
 }
 
 /// Get Gutenberg-Richter b-value
 pub(crate) fn get_b_value_internal(state: &AppState) -> Result<f64, String> {
-    let state = state
-        .lock()
-        .map_err(|e| format!("Failed to acquire state lock: {}", e))?;
-    Ok(state.get_analytics().get_b_value())
+    let analytics = analytics_handle(state)?;
+    Ok(analytics.get_b_value())
+}
+
+/// Get the annual rate of events at or above a magnitude
+pub(crate) fn get_annual_rate_above_internal(
+    state: &AppState,
+    magnitude: Option<f64>,
+) -> Result<f64, String> {
+    if magnitude.is_some_and(|magnitude| !magnitude.is_finite()) {
+        return Err("Magnitude must be a finite number".to_string());
+    }
+    let analytics = analytics_handle(state)?;
+    Ok(analytics.get_annual_rate_above(magnitude))
+}
+
+/// Get the magnitude expected to occur once within a number of years
+pub(crate) fn get_expected_max_magnitude_internal(
+    state: &AppState,
+    years: f64,
+) -> Result<f64, String> {
+    if !years.is_finite() || years <= 0.0 {
+        return Err("Years must be a positive number".to_string());
+    }
+    let analytics = analytics_handle(state)?;
+    Ok(analytics.get_expected_max_magnitude(years))
 }
 
 /// Get magnitude-frequency relationship data
 pub(crate) fn get_magnitude_frequency_data_internal(
     state: &AppState,
 ) -> Result<Vec<(f64, u32, u32)>, String> {
-    let state = state
-        .lock()
-        .map_err(|e| format!("Failed to acquire state lock: {}", e))?;
-    Ok(state.get_analytics().get_magnitude_frequency_data())
+    let analytics = analytics_handle(state)?;
+    Ok(analytics.get_magnitude_frequency_data())
+}
+
+/// Get magnitude vs log10(cumulative count) pairs
+pub(crate) fn get_magnitude_frequency_loglog_internal(
+    state: &AppState,
+) -> Result<Vec<(f64, f64)>, String> {
+    let analytics = analytics_handle(state)?;
+    Ok(analytics.get_magnitude_frequency_loglog())
 }
 
 /// Get risk assessment metrics
 pub(crate) fn get_risk_metrics_internal(state: &AppState) -> Result<(f64, f64, f64, f64), String> {
-    let state = state
-        .lock()
-        .map_err(|e| format!("Failed to acquire state lock: {}", e))?;
-    Ok(state.get_analytics().get_risk_metrics())
+    let analytics = analytics_handle(state)?;
+    Ok(analytics.get_risk_metrics())
 }
 
 /// Get total seismic energy released
 pub(crate) fn get_total_energy_internal(state: &AppState) -> Result<f64, String> {
-    let state = state
-        .lock()
-        .map_err(|e| format!("Failed to acquire state lock: {}", e))?;
-    Ok(state.get_analytics().get_total_energy())
+    let analytics = analytics_handle(state)?;
+    Ok(analytics.get_total_energy())
+}
+
+/// Get total energy in a readable unit with its TNT equivalent
+pub(crate) fn get_total_energy_scaled_internal(
+    state: &AppState,
+) -> Result<(f64, String, f64), String> {
+    let analytics = analytics_handle(state)?;
+    Ok(analytics.get_total_energy_scaled())
+}
+
+/// Get approximate magnitude quantiles (p50, p90, p99)
+pub(crate) fn get_magnitude_quantiles_internal(
+    state: &AppState,
+) -> Result<(f64, f64, f64), String> {
+    let analytics = analytics_handle(state)?;
+    Ok(analytics.get_magnitude_quantiles())
+}
+
+/// Get the dataframe columns with their types and descriptions
+pub(crate) fn get_schema_internal(state: &AppState) -> Result<Vec<ColumnInfo>, String> {
+    let analytics = analytics_handle(state)?;
+    Ok(analytics
+        .schema_info()
+        .into_iter()
+        .map(|(name, dtype)| ColumnInfo {
+            description: column_description(&name).to_string(),
+            name,
+            dtype,
+        })
+        .collect())
 }
 
 /// Get weekly frequency distribution with weekday names
 pub(crate) fn get_weekly_frequency_internal(
     state: &AppState,
 ) -> Result<Vec<(String, u32)>, String> {
+    let analytics = analytics_handle(state)?;
+    Ok(analytics.get_weekly_frequency())
+}
+
+/// Clone the shared analytics handle, holding the state lock only long enough
+/// to take the clone. The analytics keep their own locks, so read-only queries
+/// then run alongside each other and alongside writers.
+fn analytics_handle(state: &AppState) -> Result<Arc<IncrementalAnalytics>, String> {
     let state = state
         .lock()
         .map_err(|e| format!("Failed to acquire state lock: {}", e))?;
-    Ok(state.get_analytics().get_weekly_frequency())
+    Ok(state.analytics_handle())
+}
+
+/// Serialize `value` for the frontend with every float rounded to
+/// `decimals` places, or at full precision when `decimals` is `None`
+pub(crate) fn rounded_json<T: Serialize>(
+    value: &T,
+    decimals: Option<u32>,
+) -> Result<serde_json::Value, String> {
+    let mut json = serde_json::to_value(value).map_err(|e| e.to_string())?;
+    if let Some(decimals) = decimals {
+        round_json(&mut json, decimals);
+    }
+    Ok(json)
+}
+
+/// Like [`rounded_json`], with the precision configured in `state`. Commands
+/// returning floats pass their result through here, so the setting applies
+/// to all of them.
+pub(crate) fn with_precision<T: Serialize>(
+    state: &AppState,
+    value: &T,
+) -> Result<serde_json::Value, String> {
+    let decimals = state
+        .lock()
+        .map_err(|e| format!("Failed to acquire state lock: {}", e))?
+        .precision_decimals();
+    rounded_json(value, decimals)
+}
+
+/// Round a value to `decimals` places, leaving values that can't be rounded
+/// untouched
+fn round_to(value: f64, decimals: u32) -> f64 {
+    if !value.is_finite() {
+        return value;
+    }
+    let factor = 10_f64.powi(decimals as i32);
+    let rounded = (value * factor).round() / factor;
+    // Very large values overflow the scaled form and are already coarse
+    if rounded.is_finite() {
+        rounded
+    } else {
+        value
+    }
+}
+
+/// Round every floating point number inside a JSON value in place
+fn round_json(value: &mut serde_json::Value, decimals: u32) {
+    match value {
+        serde_json::Value::Number(number) if number.is_f64() => {
+            let rounded = number
+                .as_f64()
+                .map(|float| round_to(float, decimals))
+                .and_then(serde_json::Number::from_f64);
+            if let Some(rounded) = rounded {
+                *number = rounded;
+            }
+        }
+        serde_json::Value::Array(items) => {
+            items.iter_mut().for_each(|item| round_json(item, decimals));
+        }
+        serde_json::Value::Object(fields) => {
+            fields
+                .values_mut()
+                .for_each(|field| round_json(field, decimals));
+        }
+        _ => {}
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use std::sync::Mutex;
+    use std::sync::{Barrier, Mutex};
 
     use super::*;
     use crate::seismic::SeismicEvent;
-    use crate::state::SeismicData;
+    use crate::state::{DataConfig, SeismicData};
+
+    fn geojson_feature(id: &str, magnitude: f64, region: &str) -> String {
+        format!(
+            r#"{{"type":"Feature","geometry":{{"type":"Point","coordinates":[22.36,38.49,-5]}},"id":"{id}","properties":{{"source_id":"{id}","source_catalog":"EMSC-RTS","lastupdate":"2024-12-10T22:28:22.145984Z","time":"2024-12-10T22:25:50.4Z","flynn_region":"{region}","lat":38.49,"lon":22.36,"depth":5,"evtype":"ke","auth":"THE","mag":{magnitude},"magtype":"ml","unid":"{id}"}}}}"#
+        )
+    }
 
     fn empty_state() -> AppState {
         Mutex::new(SeismicData::new())
@@ -337,6 +564,151 @@ mod test {
         assert_eq!(energy, total_energy);
     }
 
+    #[test]
+    fn test_precision_rounding_applied_to_output() {
+        let state = Mutex::new(SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        }));
+        // Roughly Gutenberg-Richter shaped counts so the fitted b-value is not round
+        let counts = [20, 13, 9, 6, 4, 3];
+        let events: Vec<_> = counts
+            .iter()
+            .enumerate()
+            .flat_map(|(bin, &count)| (0..count).map(move |index| (bin, index)))
+            .map(|(bin, index)| {
+                let mut event = SeismicEvent::test_event();
+                event.id = format!("{}_{}", bin, index);
+                event.magnitude = 2.0 + 0.1 * bin as f64;
+                event.depth += 1.3 * index as f64;
+                event
+            })
+            .collect();
+        let mut guard = state.lock().unwrap();
+        guard.add_events(events).unwrap();
+        // The b-value is only refitted periodically during incremental updates
+        guard.recompute_analytics().unwrap();
+        drop(guard);
+        let has_at_most_two_decimals = |value: f64| ((value * 100.0).round() / 100.0) == value;
+
+        let full_b_value = get_b_value_internal(&state).unwrap();
+        assert!(!has_at_most_two_decimals(full_b_value));
+
+        state
+            .lock()
+            .unwrap()
+            .set_precision_decimals(Some(2))
+            .unwrap();
+
+        // Commands round their result on the way out, internally the full
+        // precision is kept
+        assert_eq!(get_b_value_internal(&state).unwrap(), full_b_value);
+        let b_value = with_precision(&state, &full_b_value).unwrap();
+        assert_eq!(b_value, (full_b_value * 100.0).round() / 100.0);
+
+        let risk = with_precision(&state, &get_risk_metrics_internal(&state).unwrap()).unwrap();
+        assert!(has_at_most_two_decimals(risk[0].as_f64().unwrap()));
+
+        let analytics =
+            with_precision(&state, &get_advanced_analytics_internal(&state).unwrap()).unwrap();
+        let serialized = serde_json::to_string(&analytics).unwrap();
+        let magnitude_stats = analytics["stats"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|stat| stat["title"] == "Magnitude Statistics")
+            .unwrap();
+        let mean = magnitude_stats["data"]["mean_magnitude"].as_f64().unwrap();
+        assert!(has_at_most_two_decimals(mean));
+        assert!(
+            !serialized.contains(&format!("{}", full_b_value)),
+            "full precision leaked into {serialized}"
+        );
+
+        state.lock().unwrap().set_precision_decimals(None).unwrap();
+        assert_eq!(with_precision(&state, &full_b_value).unwrap(), full_b_value);
+        assert!(state
+            .lock()
+            .unwrap()
+            .set_precision_decimals(Some(16))
+            .is_err());
+    }
+
+    #[test]
+    fn test_precision_rounding_applied_to_loglog() {
+        let state = Mutex::new(SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        }));
+        let events: Vec<_> = (0..3)
+            .map(|index| {
+                let mut event = SeismicEvent::test_event();
+                event.id.push_str(&index.to_string());
+                event.magnitude = 2.0 + 0.5 * index as f64;
+                event
+            })
+            .collect();
+        state.lock().unwrap().add_events(events).unwrap();
+        state
+            .lock()
+            .unwrap()
+            .set_precision_decimals(Some(2))
+            .unwrap();
+
+        let loglog = get_magnitude_frequency_loglog_internal(&state).unwrap();
+        // log10 of the cumulative count of 3 events at or above M2.0
+        assert_eq!(loglog[0], (2.0, 3_f64.log10()));
+
+        let rounded = with_precision(&state, &loglog).unwrap();
+        assert_eq!(rounded[0], serde_json::json!([2.0, 0.48]));
+    }
+
+    #[test]
+    fn test_analyze_geojson_leaves_state_untouched() {
+        let state = Mutex::new(SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        }));
+        state
+            .lock()
+            .unwrap()
+            .add_or_update_event(SeismicEvent::test_event())
+            .unwrap();
+        // Events without a magnitude are left out instead of skewing the stats
+        let geojson = format!(
+            r#"{{"type":"FeatureCollection","features":[{},{},{}]}}"#,
+            geojson_feature("upload_1", 2.0, "GREECE"),
+            geojson_feature("upload_2", 4.0, "CRETE, GREECE"),
+            geojson_feature("unmeasured", 0.0, "GREECE").replace(r#""mag":0,"#, r#""mag":null,"#),
+        );
+
+        let analytics = analyze_geojson_internal(&geojson).unwrap();
+
+        let stats = |title: &str| {
+            analytics
+                .stats
+                .iter()
+                .find(|stats| stats.title == title)
+                .unwrap()
+                .data
+                .clone()
+        };
+        let magnitudes = stats("Magnitude Statistics");
+        assert_eq!(magnitudes["min_magnitude"], 2.0);
+        assert_eq!(magnitudes["max_magnitude"], 4.0);
+        assert_eq!(magnitudes["mean_magnitude"], 3.0);
+        let regions = stats("Regional Analysis");
+        assert_eq!(regions.as_array().unwrap().len(), 2);
+
+        let stored = state.lock().unwrap().get_events().unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].id, SeismicEvent::test_event().id);
+
+        let feature = analyze_geojson_internal(&geojson_feature("single", 3.0, "GREECE"));
+        assert_eq!(feature.unwrap_err().category(), "validation");
+        assert!(analyze_geojson_internal("not json").is_err());
+    }
+
     #[test]
     fn test_weekday_functionality() {
         let state = state_with_n_entries(10);
@@ -354,4 +726,66 @@ mod test {
         let total_named: u32 = weekly_freq.iter().map(|(_, count)| count).sum();
         assert_eq!(total_named, 10);
     }
+
+    #[test]
+    fn test_concurrent_readers_run_while_writer_holds_state() {
+        const READERS: usize = 4;
+        let state = Mutex::new(SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        }));
+        let events = (0..10)
+            .map(|index| {
+                let mut event = SeismicEvent::test_event();
+                event.id.push_str(&index.to_string());
+                event
+            })
+            .collect();
+        state.lock().unwrap().add_events(events).unwrap();
+        let handles_taken = Barrier::new(READERS + 1);
+        let reads_done = Barrier::new(READERS + 1);
+
+        let counts = std::thread::scope(|scope| {
+            let readers: Vec<_> = (0..READERS)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let analytics = analytics_handle(&state).unwrap();
+                        handles_taken.wait();
+                        // The writer holds the state lock until every reader is
+                        // done, so this deadlocks if reads still need that lock
+                        let counted: u32 = analytics
+                            .get_count_by_date()
+                            .iter()
+                            .map(|(_, count)| count)
+                            .sum();
+                        reads_done.wait();
+                        counted
+                    })
+                })
+                .collect();
+            scope.spawn(|| {
+                handles_taken.wait();
+                let mut guard = state.lock().unwrap();
+                let mut event = SeismicEvent::test_event();
+                event.id.push_str("writer");
+                guard.add_or_update_event(event).unwrap();
+                reads_done.wait();
+            });
+            readers
+                .into_iter()
+                .map(|reader| reader.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        // Each reader sees the catalog before or after the write, never the
+        // write counted twice
+        assert!(
+            counts.iter().all(|&counted| counted == 10 || counted == 11),
+            "{:?}",
+            counts
+        );
+
+        let response = get_count_by_year_internal(&state).unwrap();
+        assert_eq!(response.iter().map(|(_, count)| count).sum::<u32>(), 11);
+    }
 }
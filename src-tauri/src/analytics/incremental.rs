@@ -1,20 +1,38 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 
 use chrono::{DateTime, NaiveDate, Utc};
 use dashmap::DashMap;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use polars::prelude::*;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::analytics::processors::{
-    AnalyticsProcessor, GeographicHotspotsAnalytics, GutenbergRichterAnalytics,
-    MagnitudeDepthAnalytics, MagnitudeDistributionAnalytics, RiskAssessmentAnalytics,
-    TemporalPatternsAnalytics,
+    AnalyticsProcessor, GeographicHotspotsAnalytics, GridBinning, GutenbergRichterAnalytics,
+    MagnitudeDepthAnalytics, MagnitudeDistributionAnalytics, MagnitudeQuantilesAnalytics,
+    RiskAssessmentAnalytics, TemporalPatternsAnalytics,
 };
 use crate::seismic::SeismicEvent;
 
+/// Number of regions included in the regional analysis by default
+pub const DEFAULT_REGION_LIMIT: usize = 10;
+
+/// Names of the analytics processors, as used to enable or disable them
+pub const ANALYTICS_NAMES: [&str; 7] = [
+    "magnitude_distribution",
+    "temporal_patterns",
+    "magnitude_depth_pairs",
+    "geographic_hotspots",
+    "gutenberg_richter",
+    "risk_assessment",
+    "magnitude_quantiles",
+];
+
+/// Number of newest events kept for `get_recent_events`
+pub const RECENT_EVENTS_CAPACITY: usize = 100;
+
 /// Generic analytics cache that stores multiple analytics processors
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnalyticsCache {
@@ -39,6 +57,12 @@ pub struct IncrementalAnalytics {
     pub cache: Arc<RwLock<AnalyticsCache>>,
     /// Index for fast lookups by event ID
     event_index: Arc<DashMap<String, usize>>,
+    /// Newest events ordered by (time, id), oldest first, so the latest
+    /// events can be read without sorting the dataframe
+    recent_events: Arc<RwLock<VecDeque<SeismicEvent>>>,
+    /// Ingestion sequence number given to the next stored row, starting at 1
+    /// and never reset
+    next_seq: Arc<AtomicU64>,
     /// Analytics processors
     magnitude_distribution: Arc<MagnitudeDistributionAnalytics>,
     temporal_patterns: Arc<TemporalPatternsAnalytics>,
@@ -46,12 +70,31 @@ pub struct IncrementalAnalytics {
     geographic_hotspots: Arc<GeographicHotspotsAnalytics>,
     gutenberg_richter: Arc<GutenbergRichterAnalytics>,
     risk_assessment: Arc<RiskAssessmentAnalytics>,
+    magnitude_quantiles: Arc<MagnitudeQuantilesAnalytics>,
     /// List of all analytics processors for iteration
     analytics_processors: Vec<Arc<dyn AnalyticsProcessor>>,
+    /// Names of the processors kept up to date; the others stay empty
+    enabled_analytics: Arc<RwLock<HashSet<String>>>,
     /// Flag to indicate if full recomputation is needed
     needs_full_recompute: Arc<AtomicBool>,
+    /// Whether full recomputes run the processors on the rayon pool instead
+    /// of one after another
+    parallel_recompute: Arc<AtomicBool>,
+    /// Held by writers and recomputes, so a recompute never sees a row whose
+    /// processor updates are still pending, and concurrent getters racing on
+    /// stale analytics run only one recompute
+    recompute_gate: Arc<Mutex<()>>,
+    /// Number of full recomputations run
+    #[cfg(test)]
+    recompute_runs: std::sync::atomic::AtomicUsize,
+    /// Run by writers after storing rows and before updating the processors
+    #[cfg(test)]
+    write_hook: Mutex<Option<WriteHook>>,
 }
 
+#[cfg(test)]
+type WriteHook = Box<dyn Fn() + Send + Sync>;
+
 impl IncrementalAnalytics {
     pub fn new() -> Self {
         let magnitude_distribution = Arc::new(MagnitudeDistributionAnalytics::new());
@@ -60,6 +103,7 @@ impl IncrementalAnalytics {
         let geographic_hotspots = Arc::new(GeographicHotspotsAnalytics::new());
         let gutenberg_richter = Arc::new(GutenbergRichterAnalytics::new());
         let risk_assessment = Arc::new(RiskAssessmentAnalytics::new());
+        let magnitude_quantiles = Arc::new(MagnitudeQuantilesAnalytics::new());
 
         let analytics_processors: Vec<Arc<dyn AnalyticsProcessor>> = vec![
             magnitude_distribution.clone(),
@@ -68,20 +112,38 @@ impl IncrementalAnalytics {
             geographic_hotspots.clone(),
             gutenberg_richter.clone(),
             risk_assessment.clone(),
+            magnitude_quantiles.clone(),
         ];
 
         Self {
             dataframe: Arc::new(RwLock::new(Self::empty_df())),
             cache: Arc::new(RwLock::new(AnalyticsCache::default())),
             event_index: Arc::new(DashMap::new()),
+            recent_events: Arc::new(RwLock::new(VecDeque::with_capacity(
+                RECENT_EVENTS_CAPACITY + 1,
+            ))),
+            next_seq: Arc::new(AtomicU64::new(1)),
             magnitude_distribution,
             temporal_patterns,
             magnitude_depth_pairs,
             geographic_hotspots,
             gutenberg_richter,
             risk_assessment,
+            magnitude_quantiles,
             analytics_processors,
+            enabled_analytics: Arc::new(RwLock::new(
+                ANALYTICS_NAMES
+                    .iter()
+                    .map(|name| name.to_string())
+                    .collect(),
+            )),
             needs_full_recompute: Arc::new(AtomicBool::new(false)),
+            parallel_recompute: Arc::new(AtomicBool::new(true)),
+            recompute_gate: Arc::new(Mutex::new(())),
+            #[cfg(test)]
+            recompute_runs: std::sync::atomic::AtomicUsize::new(0),
+            #[cfg(test)]
+            write_hook: Mutex::new(None),
         }
     }
 
@@ -100,6 +162,9 @@ impl IncrementalAnalytics {
             "source_catalog" => Vec::<String>::new(),
             "lastupdate" => Vec::<i64>::new(),
             "author" => Vec::<String>::new(),
+            "nsta" => Vec::<Option<i32>>::new(),
+            "gap" => Vec::<Option<f64>>::new(),
+            "seq" => Vec::<u64>::new(),
         ]
         .expect("Failed to create empty dataframe")
         .lazy()
@@ -111,23 +176,28 @@ impl IncrementalAnalytics {
 
     /// Add a single event and update analytics incrementally
     pub fn add_event(&self, event: &SeismicEvent) -> Result<(), PolarsError> {
+        let _gate = self.recompute_gate.lock();
         let event_id = event.id.clone();
 
         if self.event_index.contains_key(&event_id) {
-            return self.update_event(event);
+            return self.replace_event(event);
         }
 
-        let event_df = self.event_to_dataframe(event)?;
+        let event_df = self.with_sequence(self.event_to_dataframe(event)?)?;
 
         {
             let mut df_guard = self.dataframe.write();
             *df_guard = concat([df_guard.clone(), event_df.lazy()], UnionArgs::default())?;
         }
 
+        #[cfg(test)]
+        self.run_write_hook();
+
         let new_index = self.event_index.len();
         self.event_index.insert(event_id, new_index);
+        self.remember_recent(event);
 
-        for processor in &self.analytics_processors {
+        for processor in self.enabled_processors() {
             processor.update(event)?;
         }
 
@@ -140,28 +210,94 @@ impl IncrementalAnalytics {
         Ok(())
     }
 
+    #[cfg(test)]
+    fn run_write_hook(&self) {
+        if let Some(hook) = self.write_hook.lock().as_ref() {
+            hook();
+        }
+    }
+
+    /// Whether an event with this id is stored
+    pub fn contains_event(&self, event_id: &str) -> bool {
+        self.event_index.contains_key(event_id)
+    }
+
     /// Update an existing event
-    pub fn update_event(&self, _event: &SeismicEvent) -> Result<(), PolarsError> {
+    pub fn update_event(&self, event: &SeismicEvent) -> Result<(), PolarsError> {
+        let _gate = self.recompute_gate.lock();
+        self.replace_event(event)
+    }
+
+    /// Replace the stored row of `event` and mark analytics stale. The new
+    /// row gets a new sequence number so incremental syncs see the update.
+    /// Callers hold the gate.
+    fn replace_event(&self, event: &SeismicEvent) -> Result<(), PolarsError> {
+        let row = self.with_sequence(self.event_to_dataframe(event)?)?;
+        {
+            let mut df_guard = self.dataframe.write();
+            let kept = df_guard
+                .clone()
+                .filter(col("unid").neq(lit(event.id.as_str())));
+            *df_guard = concat([kept, row.lazy()], UnionArgs::default())?;
+        }
+
+        self.remember_recent(event);
         self.needs_full_recompute.store(true, Ordering::Relaxed);
         Ok(())
     }
 
+    /// Keep `event` in the recent events if it is among the newest, replacing
+    /// an earlier version with the same id
+    fn remember_recent(&self, event: &SeismicEvent) {
+        let mut recent = self.recent_events.write();
+        recent.retain(|known| known.id != event.id);
+
+        let position =
+            recent.partition_point(|known| (known.time, &known.id) < (event.time, &event.id));
+        if position == 0 && recent.len() >= RECENT_EVENTS_CAPACITY {
+            return;
+        }
+        recent.insert(position, event.clone());
+        if recent.len() > RECENT_EVENTS_CAPACITY {
+            recent.pop_front();
+        }
+    }
+
+    /// Up to `count` of the newest events, newest first
+    ///
+    /// At most `RECENT_EVENTS_CAPACITY` events are kept, so larger counts
+    /// return fewer events than are stored.
+    pub fn get_recent_events(&self, count: usize) -> Vec<SeismicEvent> {
+        self.recent_events
+            .read()
+            .iter()
+            .rev()
+            .take(count)
+            .cloned()
+            .collect()
+    }
+
     /// Add multiple events efficiently
     pub fn add_events(&self, events: &[SeismicEvent]) -> Result<(), PolarsError> {
         if events.is_empty() {
             return Ok(());
         }
 
-        let events_df = self.events_to_dataframe(events)?;
+        let _gate = self.recompute_gate.lock();
+        let events_df = self.with_sequence(self.events_to_dataframe(events)?)?;
 
         {
             let mut df_guard = self.dataframe.write();
             *df_guard = concat([df_guard.clone(), events_df.lazy()], UnionArgs::default())?;
         }
 
+        #[cfg(test)]
+        self.run_write_hook();
+
         let start_index = self.event_index.len();
         for (i, event) in events.iter().enumerate() {
             self.event_index.insert(event.id.clone(), start_index + i);
+            self.remember_recent(event);
         }
 
         self.update_analytics_parallel(events)?;
@@ -177,7 +313,7 @@ impl IncrementalAnalytics {
 
     fn update_analytics_parallel(&self, events: &[SeismicEvent]) -> Result<(), PolarsError> {
         let results: Result<Vec<_>, PolarsError> = self
-            .analytics_processors
+            .enabled_processors()
             .par_iter()
             .map(|processor| {
                 log::debug!(
@@ -216,104 +352,273 @@ impl IncrementalAnalytics {
         }
     }
 
+    /// Processors that are kept up to date
+    fn enabled_processors(&self) -> Vec<Arc<dyn AnalyticsProcessor>> {
+        let enabled = self.enabled_analytics.read();
+        self.analytics_processors
+            .iter()
+            .filter(|processor| enabled.contains(processor.name()))
+            .cloned()
+            .collect()
+    }
+
+    /// Names of the processors that are kept up to date
+    pub fn enabled_analytics(&self) -> HashSet<String> {
+        self.enabled_analytics.read().clone()
+    }
+
+    /// Only keep the processors named in `names` up to date. Disabled
+    /// processors are cleared, so their getters return empty results, and
+    /// re-enabled ones catch up on the next read. Unknown names are ignored.
+    pub fn set_enabled_analytics(&self, names: &HashSet<String>) {
+        let _gate = self.recompute_gate.lock();
+        *self.enabled_analytics.write() = names.clone();
+        for processor in &self.analytics_processors {
+            if !names.contains(processor.name()) {
+                processor.clear();
+            }
+        }
+        self.needs_full_recompute.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether full recomputes run the processors in parallel
+    pub fn parallel_recompute(&self) -> bool {
+        self.parallel_recompute.load(Ordering::Relaxed)
+    }
+
+    /// Run the processors of a full recompute in parallel (the default) or
+    /// one after another, e.g. to keep the rayon pool free for other work
+    pub fn set_parallel_recompute(&self, parallel: bool) {
+        self.parallel_recompute.store(parallel, Ordering::Relaxed);
+    }
+
     /// Get magnitude distribution
     pub fn get_magnitude_distribution(&self) -> Result<Vec<(String, u32)>, String> {
-        if self.needs_full_recompute.load(Ordering::Relaxed) {
-            self.recompute_all().map_err(|e| e.to_string())?;
-        }
+        self.flush().map_err(|e| e.to_string())?;
         self.magnitude_distribution.get_result()
     }
 
+    /// Get magnitude distribution without bins below `min_magnitude`, or
+    /// below the magnitude of completeness when `None`
+    pub fn get_magnitude_distribution_above(
+        &self,
+        min_magnitude: Option<f64>,
+    ) -> Result<Vec<(String, u32)>, String> {
+        self.flush().map_err(|e| e.to_string())?;
+        let min_magnitude =
+            min_magnitude.unwrap_or_else(|| self.gutenberg_richter.get_completeness_magnitude());
+        self.magnitude_distribution.get_result_above(min_magnitude)
+    }
+
+    /// Switch the magnitude distribution between fixed 0.2 buckets and
+    /// Freedman-Diaconis sized buckets, taking effect on the next read
+    pub fn set_auto_magnitude_bins(&self, enabled: bool) {
+        self.magnitude_distribution.set_auto_bins(enabled);
+        self.needs_full_recompute.store(true, Ordering::Relaxed);
+    }
+
     /// Get count by date
     pub fn get_count_by_date(&self) -> Vec<(NaiveDate, u32)> {
-        if self.needs_full_recompute.load(Ordering::Relaxed) {
-            self.recompute_all().ok();
-        }
+        self.flush().ok();
         self.temporal_patterns.get_result()
     }
 
     /// Get magnitude-depth pairs
     pub fn get_mag_depth_pairs(&self) -> Vec<(f64, f64)> {
-        if self.needs_full_recompute.load(Ordering::Relaxed) {
-            self.recompute_all().ok();
-        }
+        self.flush().ok();
         self.magnitude_depth_pairs.get_result()
     }
 
     /// Get hourly frequency distribution
     pub fn get_hourly_frequency(&self) -> Vec<(u32, u32)> {
-        if self.needs_full_recompute.load(Ordering::Relaxed) {
-            self.recompute_all().ok();
-        }
+        self.flush().ok();
         self.temporal_patterns.get_hourly_distribution()
     }
 
     /// Get monthly frequency distribution
     pub fn get_monthly_frequency(&self) -> Vec<(u32, u32)> {
-        if self.needs_full_recompute.load(Ordering::Relaxed) {
-            self.recompute_all().ok();
-        }
+        self.flush().ok();
         self.temporal_patterns.get_monthly_distribution()
     }
 
     /// Get weekly frequency distribution with weekday names
+    ///
+    /// Always contains all 7 weekdays, with zero counts when there is no data
     pub fn get_weekly_frequency(&self) -> Vec<(String, u32)> {
-        if self.needs_full_recompute.load(Ordering::Relaxed) {
-            self.recompute_all().ok();
-        }
+        self.flush().ok();
         self.temporal_patterns.get_weekly_distribution()
     }
 
     /// Get geographic hotspots by region
     pub fn get_region_hotspots(&self) -> Vec<(String, u32)> {
-        if self.needs_full_recompute.load(Ordering::Relaxed) {
-            self.recompute_all().ok();
-        }
+        self.flush().ok();
         self.geographic_hotspots.get_region_hotspots()
     }
 
+    /// Get regions ranked by recency-weighted activity, computed from the
+    /// dataframe since the weights depend on the query time
+    ///
+    /// Each event contributes `0.5^(age / half_life_days)`, so an event from
+    /// today counts 1.0 and one `half_life_days` old counts 0.5. Events dated
+    /// in the future count as if they happened now. `half_life_days` must be
+    /// positive.
+    pub fn get_weighted_region_hotspots(
+        &self,
+        half_life_days: f64,
+    ) -> Result<Vec<(String, f64)>, PolarsError> {
+        const NANOS_PER_DAY: f64 = 86_400.0 * 1_000_000_000.0;
+        let now = Utc::now().timestamp_nanos_opt().unwrap_or(i64::MAX);
+
+        let age_days = (lit(now) - col("time").cast(DataType::Int64)).cast(DataType::Float64)
+            / lit(NANOS_PER_DAY);
+        let age_days = when(age_days.clone().lt(lit(0.0)))
+            .then(lit(0.0))
+            .otherwise(age_days);
+        let weight = lit(0.5).pow(age_days / lit(half_life_days));
+
+        let scores = self
+            .get_dataframe()
+            .filter(col("flynn_region").is_not_null())
+            .group_by([col("flynn_region")])
+            .agg([weight.sum().alias("score")])
+            .sort(
+                ["score"],
+                SortMultipleOptions::default().with_order_descending(true),
+            )
+            .collect()?;
+
+        let regions = scores.column("flynn_region")?.str()?;
+        let weights = scores.column("score")?.f64()?;
+        Ok(regions
+            .iter()
+            .zip(weights.iter())
+            .filter_map(|(region, score)| Some((region?.to_string(), score?)))
+            .collect())
+    }
+
     /// Get coordinate clusters for mapping
     pub fn get_coordinate_clusters(&self) -> Vec<(f64, f64, u32)> {
-        if self.needs_full_recompute.load(Ordering::Relaxed) {
-            self.recompute_all().ok();
-        }
+        self.flush().ok();
         self.geographic_hotspots.get_coordinate_clusters()
     }
 
+    /// Get seismic energy released per grid cell for mapping
+    pub fn get_energy_grid(&self) -> Vec<(f64, f64, f64)> {
+        self.flush().ok();
+        self.geographic_hotspots.get_energy_grid()
+    }
+
+    /// Grid behind coordinate clusters and the energy grid
+    pub fn grid_binning(&self) -> GridBinning {
+        self.geographic_hotspots.binning()
+    }
+
+    /// Switch the grid behind coordinate clusters and the energy grid,
+    /// taking effect on the next read
+    pub fn set_grid_binning(&self, binning: GridBinning) {
+        self.geographic_hotspots.set_binning(binning);
+        self.needs_full_recompute.store(true, Ordering::Relaxed);
+    }
+
     /// Get Gutenberg-Richter b-value
     pub fn get_b_value(&self) -> f64 {
-        if self.needs_full_recompute.load(Ordering::Relaxed) {
-            self.recompute_all().ok();
-        }
+        self.flush().ok();
         self.gutenberg_richter.get_b_value()
     }
 
+    /// Expected events per year at or above `magnitude` from the fitted
+    /// Gutenberg-Richter relation, defaulting to the completeness magnitude
+    pub fn get_annual_rate_above(&self, magnitude: Option<f64>) -> f64 {
+        self.flush().ok();
+        let magnitude =
+            magnitude.unwrap_or_else(|| self.gutenberg_richter.get_completeness_magnitude());
+        self.gutenberg_richter
+            .annual_rate_above(magnitude, self.risk_assessment.get_time_span_days())
+    }
+
+    /// Magnitude expected to occur once within `years`, from the fitted
+    /// Gutenberg-Richter relation scaled by the catalog's time span
+    pub fn get_expected_max_magnitude(&self, years: f64) -> f64 {
+        self.flush().ok();
+        self.gutenberg_richter
+            .expected_max_magnitude(years, self.risk_assessment.get_time_span_days())
+    }
+
+    /// Get the standard error of the b-value
+    pub fn get_b_value_uncertainty(&self) -> f64 {
+        self.flush().ok();
+        self.gutenberg_richter.get_b_value_uncertainty()
+    }
+
+    /// Get the magnitude of completeness used for the b-value fit
+    pub fn get_completeness_magnitude(&self) -> f64 {
+        self.flush().ok();
+        self.gutenberg_richter.get_completeness_magnitude()
+    }
+
     /// Get magnitude-frequency relationship data
     pub fn get_magnitude_frequency_data(&self) -> Vec<(f64, u32, u32)> {
-        if self.needs_full_recompute.load(Ordering::Relaxed) {
-            self.recompute_all().ok();
-        }
+        self.flush().ok();
         self.gutenberg_richter.get_magnitude_frequency_data()
     }
 
+    /// Get magnitude vs log10(cumulative count) pairs for log-log plots
+    pub fn get_magnitude_frequency_loglog(&self) -> Vec<(f64, f64)> {
+        self.flush().ok();
+        self.gutenberg_richter.get_magnitude_frequency_loglog()
+    }
+
     /// Get risk assessment metrics
     pub fn get_risk_metrics(&self) -> (f64, f64, f64, f64) {
-        if self.needs_full_recompute.load(Ordering::Relaxed) {
-            self.recompute_all().ok();
-        }
+        self.flush().ok();
         self.risk_assessment.get_risk_metrics()
     }
 
+    /// Longest forecast horizon used for risk probabilities, in days
+    pub fn max_risk_horizon_days(&self) -> f64 {
+        self.risk_assessment.max_horizon_days()
+    }
+
+    /// Limit the forecast horizon used for risk probabilities
+    pub fn set_max_risk_horizon_days(&self, days: f64) {
+        self.risk_assessment.set_max_horizon_days(days)
+    }
+
     /// Get total seismic energy released
     pub fn get_total_energy(&self) -> f64 {
-        if self.needs_full_recompute.load(Ordering::Relaxed) {
-            self.recompute_all().ok();
-        }
+        self.flush().ok();
         self.risk_assessment.get_total_energy()
     }
 
+    /// Get total energy as `(value, unit, tons of TNT)`
+    pub fn get_total_energy_scaled(&self) -> (f64, String, f64) {
+        self.flush().ok();
+        let (value, unit) = self.risk_assessment.get_total_energy_scaled();
+        (
+            value,
+            unit,
+            self.risk_assessment.get_total_energy_tnt_tons(),
+        )
+    }
+
+    /// Get approximate (p50, p90, p99) magnitudes
+    pub fn get_magnitude_quantiles(&self) -> (f64, f64, f64) {
+        self.flush().ok();
+        self.magnitude_quantiles.get_quantiles()
+    }
+
     /// Get advanced analytics using Polars lazy evaluation
     pub fn get_advanced_analytics(&self) -> Result<AdvancedAnalytics, PolarsError> {
+        self.get_advanced_analytics_with(DEFAULT_REGION_LIMIT)
+    }
+
+    /// Get advanced analytics, including the `region_limit` most active
+    /// regions in the regional analysis
+    pub fn get_advanced_analytics_with(
+        &self,
+        region_limit: usize,
+    ) -> Result<AdvancedAnalytics, PolarsError> {
+        self.flush()?;
         let df = self.dataframe.read();
 
         log::debug!(
@@ -322,7 +627,7 @@ impl IncrementalAnalytics {
         );
 
         let stats_results: Result<Vec<_>, PolarsError> = self
-            .analytics_processors
+            .enabled_processors()
             .par_iter()
             .map(|processor| {
                 let lazy_stats = processor.get_auxiliary_stats(&df);
@@ -365,7 +670,7 @@ impl IncrementalAnalytics {
                 ["event_count"],
                 SortMultipleOptions::default().with_order_descending(true),
             )
-            .limit(10)
+            .limit(region_limit as IdxSize)
             .collect()?;
 
         let regional_data = dataframe_to_json(&regional_analysis)?;
@@ -383,9 +688,11 @@ impl IncrementalAnalytics {
 
     /// Clear all data and reset analytics
     pub fn clear(&self) {
+        let _gate = self.recompute_gate.lock();
         *self.dataframe.write() = Self::empty_df();
         *self.cache.write() = AnalyticsCache::default();
         self.event_index.clear();
+        self.recent_events.write().clear();
 
         for processor in &self.analytics_processors {
             processor.clear();
@@ -399,32 +706,84 @@ impl IncrementalAnalytics {
         self.dataframe.read().clone()
     }
 
+    /// Column names and Polars dtypes of the event dataframe, in column order
+    pub fn schema_info(&self) -> Vec<(String, String)> {
+        match self.dataframe.read().clone().collect_schema() {
+            Ok(schema) => schema
+                .iter()
+                .map(|(name, dtype)| (name.to_string(), dtype.to_string()))
+                .collect(),
+            Err(e) => {
+                log::error!("Failed to resolve dataframe schema: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Whether analytics are stale and will be recomputed on the next read
+    pub fn needs_recompute(&self) -> bool {
+        self.needs_full_recompute.load(Ordering::Relaxed)
+    }
+
+    /// Bring analytics up to date with the dataframe, recomputing only if an
+    /// earlier change marked them stale. Callers racing on the same stale
+    /// state wait for the first recompute instead of running their own.
+    pub fn flush(&self) -> Result<(), PolarsError> {
+        if !self.needs_recompute() {
+            return Ok(());
+        }
+        let _gate = self.recompute_gate.lock();
+        if self.needs_recompute() {
+            self.recompute_processors()?;
+        }
+        Ok(())
+    }
+
     /// Force a full recomputation of all analytics
     pub fn recompute_all(&self) -> Result<(), PolarsError> {
+        let _gate = self.recompute_gate.lock();
+        self.recompute_processors()
+    }
+
+    /// Recompute every enabled processor from the dataframe. Callers hold the
+    /// gate.
+    fn recompute_processors(&self) -> Result<(), PolarsError> {
+        #[cfg(test)]
+        self.recompute_runs.fetch_add(1, Ordering::Relaxed);
         let df = self.dataframe.read();
+        let parallel = self.parallel_recompute();
+        let mode = if parallel { "parallel" } else { "sequential" };
 
-        log::debug!("Starting parallel recomputation of all analytics processors");
+        log::debug!(
+            "Starting {} recomputation of all analytics processors",
+            mode
+        );
 
-        let results: Result<Vec<_>, PolarsError> = self
-            .analytics_processors
-            .par_iter()
-            .map(|processor| {
-                log::debug!("Recomputing analytics processor '{}'", processor.name());
-                processor.recompute(&df)
-            })
-            .collect();
+        // Processors guard their own state, so they can recompute concurrently
+        // over the shared read-only frame
+        let recompute = |processor: &Arc<dyn AnalyticsProcessor>| {
+            log::debug!("Recomputing analytics processor '{}'", processor.name());
+            processor.recompute(&df)
+        };
+        let processors = self.enabled_processors();
+        let results: Result<Vec<_>, PolarsError> = if parallel {
+            processors.par_iter().map(recompute).collect()
+        } else {
+            processors.iter().map(recompute).collect()
+        };
 
         match results {
             Ok(_) => {
                 log::debug!(
-                    "Successfully recomputed {} analytics processors in parallel",
-                    self.analytics_processors.len()
+                    "Successfully recomputed {} analytics processors ({})",
+                    processors.len(),
+                    mode
                 );
                 self.needs_full_recompute.store(false, Ordering::Relaxed);
                 Ok(())
             }
             Err(e) => {
-                log::error!("Parallel analytics recomputation failed: {}", e);
+                log::error!("Analytics recomputation ({}) failed: {}", mode, e);
                 Err(e)
             }
         }
@@ -432,14 +791,25 @@ impl IncrementalAnalytics {
 
     /// Replace the dataframe with a filtered version and rebuild analytics
     /// This is used for cleanup operations to remove old or excess events
+    ///
+    /// The dataframe is only replaced once `new_df` collects, but a failing
+    /// processor leaves the analytics partially rebuilt. Use
+    /// [`Self::rebuilt_from`] when the current state must survive failures.
     pub fn replace_dataframe_and_rebuild(&self, new_df: LazyFrame) -> Result<(), PolarsError> {
-        {
-            let mut df_guard = self.dataframe.write();
-            *df_guard = new_df;
+        let _gate = self.recompute_gate.lock();
+        let mut collected_df = new_df.collect()?;
+        match collected_df.column("seq") {
+            Ok(seq) => {
+                if let Some(max) = seq.u64()?.max() {
+                    self.next_seq.fetch_max(max + 1, Ordering::Relaxed);
+                }
+            }
+            // Rows that weren't stored before are numbered in frame order
+            Err(_) => collected_df = self.with_sequence(collected_df)?,
         }
+        *self.dataframe.write() = collected_df.clone().lazy();
 
         self.event_index.clear();
-        let collected_df = self.dataframe.read().clone().collect()?;
         if let Ok(ids_column) = collected_df.column("unid") {
             if let Ok(ids) = ids_column.str() {
                 for (index, id_opt) in ids.iter().enumerate() {
@@ -450,6 +820,20 @@ impl IncrementalAnalytics {
             }
         }
 
+        let newest = collected_df
+            .clone()
+            .lazy()
+            .sort(
+                ["time", "unid"],
+                SortMultipleOptions::default().with_order_descending_multi([true, true]),
+            )
+            .limit(RECENT_EVENTS_CAPACITY as IdxSize)
+            .collect()?;
+        *self.recent_events.write() = Self::dataframe_to_events(&newest)?
+            .into_iter()
+            .rev()
+            .collect();
+
         {
             let mut cache = self.cache.write();
             cache.total_events = collected_df.height();
@@ -460,10 +844,136 @@ impl IncrementalAnalytics {
             processor.clear();
         }
 
-        self.recompute_all()?;
+        self.recompute_processors()?;
+        Ok(())
+    }
+
+    /// Discard everything derived from the dataframe, including processor
+    /// state, the id index and the recent events, and rebuild it from the
+    /// stored rows, which are left untouched
+    pub fn reset_cache(&self) -> Result<(), PolarsError> {
+        self.replace_dataframe_and_rebuild(self.get_dataframe())
+    }
+
+    /// Feed `event` to the processors without storing it, leaving them out of
+    /// sync with the dataframe
+    #[cfg(test)]
+    pub(crate) fn corrupt_processors(&self, event: &SeismicEvent) -> Result<(), PolarsError> {
+        for processor in &self.analytics_processors {
+            processor.update(event)?;
+        }
         Ok(())
     }
 
+    /// Build new analytics holding `new_df`, with the same settings as this
+    /// instance, leaving this instance untouched
+    pub fn rebuilt_from(&self, new_df: LazyFrame) -> Result<Self, PolarsError> {
+        let rebuilt = Self::new();
+        rebuilt
+            .next_seq
+            .store(self.next_seq.load(Ordering::Relaxed), Ordering::Relaxed);
+        rebuilt
+            .magnitude_distribution
+            .set_auto_bins(self.magnitude_distribution.auto_bins());
+        rebuilt
+            .geographic_hotspots
+            .set_binning(self.geographic_hotspots.binning());
+        rebuilt
+            .risk_assessment
+            .set_max_horizon_days(self.risk_assessment.max_horizon_days());
+        rebuilt.set_enabled_analytics(&self.enabled_analytics());
+        rebuilt.set_parallel_recompute(self.parallel_recompute());
+        rebuilt.replace_dataframe_and_rebuild(new_df)?;
+        Ok(rebuilt)
+    }
+
+    /// Convert event rows back into events. Origins and arrivals aren't
+    /// stored, so they are always `None`.
+    pub(crate) fn dataframe_to_events(df: &DataFrame) -> Result<Vec<SeismicEvent>, PolarsError> {
+        let mut events = Vec::new();
+        let height = df.height();
+
+        if height == 0 {
+            return Ok(events);
+        }
+
+        let ids = df.column("unid")?.str()?;
+        let lats = df.column("lat")?.f64()?;
+        let lons = df.column("lon")?.f64()?;
+        let times = df.column("time")?.datetime()?;
+        let mags = df.column("mag")?.f64()?;
+        let magtypes = df.column("magtype")?.str()?;
+        let depths = df.column("depth")?.f64()?;
+        let evtypes = df.column("evtype")?.str()?;
+        let flynn_regions = df.column("flynn_region")?.str()?;
+        let source_ids = df.column("source_id")?.str()?;
+        let source_catalogs = df.column("source_catalog")?.str()?;
+        let lastupdates = df.column("lastupdate")?.datetime()?;
+        let authors = df.column("author")?.str()?;
+
+        for i in 0..height {
+            let id = ids.get(i).map(|s| s.to_string()).unwrap_or_default();
+            let latitude = lats.get(i).unwrap_or(0.0);
+            let longitude = lons.get(i).unwrap_or(0.0);
+            let time_ns = times.get(i).unwrap_or(0);
+            let magnitude = mags.get(i).unwrap_or(0.0);
+            let magnitude_type = magtypes.get(i).map(|s| s.to_string()).unwrap_or_default();
+            let depth = depths.get(i).unwrap_or(0.0);
+            let event_type = evtypes.get(i).map(|s| s.to_string()).unwrap_or_default();
+            let flynn_region = flynn_regions
+                .get(i)
+                .map(|s| s.to_string())
+                .unwrap_or_default();
+            let source_id = source_ids.get(i).map(|s| s.to_string()).unwrap_or_default();
+            let source_catalog = source_catalogs
+                .get(i)
+                .map(|s| s.to_string())
+                .unwrap_or_default();
+            let lastupdate_ns = lastupdates.get(i).unwrap_or(0);
+            let author = authors.get(i).map(|s| s.to_string()).unwrap_or_default();
+
+            let time = chrono::DateTime::from_timestamp_nanos(time_ns);
+            let last_update = chrono::DateTime::from_timestamp_nanos(lastupdate_ns);
+
+            let event = SeismicEvent {
+                geometry: geo_types::Point::new(longitude, latitude),
+                source_id,
+                source_catalog,
+                last_update,
+                time,
+                latitude,
+                longitude,
+                depth,
+                event_type,
+                author,
+                magnitude,
+                magnitude_type,
+                flynn_region,
+                id,
+                origins: None,
+                arrivals: None,
+                coordinate_mismatch: false,
+                magnitude_missing: false,
+            };
+
+            events.push(event);
+        }
+
+        Ok(events)
+    }
+
+    /// Append a `seq` column numbering the rows of `df` in order, continuing
+    /// from the last ingested row
+    pub(crate) fn with_sequence(&self, mut df: DataFrame) -> Result<DataFrame, PolarsError> {
+        let count = df.height() as u64;
+        let start = self.next_seq.fetch_add(count, Ordering::Relaxed);
+        df.with_column(Series::new(
+            "seq".into(),
+            (start..start + count).collect::<Vec<_>>(),
+        ))?;
+        Ok(df)
+    }
+
     fn event_to_dataframe(&self, event: &SeismicEvent) -> Result<DataFrame, PolarsError> {
         let mut df = df! [
             "unid" => [event.id.as_str()],
@@ -479,6 +989,8 @@ impl IncrementalAnalytics {
             "source_catalog" => [event.source_catalog.as_str()],
             "lastupdate" => [event.last_update.timestamp_nanos_opt().unwrap_or(0)],
             "author" => [event.author.as_str()],
+            "nsta" => [event.best_origin().and_then(|origin| origin.number_of_stations)],
+            "gap" => [event.best_origin().and_then(|origin| origin.azimuthal_gap)],
         ]?;
 
         df = df
@@ -492,7 +1004,10 @@ impl IncrementalAnalytics {
         Ok(df)
     }
 
-    fn events_to_dataframe(&self, events: &[SeismicEvent]) -> Result<DataFrame, PolarsError> {
+    pub(crate) fn events_to_dataframe(
+        &self,
+        events: &[SeismicEvent],
+    ) -> Result<DataFrame, PolarsError> {
         let ids: Vec<&str> = events.iter().map(|e| e.id.as_str()).collect();
         let lats: Vec<f64> = events.iter().map(|e| e.latitude).collect();
         let lons: Vec<f64> = events.iter().map(|e| e.longitude).collect();
@@ -512,6 +1027,14 @@ impl IncrementalAnalytics {
             .map(|e| e.last_update.timestamp_nanos_opt().unwrap_or(0))
             .collect();
         let authors: Vec<&str> = events.iter().map(|e| e.author.as_str()).collect();
+        let station_counts: Vec<Option<i32>> = events
+            .iter()
+            .map(|e| e.best_origin().and_then(|origin| origin.number_of_stations))
+            .collect();
+        let gaps: Vec<Option<f64>> = events
+            .iter()
+            .map(|e| e.best_origin().and_then(|origin| origin.azimuthal_gap))
+            .collect();
 
         let mut df = df! [
             "unid" => ids,
@@ -527,6 +1050,8 @@ impl IncrementalAnalytics {
             "source_catalog" => source_catalogs,
             "lastupdate" => lastupdates,
             "author" => authors,
+            "nsta" => station_counts,
+            "gap" => gaps,
         ]?;
 
         df = df
@@ -553,6 +1078,37 @@ pub struct AnalyticsStats {
     pub data: serde_json::Value,
 }
 
+/// Description of a dataframe column for building generic query UIs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnInfo {
+    pub name: String,
+    pub dtype: String,
+    pub description: String,
+}
+
+/// Human-readable description of an EMSC column name
+pub fn column_description(name: &str) -> &'static str {
+    match name {
+        "unid" => "Unique EMSC event identifier",
+        "lat" => "Epicenter latitude in degrees",
+        "lon" => "Epicenter longitude in degrees",
+        "time" => "Origin time (UTC)",
+        "mag" => "Magnitude",
+        "magtype" => "Magnitude type (e.g. ml, mb, mw)",
+        "depth" => "Hypocenter depth in kilometers",
+        "evtype" => "Event type code (e.g. ke for known earthquake)",
+        "flynn_region" => "Named seismic region",
+        "source_id" => "Identifier assigned by the reporting source",
+        "source_catalog" => "Catalog the event was reported to",
+        "lastupdate" => "Time of the last revision (UTC)",
+        "author" => "Agency that authored the solution",
+        "nsta" => "Number of stations used by the best origin, if known",
+        "gap" => "Azimuthal gap of the best origin in degrees, if known",
+        "seq" => "Ingestion sequence number, increasing in the order events were stored",
+        _ => "",
+    }
+}
+
 impl AdvancedAnalytics {
     /// Convert to a serializable format for the frontend
     pub fn to_json(&self) -> Result<serde_json::Value, String> {
@@ -611,6 +1167,48 @@ mod tests {
         assert_eq!(analytics.get_mag_depth_pairs().len(), 0);
     }
 
+    #[test]
+    fn test_weighted_region_hotspots_favor_recent_activity() {
+        let analytics = IncrementalAnalytics::new();
+        let now = Utc::now();
+
+        for i in 0..10 {
+            let event = create_test_event_with_params(
+                &format!("old_{}", i),
+                3.0,
+                10.0,
+                35.0,
+                -120.0,
+                now - chrono::TimeDelta::days(730 + i),
+                "California",
+            );
+            analytics.add_event(&event).unwrap();
+        }
+        for i in 0..2 {
+            let event = create_test_event_with_params(
+                &format!("recent_{}", i),
+                3.0,
+                10.0,
+                38.0,
+                22.0,
+                now - chrono::TimeDelta::days(i),
+                "Greece",
+            );
+            analytics.add_event(&event).unwrap();
+        }
+
+        assert_eq!(analytics.get_region_hotspots()[0].0, "California");
+
+        let weighted = analytics.get_weighted_region_hotspots(7.0).unwrap();
+        assert_eq!(weighted[0].0, "Greece");
+        assert!(weighted[0].1 > 1.5 && weighted[0].1 <= 2.0);
+        assert!(weighted[1].1 < 1e-6);
+
+        // With a very long half-life the raw counts dominate again
+        let weighted = analytics.get_weighted_region_hotspots(1e6).unwrap();
+        assert_eq!(weighted[0].0, "California");
+    }
+
     #[test]
     fn test_analytics_processors() {
         let analytics = IncrementalAnalytics::new();
@@ -626,6 +1224,17 @@ mod tests {
         assert!(!analytics.get_mag_depth_pairs().is_empty());
     }
 
+    #[test]
+    fn test_analytics_names_match_processors() {
+        let analytics = IncrementalAnalytics::new();
+        let names: Vec<_> = analytics
+            .analytics_processors
+            .iter()
+            .map(|processor| processor.name())
+            .collect();
+        assert_eq!(names, ANALYTICS_NAMES);
+    }
+
     #[test]
     fn test_magnitude_distribution_analytics() {
         let processor = MagnitudeDistributionAnalytics::new();
@@ -765,6 +1374,252 @@ mod tests {
         assert!(!analytics.needs_full_recompute.load(Ordering::Relaxed));
     }
 
+    #[test]
+    fn test_concurrent_getters_recompute_once() {
+        let analytics = IncrementalAnalytics::new();
+
+        let events = vec![
+            create_test_event_with_params("1", 2.0, 10.0, 35.0, -120.0, Utc::now(), "California"),
+            create_test_event_with_params("2", 3.0, 15.0, 36.0, -121.0, Utc::now(), "Oregon"),
+        ];
+        analytics.add_events(&events).unwrap();
+
+        analytics
+            .needs_full_recompute
+            .store(true, Ordering::Relaxed);
+        let runs_before = analytics.recompute_runs.load(Ordering::Relaxed);
+
+        std::thread::scope(|scope| {
+            for _ in 0..16 {
+                scope.spawn(|| {
+                    analytics.get_count_by_date();
+                    analytics.get_mag_depth_pairs();
+                    analytics.get_region_hotspots();
+                    analytics.get_b_value();
+                });
+            }
+        });
+
+        assert_eq!(
+            analytics.recompute_runs.load(Ordering::Relaxed) - runs_before,
+            1
+        );
+        assert!(!analytics.needs_full_recompute.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_advanced_analytics_flushes_stale_processors() {
+        let analytics = IncrementalAnalytics::new();
+        let events = vec![
+            create_test_event_with_params("1", 2.0, 10.0, 35.0, -120.0, Utc::now(), "California"),
+            create_test_event_with_params("2", 3.0, 15.0, 36.0, -121.0, Utc::now(), "Oregon"),
+        ];
+        analytics.add_events(&events).unwrap();
+
+        analytics
+            .needs_full_recompute
+            .store(true, Ordering::Relaxed);
+        let runs_before = analytics.recompute_runs.load(Ordering::Relaxed);
+
+        analytics.get_advanced_analytics_with(10).unwrap();
+        assert_eq!(
+            analytics.recompute_runs.load(Ordering::Relaxed) - runs_before,
+            1
+        );
+        assert!(!analytics.needs_full_recompute.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_coordinate_clusters_consistent_after_recompute() {
+        let analytics = IncrementalAnalytics::new();
+
+        // Coordinates on and around the 0.25-degree midpoints between grid cells
+        let events = vec![
+            create_test_event_with_params("1", 2.0, 10.0, 35.25, -120.25, Utc::now(), "California"),
+            create_test_event_with_params(
+                "2",
+                2.0,
+                10.0,
+                35.2499,
+                -120.2501,
+                Utc::now(),
+                "California",
+            ),
+            create_test_event_with_params(
+                "3",
+                2.0,
+                10.0,
+                35.2501,
+                -120.2499,
+                Utc::now(),
+                "California",
+            ),
+            create_test_event_with_params("4", 2.0, 10.0, -35.25, 120.75, Utc::now(), "Australia"),
+            create_test_event_with_params(
+                "5",
+                2.0,
+                10.0,
+                -35.7499,
+                120.7501,
+                Utc::now(),
+                "Australia",
+            ),
+        ];
+
+        for event in &events {
+            analytics.add_event(event).unwrap();
+        }
+        let incremental = analytics.get_coordinate_clusters();
+
+        analytics.recompute_all().unwrap();
+        let recomputed = analytics.get_coordinate_clusters();
+
+        assert_eq!(incremental, recomputed);
+        let total: u32 = recomputed.iter().map(|(_, _, count)| count).sum();
+        assert_eq!(total, 5);
+    }
+
+    #[test]
+    fn test_recent_events_keep_newest_first_and_are_capped() {
+        let analytics = IncrementalAnalytics::new();
+        let base = Utc::now() - chrono::TimeDelta::days(1);
+        let event_at = |minutes: i64| {
+            create_test_event_with_params(
+                &format!("event_{:03}", minutes),
+                3.0,
+                10.0,
+                35.0,
+                -120.0,
+                base + chrono::TimeDelta::minutes(minutes),
+                "California",
+            )
+        };
+
+        // Arrive out of order, partly one by one and partly in batches
+        let total = RECENT_EVENTS_CAPACITY as i64 + 50;
+        let mut minutes: Vec<i64> = (0..total).collect();
+        minutes.reverse();
+        minutes.swap(3, 70);
+        let (single, batched) = minutes.split_at(40);
+        for &minute in single {
+            analytics.add_event(&event_at(minute)).unwrap();
+        }
+        let batch: Vec<_> = batched.iter().map(|&minute| event_at(minute)).collect();
+        analytics.add_events(&batch).unwrap();
+
+        let recent_ids = |count| -> Vec<String> {
+            analytics
+                .get_recent_events(count)
+                .into_iter()
+                .map(|event| event.id)
+                .collect()
+        };
+        let expected = |count: i64| -> Vec<String> {
+            (total - count..total)
+                .rev()
+                .map(|minute| format!("event_{:03}", minute))
+                .collect()
+        };
+        assert_eq!(recent_ids(5), expected(5));
+        assert_eq!(
+            recent_ids(usize::MAX),
+            expected(RECENT_EVENTS_CAPACITY as i64)
+        );
+
+        // An update replaces the buffered copy and the stored row instead of
+        // duplicating them
+        let mut updated = event_at(total - 1);
+        updated.magnitude = 6.0;
+        analytics.add_event(&updated).unwrap();
+        let newest = analytics.get_recent_events(2);
+        assert_eq!(newest[0].magnitude, 6.0);
+        assert_eq!(newest[1].id, expected(2)[1]);
+        let stored = analytics
+            .get_dataframe()
+            .filter(col("unid").eq(lit(updated.id.as_str())))
+            .collect()
+            .unwrap();
+        assert_eq!(stored.height(), 1);
+        assert_eq!(
+            stored.column("mag").unwrap().f64().unwrap().get(0),
+            Some(6.0)
+        );
+        assert_eq!(
+            analytics.get_dataframe().collect().unwrap().height(),
+            total as usize
+        );
+
+        // Cleanup rebuilds the buffer from the remaining rows
+        let cutoff = (base + chrono::TimeDelta::minutes(total - 3))
+            .timestamp_nanos_opt()
+            .unwrap();
+        analytics
+            .replace_dataframe_and_rebuild(
+                analytics
+                    .get_dataframe()
+                    .filter(col("time").lt(lit(cutoff))),
+            )
+            .unwrap();
+        assert_eq!(recent_ids(3), expected(6)[3..].to_vec());
+        assert_eq!(recent_ids(usize::MAX).len(), RECENT_EVENTS_CAPACITY);
+
+        // Rebuilding keeps the updated version
+        let mut updated = event_at(total - 4);
+        updated.magnitude = 5.5;
+        analytics.update_event(&updated).unwrap();
+        analytics.reset_cache().unwrap();
+        assert_eq!(analytics.get_recent_events(1)[0].magnitude, 5.5);
+
+        analytics.clear();
+        assert!(analytics.get_recent_events(10).is_empty());
+    }
+
+    #[test]
+    fn test_energy_grid_dominated_by_large_event() {
+        let analytics = IncrementalAnalytics::new();
+
+        let mut events: Vec<_> = (0..200)
+            .map(|i| {
+                create_test_event_with_params(
+                    &format!("small_{}", i),
+                    2.0,
+                    10.0,
+                    35.0,
+                    -120.0,
+                    Utc::now(),
+                    "California",
+                )
+            })
+            .collect();
+        events.push(create_test_event_with_params(
+            "large",
+            7.0,
+            30.0,
+            38.0,
+            142.0,
+            Utc::now(),
+            "Japan",
+        ));
+        for event in &events {
+            analytics.add_event(event).unwrap();
+        }
+
+        let incremental = analytics.get_energy_grid();
+        assert_eq!(incremental.len(), 2);
+        let strongest = incremental
+            .iter()
+            .max_by(|a, b| a.2.total_cmp(&b.2))
+            .unwrap();
+        assert_eq!((strongest.0, strongest.1), (38.0, 142.0));
+
+        analytics.recompute_all().unwrap();
+        let recomputed = analytics.get_energy_grid();
+        for (before, after) in incremental.iter().zip(&recomputed) {
+            assert_eq!((before.0, before.1), (after.0, after.1));
+            assert!((before.2 - after.2).abs() <= before.2 * 1e-9);
+        }
+    }
+
     #[test]
     fn test_clear_analytics() {
         let analytics = IncrementalAnalytics::new();
@@ -788,6 +1643,33 @@ mod tests {
         assert!(!analytics.needs_full_recompute.load(Ordering::Relaxed));
     }
 
+    #[test]
+    fn test_weekly_frequency_after_clear() {
+        let analytics = IncrementalAnalytics::new();
+        assert_eq!(analytics.get_weekly_frequency().len(), 7);
+
+        let events = vec![
+            create_test_event_with_params("1", 2.0, 10.0, 35.0, -120.0, Utc::now(), "California"),
+            create_test_event_with_params("2", 3.0, 15.0, 36.0, -121.0, Utc::now(), "Oregon"),
+        ];
+        analytics.add_events(&events).unwrap();
+
+        let total: u32 = analytics
+            .get_weekly_frequency()
+            .iter()
+            .map(|(_, c)| c)
+            .sum();
+        assert_eq!(total, 2);
+
+        analytics.clear();
+
+        let weekly = analytics.get_weekly_frequency();
+        assert_eq!(weekly.len(), 7);
+        assert_eq!(weekly[0].0, "Mon");
+        assert_eq!(weekly[6].0, "Sun");
+        assert!(weekly.iter().all(|(_, count)| *count == 0));
+    }
+
     #[test]
     fn test_get_dataframe() {
         let analytics = IncrementalAnalytics::new();
@@ -814,6 +1696,120 @@ mod tests {
         assert_eq!(lon_col.get(0), Some(-120.5));
     }
 
+    #[test]
+    fn test_recompute_waits_for_pending_processor_updates() {
+        let analytics = Arc::new(IncrementalAnalytics::new());
+        let recomputes = Arc::new(Mutex::new(Vec::new()));
+
+        // Try to recompute while a writer has stored its rows but not yet
+        // updated the processors; a recompute in that gap counts them twice
+        let (hook_analytics, hook_recomputes) = (analytics.clone(), recomputes.clone());
+        *analytics.write_hook.lock() = Some(Box::new(move || {
+            let analytics = hook_analytics.clone();
+            let (done, finished) = std::sync::mpsc::channel();
+            hook_recomputes.lock().push(std::thread::spawn(move || {
+                analytics.recompute_all().unwrap();
+                done.send(()).ok();
+            }));
+            finished
+                .recv_timeout(std::time::Duration::from_millis(200))
+                .ok();
+        }));
+
+        let base_time = DateTime::parse_from_rfc3339("2024-01-15T10:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let event = |i: i64| {
+            create_test_event_with_params(
+                &format!("event_{}", i),
+                3.0,
+                10.0,
+                35.0,
+                -120.0,
+                base_time + chrono::TimeDelta::minutes(i),
+                "California",
+            )
+        };
+        analytics.add_event(&event(0)).unwrap();
+        analytics.add_events(&[event(1), event(2)]).unwrap();
+
+        *analytics.write_hook.lock() = None;
+        for recompute in recomputes.lock().drain(..) {
+            recompute.join().unwrap();
+        }
+
+        assert!(!analytics.needs_recompute());
+        let counted: u32 = analytics
+            .get_count_by_date()
+            .iter()
+            .map(|(_, count)| count)
+            .sum();
+        assert_eq!(counted, 3);
+        assert_eq!(analytics.get_mag_depth_pairs().len(), 3);
+    }
+
+    #[test]
+    fn test_parallel_recompute_matches_sequential() {
+        let base_time = DateTime::parse_from_rfc3339("2024-01-15T10:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        // Distinct region counts, as hotspots with equal counts come in no
+        // particular order
+        let regions = [
+            "California",
+            "Alaska",
+            "Alaska",
+            "Japan",
+            "Japan",
+            "Japan",
+            "Chile",
+            "Chile",
+            "Chile",
+            "Chile",
+        ];
+        let events: Vec<SeismicEvent> = (0..5_000)
+            .map(|i| {
+                create_test_event_with_params(
+                    &format!("event_{}", i),
+                    1.0 + (i % 60) as f64 / 10.0,
+                    (i % 700) as f64,
+                    -60.0 + (i % 120) as f64,
+                    -180.0 + (i % 360) as f64,
+                    base_time + chrono::TimeDelta::minutes(i * 37),
+                    regions[i as usize % regions.len()],
+                )
+            })
+            .collect();
+
+        let snapshot = |analytics: &IncrementalAnalytics| {
+            analytics.recompute_all().unwrap();
+            serde_json::json!({
+                "magnitude_distribution": analytics.get_magnitude_distribution().unwrap(),
+                "count_by_date": analytics.get_count_by_date(),
+                "mag_depth_pairs": analytics.get_mag_depth_pairs(),
+                "hourly_frequency": analytics.get_hourly_frequency(),
+                "region_hotspots": analytics.get_region_hotspots(),
+                "b_value": analytics.get_b_value(),
+                "magnitude_frequency": analytics.get_magnitude_frequency_data(),
+                "total_energy": analytics.get_total_energy(),
+                "magnitude_quantiles": analytics.get_magnitude_quantiles(),
+            })
+        };
+
+        let parallel = IncrementalAnalytics::new();
+        parallel.add_events(&events).unwrap();
+        assert!(parallel.parallel_recompute());
+
+        let sequential = IncrementalAnalytics::new();
+        sequential.set_parallel_recompute(false);
+        sequential.add_events(&events).unwrap();
+
+        assert_eq!(snapshot(&parallel), snapshot(&sequential));
+        // The setting survives rebuilding
+        let rebuilt = sequential.rebuilt_from(sequential.get_dataframe()).unwrap();
+        assert!(!rebuilt.parallel_recompute());
+    }
+
     #[test]
     fn test_all_analytics_methods() {
         let analytics = IncrementalAnalytics::new();
@@ -921,7 +1917,7 @@ mod tests {
 
         let advanced_analytics = analytics.get_advanced_analytics().unwrap();
 
-        assert!(advanced_analytics.stats.len() >= 6); // 6 processors + regional analysis
+        assert!(advanced_analytics.stats.len() >= 7); // 7 processors + regional analysis
 
         for stat in &advanced_analytics.stats {
             assert!(!stat.title.is_empty());
@@ -942,6 +1938,37 @@ mod tests {
         assert!(titles.contains(&"Regional Analysis"));
     }
 
+    #[test]
+    fn test_advanced_analytics_region_limit() {
+        let analytics = IncrementalAnalytics::new();
+
+        let events: Vec<_> = ["Alaska", "Chile", "Greece", "Italy", "Japan"]
+            .iter()
+            .enumerate()
+            .map(|(index, region)| {
+                create_test_event_with_params(
+                    &index.to_string(),
+                    3.0,
+                    10.0,
+                    35.0,
+                    -120.0,
+                    Utc::now(),
+                    region,
+                )
+            })
+            .collect();
+        analytics.add_events(&events).unwrap();
+
+        let advanced_analytics = analytics.get_advanced_analytics_with(3).unwrap();
+
+        let regional = advanced_analytics
+            .stats
+            .iter()
+            .find(|stat| stat.title == "Regional Analysis")
+            .unwrap();
+        assert_eq!(regional.data.as_array().unwrap().len(), 3);
+    }
+
     #[test]
     fn test_advanced_analytics_to_json() {
         let analytics = IncrementalAnalytics::new();
@@ -1169,6 +2196,35 @@ mod tests {
         assert!(obj.contains_key("stats"));
     }
 
+    #[test]
+    fn test_analytics_payload_keys_are_snake_case() {
+        let analytics = IncrementalAnalytics::new();
+        analytics.add_event(&SeismicEvent::test_event()).unwrap();
+
+        let json = analytics
+            .get_advanced_analytics()
+            .unwrap()
+            .to_json()
+            .unwrap();
+        let keys: Vec<&String> = json.as_object().unwrap().keys().collect();
+        assert_eq!(keys, vec!["stats"]);
+        for stat in json["stats"].as_array().unwrap() {
+            let mut keys: Vec<&String> = stat.as_object().unwrap().keys().collect();
+            keys.sort();
+            assert_eq!(keys, vec!["data", "title"]);
+        }
+
+        let column = serde_json::to_value(ColumnInfo {
+            name: "mag".to_string(),
+            dtype: "f64".to_string(),
+            description: column_description("mag").to_string(),
+        })
+        .unwrap();
+        let mut keys: Vec<&String> = column.as_object().unwrap().keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["description", "dtype", "name"]);
+    }
+
     #[test]
     fn test_concurrent_access() {
         use std::sync::Arc;
@@ -1245,4 +2301,33 @@ mod tests {
         let advanced = analytics.get_advanced_analytics().unwrap();
         assert!(!advanced.stats.is_empty());
     }
+
+    #[test]
+    fn test_schema_info() {
+        let analytics = IncrementalAnalytics::new();
+
+        let schema = analytics.schema_info();
+
+        assert_eq!(schema.len(), 16);
+        let dtype_of = |name: &str| {
+            schema
+                .iter()
+                .find(|(column, _)| column == name)
+                .map(|(_, dtype)| dtype.as_str())
+                .unwrap_or_else(|| panic!("missing column {name}"))
+        };
+        assert_eq!(dtype_of("unid"), "str");
+        assert_eq!(dtype_of("mag"), "f64");
+        assert_eq!(dtype_of("lat"), "f64");
+        assert!(dtype_of("time").starts_with("datetime"));
+        assert!(dtype_of("lastupdate").starts_with("datetime"));
+        assert_eq!(dtype_of("nsta"), "i32");
+        assert_eq!(dtype_of("gap"), "f64");
+        assert_eq!(dtype_of("seq"), "u64");
+
+        for (name, _) in &schema {
+            assert!(!column_description(name).is_empty(), "{name} undocumented");
+        }
+        assert_eq!(column_description("flynn_region"), "Named seismic region");
+    }
 }
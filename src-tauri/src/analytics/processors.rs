@@ -5,9 +5,39 @@ use chrono::{Datelike, NaiveDate, Timelike, Weekday};
 use itertools::Itertools;
 use parking_lot::RwLock;
 use polars::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::seismic::SeismicEvent;
 
+const DAYS_PER_YEAR: f64 = 365.25;
+
+/// Slack in tenths of a magnitude unit absorbing floating point error, so a
+/// computed 0.7 + 0.1 still lands in the 0.8 bucket
+const MAGNITUDE_KEY_EPSILON: f64 = 1e-6;
+
+/// Key of the magnitude bucket containing `magnitude`, in tenths of a
+/// magnitude unit, for buckets `width` tenths wide
+///
+/// Every processor keys magnitudes this way. Magnitudes are floored to the
+/// lower edge of their bucket, so with `width` 1 both 2.05 and 2.099 map to
+/// 20 (M2.0) while 2.1 maps to 21. Negative magnitudes share the lowest
+/// bucket, 0.
+pub(crate) fn mag_to_key(magnitude: f64, width: u32) -> u32 {
+    let tenths = (magnitude * 10.0 + MAGNITUDE_KEY_EPSILON).floor().max(0.0) as u32;
+    tenths - tenths % width.max(1)
+}
+
+/// `value` if finite, otherwise `fallback`. NaN and infinity serialize to
+/// invalid JSON, so degenerate results are logged and replaced.
+fn finite_or(value: f64, fallback: f64, what: &str) -> f64 {
+    if value.is_finite() {
+        value
+    } else {
+        log::warn!("Degenerate {} ({}), using {}", what, value, fallback);
+        fallback
+    }
+}
+
 /// Trait for analytics that can be incrementally updated
 pub trait AnalyticsProcessor: Send + Sync {
     /// Get the name/identifier for this analytics processor
@@ -38,15 +68,90 @@ pub trait AnalyticsProcessor: Send + Sync {
 /// - Distribution shape (exponential, normal, etc.)
 pub struct MagnitudeDistributionAnalytics {
     buckets: Arc<RwLock<HashMap<u32, u32>>>,
+    /// Current bucket width in tenths of a magnitude unit
+    bin_width: Arc<RwLock<u32>>,
+    /// Whether `recompute` sizes buckets with the Freedman-Diaconis rule
+    auto_bins: Arc<RwLock<bool>>,
 }
 
 impl MagnitudeDistributionAnalytics {
+    /// Fixed bucket width in tenths of a magnitude unit
+    const DEFAULT_BIN_WIDTH: u32 = 2;
+    /// Samples below which automatic bin sizing falls back to the fixed width
+    const MIN_AUTO_BIN_SAMPLES: usize = 30;
+
     pub fn new() -> Self {
         Self {
             buckets: Arc::new(RwLock::new(HashMap::new())),
+            bin_width: Arc::new(RwLock::new(Self::DEFAULT_BIN_WIDTH)),
+            auto_bins: Arc::new(RwLock::new(false)),
         }
     }
 
+    /// Current bucket width in magnitude units
+    pub fn bin_width(&self) -> f64 {
+        *self.bin_width.read() as f64 / 10.0
+    }
+
+    /// Whether buckets are sized with the Freedman-Diaconis rule
+    pub fn auto_bins(&self) -> bool {
+        *self.auto_bins.read()
+    }
+
+    /// Enable or disable Freedman-Diaconis bin sizing on the next recompute.
+    /// Disabling restores the fixed 0.2 width.
+    pub fn set_auto_bins(&self, enabled: bool) {
+        *self.auto_bins.write() = enabled;
+        if !enabled {
+            *self.bin_width.write() = Self::DEFAULT_BIN_WIDTH;
+        }
+    }
+
+    /// Rebuild the buckets with a width chosen by the Freedman-Diaconis rule,
+    /// `2 * IQR / n^(1/3)`, rounded up to a multiple of 0.1
+    ///
+    /// Samples smaller than `MIN_AUTO_BIN_SAMPLES` or without spread keep the
+    /// fixed 0.2 width.
+    pub fn recompute_auto_bins(&self, dataframe: &LazyFrame) -> Result<(), PolarsError> {
+        let result = dataframe.clone().select([col("mag")]).collect()?;
+        let mut magnitudes: Vec<f64> = result.column("mag")?.f64()?.into_no_null_iter().collect();
+        magnitudes.sort_by(f64::total_cmp);
+
+        let width = Self::freedman_diaconis_width(&magnitudes).unwrap_or(Self::DEFAULT_BIN_WIDTH);
+        *self.bin_width.write() = width;
+        *self.buckets.write() = Self::bucket_counts(magnitudes, width);
+        Ok(())
+    }
+
+    /// Freedman-Diaconis width in tenths for sorted magnitudes
+    fn freedman_diaconis_width(sorted: &[f64]) -> Option<u32> {
+        if sorted.len() < Self::MIN_AUTO_BIN_SAMPLES {
+            return None;
+        }
+
+        let quantile = |q: f64| {
+            let position = q * (sorted.len() - 1) as f64;
+            let lower = position.floor() as usize;
+            let upper = position.ceil() as usize;
+            sorted[lower] + (sorted[upper] - sorted[lower]) * (position - lower as f64)
+        };
+        let iqr = quantile(0.75) - quantile(0.25);
+        if iqr <= 0.0 {
+            return None;
+        }
+
+        let width = 2.0 * iqr / (sorted.len() as f64).cbrt();
+        Some(((width * 10.0).ceil() as u32).max(1))
+    }
+
+    fn bucket_counts(magnitudes: impl IntoIterator<Item = f64>, width: u32) -> HashMap<u32, u32> {
+        let mut buckets = HashMap::new();
+        for magnitude in magnitudes {
+            *buckets.entry(mag_to_key(magnitude, width)).or_insert(0) += 1;
+        }
+        buckets
+    }
+
     pub fn get_result(&self) -> Result<Vec<(String, u32)>, String> {
         let buckets = self.buckets.read();
         let mut result: Vec<_> = buckets
@@ -70,6 +175,19 @@ impl MagnitudeDistributionAnalytics {
 
         Ok(result)
     }
+
+    /// Like `get_result`, but drops bins starting below `min_magnitude`,
+    /// typically the magnitude of completeness
+    pub fn get_result_above(&self, min_magnitude: f64) -> Result<Vec<(String, u32)>, String> {
+        let mut result = self.get_result()?;
+        // Bin labels come from f32, allow for their rounding
+        result.retain(|(bucket, _)| {
+            bucket
+                .parse::<f64>()
+                .is_ok_and(|start| start >= min_magnitude - 1e-6)
+        });
+        Ok(result)
+    }
 }
 
 impl AnalyticsProcessor for MagnitudeDistributionAnalytics {
@@ -78,26 +196,22 @@ impl AnalyticsProcessor for MagnitudeDistributionAnalytics {
     }
 
     fn update(&self, event: &SeismicEvent) -> Result<(), PolarsError> {
-        let bucket = ((event.magnitude * 10.0) as u32) - (((event.magnitude * 10.0) as u32) % 2);
+        let bucket = mag_to_key(event.magnitude, *self.bin_width.read());
         let mut buckets = self.buckets.write();
         *buckets.entry(bucket).or_insert(0) += 1;
         Ok(())
     }
 
     fn recompute(&self, dataframe: &LazyFrame) -> Result<(), PolarsError> {
-        let result = dataframe.clone().select([col("mag")]).collect()?;
+        if *self.auto_bins.read() {
+            return self.recompute_auto_bins(dataframe);
+        }
 
+        let result = dataframe.clone().select([col("mag")]).collect()?;
         let magnitudes = result.column("mag")?.f64()?;
-        let mut buckets = HashMap::new();
-
-        for mag_opt in magnitudes.iter() {
-            if let Some(mag) = mag_opt {
-                let bucket = ((mag * 10.0) as u32) - (((mag * 10.0) as u32) % 2);
-                *buckets.entry(bucket).or_insert(0) += 1;
-            }
-        }
 
-        *self.buckets.write() = buckets;
+        *self.buckets.write() =
+            Self::bucket_counts(magnitudes.into_no_null_iter(), *self.bin_width.read());
         Ok(())
     }
 
@@ -190,21 +304,23 @@ impl TemporalPatternsAnalytics {
     }
 
     /// Get weekly distribution with weekday names
+    ///
+    /// Always returns all 7 weekdays in Monday-first order, with zero counts
+    /// for days without events. This also holds when no data has been added
+    /// or after `clear`, so callers can rely on a fixed-length result.
     pub fn get_weekly_distribution(&self) -> Vec<(String, u32)> {
-        use chrono::Weekday;
         let counts = self.weekly_counts.read();
-        
-        // Always return all 7 weekdays, even if some have zero counts
+
         let all_weekdays = [
             Weekday::Mon,
-            Weekday::Tue, 
+            Weekday::Tue,
             Weekday::Wed,
             Weekday::Thu,
             Weekday::Fri,
             Weekday::Sat,
             Weekday::Sun,
         ];
-        
+
         all_weekdays
             .iter()
             .map(|weekday| {
@@ -390,7 +506,11 @@ impl AnalyticsProcessor for MagnitudeDepthAnalytics {
 ///
 /// 2. **Coordinate clustering**: Groups earthquakes into a 0.5-degree grid to
 ///    create spatial clusters for mapping and visualization. This helps
-///    identify hotspots that may not align with named regions.
+///    identify hotspots that may not align with named regions. See
+///    [`GridBinning`] for keeping cells roughly equal in area near the poles.
+///
+/// 3. **Energy release**: Sums the seismic energy released in each grid cell,
+///    so a single large event outweighs a dense cluster of small ones.
 ///
 /// Applications include:
 /// - Risk assessment for populated areas
@@ -399,17 +519,77 @@ impl AnalyticsProcessor for MagnitudeDepthAnalytics {
 /// - Scientific research on fault systems
 pub struct GeographicHotspotsAnalytics {
     region_counts: Arc<RwLock<HashMap<String, u32>>>,
-    coordinate_clusters: Arc<RwLock<Vec<(f64, f64, u32)>>>, // lat, lon, count
+    coordinate_clusters: Arc<RwLock<HashMap<(i32, i32), u32>>>, // grid key -> count
+    cell_energy: Arc<RwLock<HashMap<(i32, i32), f64>>>,         // grid key -> Joules
+    binning: Arc<RwLock<GridBinning>>,
+}
+
+/// How coordinates are assigned to grid cells for clustering and energy
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GridBinning {
+    /// Fixed 0.5 x 0.5 degree cells, which shrink towards the poles
+    #[default]
+    Regular,
+    /// 0.5-degree latitude rows whose longitude width grows with
+    /// `1 / cos(latitude)`, keeping cells close to equal in area
+    EqualArea,
+}
+
+impl GridBinning {
+    /// Grid keys per degree of latitude
+    const KEYS_PER_DEGREE: f64 = 2.0;
+
+    /// Grid keys per degree of longitude in the row `lat_key`. Equal-area
+    /// rows at the poles collapse to a single cell centered on longitude 0:
+    /// the floor keeps `|lon| * k <= 0.25`, so every longitude rounds to key 0.
+    fn lon_keys_per_degree(self, lat_key: i32) -> f64 {
+        match self {
+            GridBinning::Regular => Self::KEYS_PER_DEGREE,
+            GridBinning::EqualArea => {
+                let row_lat = (lat_key as f64 / Self::KEYS_PER_DEGREE).to_radians();
+                (Self::KEYS_PER_DEGREE * row_lat.cos()).max(1.0 / 720.0)
+            }
+        }
+    }
+
+    /// Map a coordinate onto the grid key shared by `update` and `recompute`,
+    /// so both paths assign boundary events to the same cell
+    fn key(self, lat: f64, lon: f64) -> (i32, i32) {
+        let lat_key = (lat * Self::KEYS_PER_DEGREE).round() as i32;
+        let lon_key = (lon * self.lon_keys_per_degree(lat_key)).round() as i32;
+        (lat_key, lon_key)
+    }
+
+    /// (latitude, longitude) of the center of the cell `key`
+    fn center(self, (lat_key, lon_key): (i32, i32)) -> (f64, f64) {
+        (
+            lat_key as f64 / Self::KEYS_PER_DEGREE,
+            lon_key as f64 / self.lon_keys_per_degree(lat_key),
+        )
+    }
 }
 
 impl GeographicHotspotsAnalytics {
     pub fn new() -> Self {
         Self {
             region_counts: Arc::new(RwLock::new(HashMap::new())),
-            coordinate_clusters: Arc::new(RwLock::new(Vec::new())),
+            coordinate_clusters: Arc::new(RwLock::new(HashMap::new())),
+            cell_energy: Arc::new(RwLock::new(HashMap::new())),
+            binning: Arc::new(RwLock::new(GridBinning::default())),
         }
     }
 
+    pub fn binning(&self) -> GridBinning {
+        *self.binning.read()
+    }
+
+    /// Switch the grid used for clusters and energy. Existing cells keep
+    /// their old keys, so callers must recompute afterwards.
+    pub fn set_binning(&self, binning: GridBinning) {
+        *self.binning.write() = binning;
+    }
+
     pub fn get_region_hotspots(&self) -> Vec<(String, u32)> {
         let counts = self.region_counts.read();
         let mut result: Vec<_> = counts
@@ -421,7 +601,33 @@ impl GeographicHotspotsAnalytics {
     }
 
     pub fn get_coordinate_clusters(&self) -> Vec<(f64, f64, u32)> {
-        self.coordinate_clusters.read().clone()
+        let binning = self.binning();
+        let clusters = self.coordinate_clusters.read();
+        let mut result: Vec<_> = clusters
+            .iter()
+            .map(|(&key, &count)| {
+                let (lat, lon) = binning.center(key);
+                (lat, lon, count)
+            })
+            .collect();
+        result.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        result
+    }
+
+    /// (latitude, longitude, Joules) of the energy released per grid cell,
+    /// using the same cells as `get_coordinate_clusters`
+    pub fn get_energy_grid(&self) -> Vec<(f64, f64, f64)> {
+        let binning = self.binning();
+        let energy = self.cell_energy.read();
+        let mut result: Vec<_> = energy
+            .iter()
+            .map(|(&key, &joules)| {
+                let (lat, lon) = binning.center(key);
+                (lat, lon, joules)
+            })
+            .collect();
+        result.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        result
     }
 }
 
@@ -436,18 +642,15 @@ impl AnalyticsProcessor for GeographicHotspotsAnalytics {
             *regions.entry(event.flynn_region.clone()).or_insert(0) += 1;
         }
 
-        let lat_cluster = (event.latitude * 2.0).round() / 2.0;
-        let lon_cluster = (event.longitude * 2.0).round() / 2.0;
-
+        let key = self.binning().key(event.latitude, event.longitude);
         {
             let mut clusters = self.coordinate_clusters.write();
-            if let Some(existing) = clusters.iter_mut().find(|(lat, lon, _)| {
-                (*lat - lat_cluster).abs() < 0.01 && (*lon - lon_cluster).abs() < 0.01
-            }) {
-                existing.2 += 1;
-            } else {
-                clusters.push((lat_cluster, lon_cluster, 1));
-            }
+            *clusters.entry(key).or_insert(0) += 1;
+        }
+        {
+            let mut energy = self.cell_energy.write();
+            *energy.entry(key).or_insert(0.0) +=
+                RiskAssessmentAnalytics::magnitude_to_energy(event.magnitude);
         }
 
         Ok(())
@@ -456,33 +659,41 @@ impl AnalyticsProcessor for GeographicHotspotsAnalytics {
     fn recompute(&self, dataframe: &LazyFrame) -> Result<(), PolarsError> {
         let result = dataframe
             .clone()
-            .select([col("flynn_region"), col("lat"), col("lon")])
+            .select([col("flynn_region"), col("lat"), col("lon"), col("mag")])
             .collect()?;
 
         let regions = result.column("flynn_region")?.str()?;
         let lats = result.column("lat")?.f64()?;
         let lons = result.column("lon")?.f64()?;
+        let mags = result.column("mag")?.f64()?;
 
+        let binning = self.binning();
         let mut region_counts = HashMap::new();
         let mut coordinate_clusters: HashMap<(i32, i32), u32> = HashMap::new();
+        let mut cell_energy: HashMap<(i32, i32), f64> = HashMap::new();
 
-        for ((region_opt, lat_opt), lon_opt) in regions.iter().zip(lats.iter()).zip(lons.iter()) {
+        for (((region_opt, lat_opt), lon_opt), mag_opt) in regions
+            .iter()
+            .zip(lats.iter())
+            .zip(lons.iter())
+            .zip(mags.iter())
+        {
             if let (Some(region), Some(lat), Some(lon)) = (region_opt, lat_opt, lon_opt) {
                 *region_counts.entry(region.to_string()).or_insert(0) += 1;
 
-                let lat_key = (lat * 2.0).round() as i32;
-                let lon_key = (lon * 2.0).round() as i32;
-                *coordinate_clusters.entry((lat_key, lon_key)).or_insert(0) += 1;
+                let key = binning.key(lat, lon);
+                *coordinate_clusters.entry(key).or_insert(0) += 1;
+
+                if let Some(mag) = mag_opt {
+                    *cell_energy.entry(key).or_insert(0.0) +=
+                        RiskAssessmentAnalytics::magnitude_to_energy(mag);
+                }
             }
         }
 
         *self.region_counts.write() = region_counts;
-
-        let clusters: Vec<(f64, f64, u32)> = coordinate_clusters
-            .into_iter()
-            .map(|((lat_key, lon_key), count)| (lat_key as f64 / 2.0, lon_key as f64 / 2.0, count))
-            .collect();
-        *self.coordinate_clusters.write() = clusters;
+        *self.coordinate_clusters.write() = coordinate_clusters;
+        *self.cell_energy.write() = cell_energy;
 
         Ok(())
     }
@@ -490,6 +701,7 @@ impl AnalyticsProcessor for GeographicHotspotsAnalytics {
     fn clear(&self) {
         self.region_counts.write().clear();
         self.coordinate_clusters.write().clear();
+        self.cell_energy.write().clear();
     }
 
     fn get_auxiliary_stats(&self, dataframe: &LazyFrame) -> LazyFrame {
@@ -559,29 +771,101 @@ impl GutenbergRichterAnalytics {
         *self.completeness_magnitude.read()
     }
 
-    pub fn get_magnitude_frequency_data(&self) -> Vec<(f64, u32, u32)> {
+    /// Expected number of events per year at or above magnitude `m`, reading
+    /// `10^(a - b*m)` as the count over a catalog spanning `span_days`
+    pub fn annual_rate_above(&self, m: f64, span_days: f64) -> f64 {
+        if span_days <= 0.0 {
+            return 0.0;
+        }
+        let catalog_count = 10_f64.powf(self.get_a_value() - self.get_b_value() * m);
+        finite_or(
+            catalog_count * DAYS_PER_YEAR / span_days,
+            0.0,
+            "annual rate",
+        )
+    }
+
+    /// Magnitude expected to be reached once within `years`, reading
+    /// `10^(a - b*m)` as the count over a catalog spanning `span_days` and
+    /// solving for an expected count of one over the horizon
+    pub fn expected_max_magnitude(&self, years: f64, span_days: f64) -> f64 {
+        let b_value = self.get_b_value();
+        if years <= 0.0 || span_days <= 0.0 || b_value <= 0.0 {
+            return 0.0;
+        }
+        let horizon_scale = years * DAYS_PER_YEAR / span_days;
+        finite_or(
+            (self.get_a_value() + horizon_scale.log10()) / b_value,
+            0.0,
+            "expected maximum magnitude",
+        )
+    }
+
+    /// Standard error of the b-value after Shi & Bolt (1982), using the
+    /// events at or above the completeness magnitude
+    pub fn get_b_value_uncertainty(&self) -> f64 {
         let counts = self.magnitude_counts.read();
-        let mut result = Vec::new();
+        let completeness_key = mag_to_key(*self.completeness_magnitude.read(), 1);
+        let complete: Vec<(f64, f64)> = counts
+            .iter()
+            .filter(|(&mag_key, _)| mag_key >= completeness_key)
+            .map(|(&mag_key, &count)| (mag_key as f64 / 10.0, count as f64))
+            .collect();
 
-        let mut sorted_mags: Vec<_> = counts.keys().collect();
-        sorted_mags.sort();
+        let n: f64 = complete.iter().map(|(_, count)| count).sum();
+        if n < 2.0 {
+            return 0.0;
+        }
 
-        for &mag_key in &sorted_mags {
-            let magnitude = *mag_key as f64 / 10.0;
-            let count = *counts.get(mag_key).unwrap_or(&0);
+        let mean = complete.iter().map(|(m, count)| m * count).sum::<f64>() / n;
+        let squared_deviations: f64 = complete
+            .iter()
+            .map(|(m, count)| count * (m - mean).powi(2))
+            .sum();
+        let b_value = self.get_b_value();
 
-            let cumulative_count: u32 = sorted_mags
-                .iter()
-                .filter(|&&m| m >= mag_key)
-                .map(|&m| counts.get(m).unwrap_or(&0))
-                .sum();
+        finite_or(
+            2.3 * b_value * b_value * (squared_deviations / (n * (n - 1.0))).sqrt(),
+            0.0,
+            "b-value uncertainty",
+        )
+    }
 
-            result.push((magnitude, count, cumulative_count));
-        }
+    /// `(magnitude, count, cumulative count)` per magnitude bin from low to
+    /// high, where the cumulative count includes every event at or above the
+    /// bin's magnitude
+    pub fn get_magnitude_frequency_data(&self) -> Vec<(f64, u32, u32)> {
+        let counts = self.magnitude_counts.read();
+        let mut bins: Vec<(u32, u32)> = counts.iter().map(|(&key, &count)| (key, count)).collect();
+        bins.sort_unstable_by_key(|&(key, _)| key);
 
+        // Sum from the largest magnitude down so each bin is visited once
+        let mut cumulative_count = 0;
+        let mut result: Vec<(f64, u32, u32)> = bins
+            .into_iter()
+            .rev()
+            .map(|(mag_key, count)| {
+                cumulative_count += count;
+                (mag_key as f64 / 10.0, count, cumulative_count)
+            })
+            .collect();
+        result.reverse();
         result
     }
 
+    /// (magnitude, log10 of cumulative count) pairs ready to plot against the
+    /// fitted Gutenberg-Richter line, skipping magnitudes with no events at or
+    /// above them
+    pub fn get_magnitude_frequency_loglog(&self) -> Vec<(f64, f64)> {
+        self.get_magnitude_frequency_data()
+            .into_iter()
+            .filter(|&(_, _, cumulative)| cumulative > 0)
+            .map(|(magnitude, _, cumulative)| (magnitude, (cumulative as f64).log10()))
+            .collect()
+    }
+
+    /// Least-squares fit of `log10 N(>= M) = a - b*M` over the magnitudes at
+    /// or above the completeness magnitude
     fn calculate_b_value(&self) {
         let counts = self.magnitude_counts.read();
         if counts.len() < 3 {
@@ -589,17 +873,26 @@ impl GutenbergRichterAnalytics {
         }
 
         let completeness_mag = *self.completeness_magnitude.read();
-        let completeness_key = (completeness_mag * 10.0) as u32;
+        let completeness_key = mag_to_key(completeness_mag, 1);
 
-        let valid_data: Vec<(f64, f64)> = counts
+        let mut keys: Vec<u32> = counts
             .iter()
             .filter(|(&mag_key, &count)| mag_key >= completeness_key && count > 0)
-            .map(|(&mag_key, &count)| {
-                let magnitude = mag_key as f64 / 10.0;
-                let log_count = (count as f64).ln();
-                (magnitude, log_count)
+            .map(|(&mag_key, _)| mag_key)
+            .collect();
+        keys.sort_unstable();
+
+        // Accumulate from the largest magnitude down to get N(>= M)
+        let mut cumulative = 0u32;
+        let mut valid_data: Vec<(f64, f64)> = keys
+            .iter()
+            .rev()
+            .map(|mag_key| {
+                cumulative += counts[mag_key];
+                (*mag_key as f64 / 10.0, (cumulative as f64).log10())
             })
             .collect();
+        valid_data.reverse();
 
         if valid_data.len() < 3 {
             return;
@@ -611,10 +904,26 @@ impl GutenbergRichterAnalytics {
         let sum_m_log_n: f64 = valid_data.iter().map(|(m, log_n)| m * log_n).sum();
         let sum_m_squared: f64 = valid_data.iter().map(|(m, _)| m * m).sum();
 
-        let b_value = (n * sum_m_log_n - sum_m * sum_log_n) / (sum_m * sum_m - n * sum_m_squared);
-        let a_value = (sum_log_n - b_value * sum_m) / n;
+        // Zero only when every magnitude is the same, where no line fits
+        let denominator = n * sum_m_squared - sum_m * sum_m;
+        if denominator.abs() < f64::EPSILON * n * sum_m_squared {
+            log::warn!("Skipping b-value fit, magnitudes have no spread");
+            return;
+        }
 
-        *self.b_value.write() = -b_value; // Negative because of the relationship
+        let slope = (n * sum_m_log_n - sum_m * sum_log_n) / denominator;
+        let a_value = (sum_log_n - slope * sum_m) / n;
+        if !slope.is_finite() || !a_value.is_finite() {
+            log::warn!(
+                "Skipping non-finite b-value fit (b = {}, a = {})",
+                -slope,
+                a_value
+            );
+            return;
+        }
+
+        // Counts fall with magnitude, so the fitted slope is -b
+        *self.b_value.write() = -slope;
         *self.a_value.write() = a_value;
     }
 }
@@ -625,7 +934,7 @@ impl AnalyticsProcessor for GutenbergRichterAnalytics {
     }
 
     fn update(&self, event: &SeismicEvent) -> Result<(), PolarsError> {
-        let mag_key = (event.magnitude * 10.0) as u32;
+        let mag_key = mag_to_key(event.magnitude, 1);
         {
             let mut counts = self.magnitude_counts.write();
             *counts.entry(mag_key).or_insert(0) += 1;
@@ -646,7 +955,7 @@ impl AnalyticsProcessor for GutenbergRichterAnalytics {
 
         for mag_opt in magnitudes.iter() {
             if let Some(mag) = mag_opt {
-                let mag_key = (mag * 10.0) as u32;
+                let mag_key = mag_to_key(mag, 1);
                 *magnitude_counts.entry(mag_key).or_insert(0) += 1;
             }
         }
@@ -712,23 +1021,67 @@ pub struct RiskAssessmentAnalytics {
     time_span_days: Arc<RwLock<f64>>,
     magnitude_counts: Arc<RwLock<HashMap<u32, u32>>>,
     total_energy_joules: Arc<RwLock<f64>>,
+    /// Energy for every 0.1 magnitude bin from 0.0 up to
+    /// `MAX_TABLE_MAGNITUDE_BIN`
+    energy_table: Vec<f64>,
+    /// Longest forecast horizon accepted by `probability_magnitude_in_days`
+    max_horizon_days: Arc<RwLock<f64>>,
 }
 
 impl RiskAssessmentAnalytics {
+    /// Highest magnitude bin (in tenths) covered by the energy lookup table
+    const MAX_TABLE_MAGNITUDE_BIN: u32 = 100;
+    /// Default forecast horizon limit (10 years)
+    pub const DEFAULT_MAX_HORIZON_DAYS: f64 = 3650.0;
+    /// Energy released by one ton of TNT in Joules
+    pub const JOULES_PER_TON_TNT: f64 = 4.184e9;
+    /// Energy units from smallest to largest, each 1000 times the previous
+    const ENERGY_UNITS: [&'static str; 6] = ["J", "kJ", "MJ", "GJ", "TJ", "PJ"];
+
     pub fn new() -> Self {
+        let energy_table = (0..=Self::MAX_TABLE_MAGNITUDE_BIN)
+            .map(|bin| Self::magnitude_to_energy(bin as f64 / 10.0))
+            .collect();
+
         Self {
             total_events: Arc::new(RwLock::new(0)),
             time_span_days: Arc::new(RwLock::new(1.0)),
             magnitude_counts: Arc::new(RwLock::new(HashMap::new())),
             total_energy_joules: Arc::new(RwLock::new(0.0)),
+            energy_table,
+            max_horizon_days: Arc::new(RwLock::new(Self::DEFAULT_MAX_HORIZON_DAYS)),
         }
     }
 
+    /// Longest forecast horizon in days, see `probability_magnitude_in_days`
+    pub fn max_horizon_days(&self) -> f64 {
+        *self.max_horizon_days.read()
+    }
+
+    /// Set the longest forecast horizon in days. Callers make sure it is
+    /// positive and finite.
+    pub fn set_max_horizon_days(&self, days: f64) {
+        *self.max_horizon_days.write() = days;
+    }
+
     /// Calculate probability of magnitude >= threshold in next N days
+    ///
+    /// `days` is clamped to `[0, max_horizon_days]`: beyond a few years the
+    /// stationary-rate assumption breaks down and every probability rounds to
+    /// 1.0, so longer horizons are answered as if the maximum had been asked.
     pub fn probability_magnitude_in_days(&self, magnitude_threshold: f64, days: f64) -> f64 {
+        if !magnitude_threshold.is_finite() || days.is_nan() {
+            log::warn!(
+                "Invalid probability query (magnitude {}, {} days)",
+                magnitude_threshold,
+                days
+            );
+            return 0.0;
+        }
+        let days = days.clamp(0.0, self.max_horizon_days());
         let counts = self.magnitude_counts.read();
         let time_span = *self.time_span_days.read();
-        let threshold_key = (magnitude_threshold * 10.0) as u32;
+        let threshold_key = mag_to_key(magnitude_threshold, 1);
 
         let events_above_threshold: u32 = counts
             .iter()
@@ -736,7 +1089,7 @@ impl RiskAssessmentAnalytics {
             .map(|(_, &count)| count)
             .sum();
 
-        if time_span <= 0.0 {
+        if time_span <= 0.0 || !time_span.is_finite() {
             return 0.0;
         }
 
@@ -751,12 +1104,56 @@ impl RiskAssessmentAnalytics {
         *self.total_energy_joules.read()
     }
 
+    /// Total energy as a value and unit (J up to PJ) chosen so the value lies
+    /// in `[1, 1000)`, where possible
+    pub fn get_total_energy_scaled(&self) -> (f64, String) {
+        let (value, unit) = Self::scale_energy(self.get_total_energy());
+        (value, unit.to_string())
+    }
+
+    /// Days between the oldest and newest event, or 1 when they coincide
+    pub fn get_time_span_days(&self) -> f64 {
+        *self.time_span_days.read()
+    }
+
+    /// Total energy expressed in tons of TNT
+    pub fn get_total_energy_tnt_tons(&self) -> f64 {
+        self.get_total_energy() / Self::JOULES_PER_TON_TNT
+    }
+
+    /// Scale Joules to the largest unit keeping the value at least 1. Values
+    /// beyond 1000 PJ stay in PJ.
+    fn scale_energy(joules: f64) -> (f64, &'static str) {
+        let mut value = joules;
+        let mut unit = 0;
+        while value.abs() >= 1000.0 && unit < Self::ENERGY_UNITS.len() - 1 {
+            value /= 1000.0;
+            unit += 1;
+        }
+        (value, Self::ENERGY_UNITS[unit])
+    }
+
     /// Convert magnitude to energy (Joules) using: log10(E) = 11.8 + 1.5*M
-    fn magnitude_to_energy(magnitude: f64) -> f64 {
+    pub(crate) fn magnitude_to_energy(magnitude: f64) -> f64 {
         let log_energy = 11.8 + 1.5 * magnitude;
         10_f64.powf(log_energy)
     }
 
+    /// Energy for a magnitude, served from the lookup table when the magnitude
+    /// sits on the 0.1 grid and computed exactly otherwise
+    fn cached_energy(&self, magnitude: f64) -> f64 {
+        let scaled = magnitude * 10.0;
+        let bin = scaled.round();
+
+        if (scaled - bin).abs() < 1e-9 && bin >= 0.0 {
+            if let Some(&energy) = self.energy_table.get(bin as usize) {
+                return energy;
+            }
+        }
+
+        Self::magnitude_to_energy(magnitude)
+    }
+
     pub fn get_risk_metrics(&self) -> (f64, f64, f64, f64) {
         let prob_5_30days = self.probability_magnitude_in_days(5.0, 30.0);
         let prob_6_365days = self.probability_magnitude_in_days(6.0, 365.0);
@@ -778,13 +1175,13 @@ impl AnalyticsProcessor for RiskAssessmentAnalytics {
             *total += 1;
         }
 
-        let mag_key = (event.magnitude * 10.0) as u32;
+        let mag_key = mag_to_key(event.magnitude, 1);
         {
             let mut counts = self.magnitude_counts.write();
             *counts.entry(mag_key).or_insert(0) += 1;
         }
 
-        let energy = Self::magnitude_to_energy(event.magnitude);
+        let energy = self.cached_energy(event.magnitude);
         {
             let mut total_energy = self.total_energy_joules.write();
             *total_energy += energy;
@@ -809,10 +1206,10 @@ impl AnalyticsProcessor for RiskAssessmentAnalytics {
 
         for (mag_opt, time_opt) in magnitudes.iter().zip(timestamps.iter()) {
             if let (Some(mag), Some(time)) = (mag_opt, time_opt) {
-                let mag_key = (mag * 10.0) as u32;
+                let mag_key = mag_to_key(mag, 1);
                 *magnitude_counts.entry(mag_key).or_insert(0) += 1;
 
-                total_energy += Self::magnitude_to_energy(mag);
+                total_energy += self.cached_energy(mag);
 
                 min_time = min_time.min(time);
                 max_time = max_time.max(time);
@@ -856,6 +1253,181 @@ impl AnalyticsProcessor for RiskAssessmentAnalytics {
     }
 }
 
+/// Streaming magnitude quantiles processor
+///
+/// Tracks approximate p50, p90 and p99 magnitudes without storing the
+/// catalog, using the P² algorithm (Jain & Chlamtac, 1985). Each quantile keeps
+/// five markers whose heights are adjusted with a piecewise-parabolic formula
+/// as events arrive, so an update costs O(1) regardless of catalog size.
+///
+/// Estimates are exact for fewer than five events and converge towards the
+/// true quantiles as the catalog grows.
+pub struct MagnitudeQuantilesAnalytics {
+    estimators: Arc<RwLock<[P2Quantile; 3]>>,
+}
+
+impl MagnitudeQuantilesAnalytics {
+    const QUANTILES: [f64; 3] = [0.5, 0.9, 0.99];
+
+    pub fn new() -> Self {
+        Self {
+            estimators: Arc::new(RwLock::new(Self::QUANTILES.map(P2Quantile::new))),
+        }
+    }
+
+    /// Approximate (p50, p90, p99) magnitudes, zero when no events are stored
+    pub fn get_quantiles(&self) -> (f64, f64, f64) {
+        let estimators = self.estimators.read();
+        (
+            estimators[0].estimate(),
+            estimators[1].estimate(),
+            estimators[2].estimate(),
+        )
+    }
+}
+
+impl AnalyticsProcessor for MagnitudeQuantilesAnalytics {
+    fn name(&self) -> &'static str {
+        "magnitude_quantiles"
+    }
+
+    fn update(&self, event: &SeismicEvent) -> Result<(), PolarsError> {
+        for estimator in self.estimators.write().iter_mut() {
+            estimator.observe(event.magnitude);
+        }
+        Ok(())
+    }
+
+    fn recompute(&self, dataframe: &LazyFrame) -> Result<(), PolarsError> {
+        let result = dataframe.clone().select([col("mag")]).collect()?;
+        let magnitudes = result.column("mag")?.f64()?;
+
+        let mut estimators = Self::QUANTILES.map(P2Quantile::new);
+        for mag in magnitudes.iter().flatten() {
+            for estimator in estimators.iter_mut() {
+                estimator.observe(mag);
+            }
+        }
+
+        *self.estimators.write() = estimators;
+        Ok(())
+    }
+
+    fn clear(&self) {
+        *self.estimators.write() = Self::QUANTILES.map(P2Quantile::new);
+    }
+
+    fn get_auxiliary_stats(&self, dataframe: &LazyFrame) -> LazyFrame {
+        let (p50, p90, p99) = self.get_quantiles();
+
+        dataframe
+            .clone()
+            .select([
+                lit(p50).alias("p50_magnitude"),
+                lit(p90).alias("p90_magnitude"),
+                lit(p99).alias("p99_magnitude"),
+            ])
+            .with_columns([lit("Magnitude Quantiles").alias("title")])
+    }
+}
+
+/// P² estimator for a single quantile
+#[derive(Debug, Clone)]
+struct P2Quantile {
+    p: f64,
+    count: usize,
+    heights: [f64; 5],
+    positions: [f64; 5],
+    desired: [f64; 5],
+    increments: [f64; 5],
+}
+
+impl P2Quantile {
+    fn new(p: f64) -> Self {
+        Self {
+            p,
+            count: 0,
+            heights: [0.0; 5],
+            positions: [1.0, 2.0, 3.0, 4.0, 5.0],
+            desired: [1.0, 1.0 + 2.0 * p, 1.0 + 4.0 * p, 3.0 + 2.0 * p, 5.0],
+            increments: [0.0, p / 2.0, p, (1.0 + p) / 2.0, 1.0],
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        if self.count < 5 {
+            self.heights[self.count] = value;
+            self.count += 1;
+            if self.count == 5 {
+                self.heights.sort_by(f64::total_cmp);
+            }
+            return;
+        }
+        self.count += 1;
+
+        let cell = if value < self.heights[0] {
+            self.heights[0] = value;
+            0
+        } else if value >= self.heights[4] {
+            self.heights[4] = value;
+            3
+        } else {
+            (0..4).find(|&i| value < self.heights[i + 1]).unwrap_or(3)
+        };
+
+        for position in &mut self.positions[cell + 1..] {
+            *position += 1.0;
+        }
+        for (desired, increment) in self.desired.iter_mut().zip(self.increments) {
+            *desired += increment;
+        }
+
+        for i in 1..4 {
+            let offset = self.desired[i] - self.positions[i];
+            let room_above = self.positions[i + 1] - self.positions[i] > 1.0;
+            let room_below = self.positions[i - 1] - self.positions[i] < -1.0;
+
+            if (offset >= 1.0 && room_above) || (offset <= -1.0 && room_below) {
+                let step = offset.signum();
+                let candidate = self.parabolic(i, step);
+                self.heights[i] =
+                    if self.heights[i - 1] < candidate && candidate < self.heights[i + 1] {
+                        candidate
+                    } else {
+                        self.linear(i, step)
+                    };
+                self.positions[i] += step;
+            }
+        }
+    }
+
+    fn parabolic(&self, i: usize, step: f64) -> f64 {
+        let (n, q) = (&self.positions, &self.heights);
+        q[i] + step / (n[i + 1] - n[i - 1])
+            * ((n[i] - n[i - 1] + step) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+                + (n[i + 1] - n[i] - step) * (q[i] - q[i - 1]) / (n[i] - n[i - 1]))
+    }
+
+    fn linear(&self, i: usize, step: f64) -> f64 {
+        let j = if step > 0.0 { i + 1 } else { i - 1 };
+        self.heights[i]
+            + step * (self.heights[j] - self.heights[i]) / (self.positions[j] - self.positions[i])
+    }
+
+    fn estimate(&self) -> f64 {
+        match self.count {
+            0 => 0.0,
+            1..=4 => {
+                let mut observed = self.heights[..self.count].to_vec();
+                observed.sort_by(f64::total_cmp);
+                let rank = (self.p * (self.count - 1) as f64).round() as usize;
+                observed[rank]
+            }
+            _ => self.heights[2],
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use chrono::{DateTime, Utc};
@@ -864,6 +1436,74 @@ mod tests {
     use crate::seismic::SeismicEvent;
     use crate::test_utils::create_test_event_with_params;
 
+    #[test]
+    fn test_mag_to_key_boundaries() {
+        assert_eq!(mag_to_key(2.0, 1), 20);
+        assert_eq!(mag_to_key(2.05, 1), 20);
+        assert_eq!(mag_to_key(2.099, 1), 20);
+        assert_eq!(mag_to_key(2.1, 1), 21);
+        // 0.7 + 0.1 is 0.7999999999999999 in floating point
+        assert_eq!(mag_to_key(0.7 + 0.1, 1), 8);
+        assert_eq!(mag_to_key(-0.5, 1), 0);
+
+        assert_eq!(mag_to_key(2.05, 2), 20);
+        assert_eq!(mag_to_key(2.199, 2), 20);
+        assert_eq!(mag_to_key(2.2, 2), 22);
+    }
+
+    #[test]
+    fn test_processors_agree_on_boundary_magnitudes() {
+        let magnitudes = [2.0, 2.05, 2.099, 2.1];
+        let distribution = MagnitudeDistributionAnalytics::new();
+        let gutenberg_richter = GutenbergRichterAnalytics::new();
+        let risk = RiskAssessmentAnalytics::new();
+        for (i, magnitude) in magnitudes.iter().enumerate() {
+            let mut event = SeismicEvent::test_event();
+            event.id = format!("test_{}", i);
+            event.magnitude = *magnitude;
+            distribution.update(&event).unwrap();
+            gutenberg_richter.update(&event).unwrap();
+            risk.update(&event).unwrap();
+        }
+
+        let expected = HashMap::from([(20, 3), (21, 1)]);
+        assert_eq!(*gutenberg_richter.magnitude_counts.read(), expected);
+        assert_eq!(*risk.magnitude_counts.read(), expected);
+        assert_eq!(
+            distribution.get_result().unwrap(),
+            vec![("2".to_string(), 4)]
+        );
+
+        let dataframe = df!["mag" => magnitudes].unwrap().lazy();
+        gutenberg_richter.recompute(&dataframe).unwrap();
+        assert_eq!(*gutenberg_richter.magnitude_counts.read(), expected);
+    }
+
+    #[test]
+    fn test_magnitude_distribution_above_cutoff() {
+        let processor = MagnitudeDistributionAnalytics::new();
+        for (i, magnitude) in [1.1, 1.5, 1.9, 2.0, 2.1, 2.3, 3.0, 3.1, 4.5]
+            .iter()
+            .enumerate()
+        {
+            let mut event = SeismicEvent::test_event();
+            event.id = format!("test_{}", i);
+            event.magnitude = *magnitude;
+            processor.update(&event).unwrap();
+        }
+
+        let full = processor.get_result().unwrap();
+        let above = processor.get_result_above(2.0).unwrap();
+
+        let labels: Vec<&str> = above.iter().map(|(bucket, _)| bucket.as_str()).collect();
+        assert_eq!(labels, ["2", "2.2", "3", "4.4"]);
+        for (bucket, count) in &above {
+            assert!(full.contains(&(bucket.clone(), *count)));
+        }
+        assert_eq!(processor.get_result_above(0.0).unwrap(), full);
+        assert!(processor.get_result_above(5.0).unwrap().is_empty());
+    }
+
     #[test]
     fn test_magnitude_distribution_analytics_comprehensive() {
         let processor = MagnitudeDistributionAnalytics::new();
@@ -1004,7 +1644,11 @@ mod tests {
         assert_eq!(processor.get_daily_counts().len(), 0);
         assert_eq!(processor.get_hourly_distribution().len(), 0);
         assert_eq!(processor.get_monthly_distribution().len(), 0);
-        assert_eq!(processor.get_weekly_distribution().len(), 0);
+        assert_eq!(processor.get_weekly_distribution().len(), 7);
+        assert!(processor
+            .get_weekly_distribution()
+            .iter()
+            .all(|(_, count)| *count == 0));
     }
 
     #[test]
@@ -1091,6 +1735,88 @@ mod tests {
         assert_eq!(processor.get_coordinate_clusters().len(), 0);
     }
 
+    #[test]
+    fn test_annual_rate_above() {
+        let processor = GutenbergRichterAnalytics::new();
+        *processor.a_value.write() = 5.0;
+        *processor.b_value.write() = 1.0;
+
+        // 10^(5 - 3) = 100 events over half a year
+        let rate = processor.annual_rate_above(3.0, DAYS_PER_YEAR / 2.0);
+        assert!((rate - 200.0).abs() < 1e-9);
+
+        let rate = processor.annual_rate_above(4.5, 730.5);
+        let expected = 10_f64.powf(0.5) * DAYS_PER_YEAR / 730.5;
+        assert!((rate - expected).abs() < 1e-12);
+
+        assert_eq!(processor.annual_rate_above(3.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_expected_max_magnitude_grows_with_horizon() {
+        let processor = GutenbergRichterAnalytics::new();
+        *processor.a_value.write() = 5.0;
+        *processor.b_value.write() = 1.0;
+
+        // 10^(5 - M) events over one year reach a count of one at M5
+        let one_year = processor.expected_max_magnitude(1.0, DAYS_PER_YEAR);
+        assert!((one_year - 5.0).abs() < 1e-9);
+
+        let ten_years = processor.expected_max_magnitude(10.0, DAYS_PER_YEAR);
+        let century = processor.expected_max_magnitude(100.0, DAYS_PER_YEAR);
+        assert!(ten_years > one_year);
+        assert!(century > ten_years);
+        assert!((century - 7.0).abs() < 1e-9);
+
+        assert_eq!(processor.expected_max_magnitude(0.0, DAYS_PER_YEAR), 0.0);
+        assert_eq!(processor.expected_max_magnitude(1.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_degenerate_inputs_give_finite_outputs() {
+        let single = GutenbergRichterAnalytics::new();
+        let mut event = SeismicEvent::test_event();
+        event.magnitude = 3.0;
+        single.update(&event).unwrap();
+        single
+            .recompute(&df!["mag" => [3.0]].unwrap().lazy())
+            .unwrap();
+
+        let same = GutenbergRichterAnalytics::new();
+        for i in 0..200 {
+            let mut event = SeismicEvent::test_event();
+            event.id = format!("test_{}", i);
+            event.magnitude = 3.0;
+            same.update(&event).unwrap();
+        }
+        same.recompute(&df!["mag" => [3.0; 200]].unwrap().lazy())
+            .unwrap();
+
+        for processor in [&single, &same] {
+            assert!(processor.get_b_value().is_finite());
+            assert!(processor.get_a_value().is_finite());
+            assert!(processor.get_b_value_uncertainty().is_finite());
+            assert!(processor.annual_rate_above(4.0, 1.0).is_finite());
+            assert_eq!(processor.annual_rate_above(4.0, 0.0), 0.0);
+        }
+
+        // A rate over a vanishing span overflows to infinity
+        *same.a_value.write() = 5.0;
+        assert_eq!(same.annual_rate_above(-2.0, f64::MIN_POSITIVE), 0.0);
+
+        let risk = RiskAssessmentAnalytics::new();
+        risk.update(&event).unwrap();
+        let (p30, p365, p365_large, energy) = risk.get_risk_metrics();
+        assert!([p30, p365, p365_large, energy]
+            .iter()
+            .all(|v| v.is_finite()));
+        assert_eq!(risk.probability_magnitude_in_days(f64::NAN, 30.0), 0.0);
+        assert_eq!(risk.probability_magnitude_in_days(5.0, f64::NAN), 0.0);
+        assert!(risk
+            .probability_magnitude_in_days(2.0, f64::INFINITY)
+            .is_finite());
+    }
+
     #[test]
     fn test_gutenberg_richter_analytics_comprehensive() {
         let processor = GutenbergRichterAnalytics::new();
@@ -1137,6 +1863,61 @@ mod tests {
         assert_eq!(processor.get_magnitude_frequency_data().len(), 0);
     }
 
+    #[test]
+    fn test_magnitude_frequency_cumulative_counts() {
+        let processor = GutenbergRichterAnalytics::new();
+        // Sparse bins with gaps between them
+        *processor.magnitude_counts.write() =
+            HashMap::from([(20, 5), (21, 3), (25, 0), (30, 2), (47, 1)]);
+
+        assert_eq!(
+            processor.get_magnitude_frequency_data(),
+            [
+                (2.0, 5, 11),
+                (2.1, 3, 6),
+                (2.5, 0, 3),
+                (3.0, 2, 3),
+                (4.7, 1, 1)
+            ]
+        );
+
+        // Tens of thousands of bins stay cheap and the cumulative never grows
+        // with magnitude
+        *processor.magnitude_counts.write() = (0..50_000).map(|key| (key, key % 3)).collect();
+        let data = processor.get_magnitude_frequency_data();
+        assert_eq!(data.len(), 50_000);
+        assert_eq!(data[0].2, (0..50_000).map(|key| key % 3).sum::<u32>());
+        assert!(data
+            .windows(2)
+            .all(|pair| pair[0].2 >= pair[1].2 && pair[0].2 - pair[1].2 == pair[0].1));
+    }
+
+    #[test]
+    fn test_magnitude_frequency_loglog() {
+        let processor = GutenbergRichterAnalytics::new();
+        assert!(processor.get_magnitude_frequency_loglog().is_empty());
+
+        for (i, mag) in [2.0, 2.0, 2.0, 2.5, 2.5, 3.0].iter().enumerate() {
+            let mut event = SeismicEvent::test_event();
+            event.id = format!("test_{}", i);
+            event.magnitude = *mag;
+            processor.update(&event).unwrap();
+        }
+        // A bin left empty, e.g. after events were removed, has no cumulative count
+        processor.magnitude_counts.write().insert(40, 0);
+
+        let loglog = processor.get_magnitude_frequency_loglog();
+
+        let expected = [(2.0, 6.0), (2.5, 3.0), (3.0, 1.0)];
+        assert_eq!(loglog.len(), expected.len());
+        for ((magnitude, log_cumulative), (expected_mag, cumulative)) in
+            loglog.iter().zip(expected.iter())
+        {
+            assert_eq!(magnitude, expected_mag);
+            assert!((log_cumulative - f64::log10(*cumulative)).abs() < 1e-12);
+        }
+    }
+
     #[test]
     fn test_risk_assessment_analytics_comprehensive() {
         let processor = RiskAssessmentAnalytics::new();
@@ -1198,6 +1979,234 @@ mod tests {
         assert_eq!(total_energy, 0.0);
     }
 
+    #[test]
+    fn test_probability_horizon_is_clamped() {
+        let processor = RiskAssessmentAnalytics::new();
+        let day_ns = 86_400_000_000_000_i64;
+        let dataframe = df![
+            "mag" => [5.0, 3.0, 3.0, 3.0],
+            "time" => [0, 1000 * day_ns, 2000 * day_ns, 3000 * day_ns],
+        ]
+        .unwrap()
+        .lazy()
+        .with_column(col("time").cast(DataType::Datetime(TimeUnit::Nanoseconds, None)));
+        processor.recompute(&dataframe).unwrap();
+
+        let max = RiskAssessmentAnalytics::DEFAULT_MAX_HORIZON_DAYS;
+        assert_eq!(processor.max_horizon_days(), max);
+        assert_eq!(
+            processor.probability_magnitude_in_days(5.0, 1e9),
+            processor.probability_magnitude_in_days(5.0, max)
+        );
+        assert!(processor.probability_magnitude_in_days(5.0, max) < 1.0);
+
+        let within = processor.probability_magnitude_in_days(5.0, 365.0);
+        let lambda = 365.0 / 3000.0;
+        assert!((within - (1.0 - f64::exp(-lambda))).abs() < 1e-12);
+
+        processor.set_max_horizon_days(100.0);
+        assert_eq!(
+            processor.probability_magnitude_in_days(5.0, 365.0),
+            processor.probability_magnitude_in_days(5.0, 100.0)
+        );
+        assert_eq!(processor.probability_magnitude_in_days(5.0, -5.0), 0.0);
+    }
+
+    #[test]
+    fn test_energy_scaling_picks_unit() {
+        let cases = [
+            (0.0, 0.0, "J"),
+            (950.0, 950.0, "J"),
+            (1000.0, 1.0, "kJ"),
+            (2.5e7, 25.0, "MJ"),
+            (3.16e15, 3.16, "PJ"),
+            (4.0e20, 400_000.0, "PJ"),
+        ];
+        for (joules, expected_value, expected_unit) in cases {
+            let (value, unit) = RiskAssessmentAnalytics::scale_energy(joules);
+            assert_eq!(unit, expected_unit, "unit for {} J", joules);
+            assert!(
+                (value - expected_value).abs() < 1e-9 * expected_value.max(1.0),
+                "value for {} J: {}",
+                joules,
+                value
+            );
+        }
+
+        let processor = RiskAssessmentAnalytics::new();
+        let event = create_test_event_with_params(
+            "energy",
+            5.0,
+            10.0,
+            35.0,
+            -120.0,
+            Utc::now(),
+            "California",
+        );
+        processor.update(&event).unwrap();
+
+        // log10(E) = 11.8 + 1.5 * 5.0 = 19.3
+        let (value, unit) = processor.get_total_energy_scaled();
+        assert_eq!(unit, "PJ");
+        assert!((value - 10_f64.powf(19.3) / 1e15).abs() < 1e-6);
+        let tons = processor.get_total_energy_tnt_tons();
+        assert!((tons - 10_f64.powf(19.3) / 4.184e9).abs() / tons < 1e-12);
+    }
+
+    #[test]
+    fn test_cached_energy_matches_exact() {
+        let processor = RiskAssessmentAnalytics::new();
+
+        // Table bins, off-grid magnitudes and values outside the table
+        let magnitudes = (-10..=110)
+            .map(|bin| bin as f64 / 10.0)
+            .chain([2.37, 4.25, 6.99, 10.45]);
+
+        for magnitude in magnitudes {
+            let exact = RiskAssessmentAnalytics::magnitude_to_energy(magnitude);
+            let cached = processor.cached_energy(magnitude);
+            assert!(
+                ((cached - exact) / exact).abs() < 1e-12,
+                "energy mismatch for magnitude {}: {} vs {}",
+                magnitude,
+                cached,
+                exact
+            );
+        }
+    }
+
+    #[test]
+    fn test_auto_bins_follow_spread() {
+        let magnitudes_frame = |magnitudes: Vec<f64>| df!["mag" => magnitudes].unwrap().lazy();
+
+        let wide: Vec<f64> = (0..200).map(|i| 1.0 + i as f64 * 0.03).collect();
+        let tight: Vec<f64> = (0..200).map(|i| 3.0 + (i % 10) as f64 * 0.05).collect();
+
+        let processor = MagnitudeDistributionAnalytics::new();
+        processor
+            .recompute_auto_bins(&magnitudes_frame(wide))
+            .unwrap();
+        let wide_width = processor.bin_width();
+        let wide_total: u32 = processor
+            .get_result()
+            .unwrap()
+            .iter()
+            .map(|(_, count)| count)
+            .sum();
+
+        processor
+            .recompute_auto_bins(&magnitudes_frame(tight))
+            .unwrap();
+        let tight_width = processor.bin_width();
+
+        assert!(
+            wide_width > tight_width,
+            "{} <= {}",
+            wide_width,
+            tight_width
+        );
+        assert_eq!(wide_total, 200);
+
+        // Too few samples keep the fixed width
+        processor
+            .recompute_auto_bins(&magnitudes_frame(vec![1.0, 4.0, 7.5]))
+            .unwrap();
+        assert_eq!(processor.bin_width(), 0.2);
+
+        // The flag makes recompute pick automatic bins
+        let wide: Vec<f64> = (0..200).map(|i| 1.0 + i as f64 * 0.03).collect();
+        processor.set_auto_bins(true);
+        processor
+            .recompute(&magnitudes_frame(wide.clone()))
+            .unwrap();
+        assert_eq!(processor.bin_width(), wide_width);
+        processor.set_auto_bins(false);
+        processor.recompute(&magnitudes_frame(wide)).unwrap();
+        assert_eq!(processor.bin_width(), 0.2);
+    }
+
+    #[test]
+    fn test_magnitude_quantiles_match_exact() {
+        // Deterministic pseudo-random uniform samples in [0, 1)
+        let mut seed: u64 = 42;
+        let mut next_uniform = move || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 11) as f64 / (1u64 << 53) as f64
+        };
+
+        // Uniform magnitudes and a Gutenberg-Richter-like exponential tail
+        let uniform: Vec<f64> = (0..20_000).map(|_| 1.0 + 6.0 * next_uniform()).collect();
+        let exponential: Vec<f64> = (0..20_000)
+            .map(|_| 1.0 - (1.0 - next_uniform()).ln() / std::f64::consts::LN_10)
+            .collect();
+
+        for magnitudes in [uniform, exponential] {
+            let processor = MagnitudeQuantilesAnalytics::new();
+            for (i, mag) in magnitudes.iter().enumerate() {
+                let event = create_test_event_with_params(
+                    &format!("test_{}", i),
+                    *mag,
+                    10.0,
+                    35.0,
+                    -120.0,
+                    Utc::now(),
+                    "California",
+                );
+                processor.update(&event).unwrap();
+            }
+
+            let mut sorted = magnitudes.clone();
+            sorted.sort_by(f64::total_cmp);
+            let exact = |p: f64| sorted[((sorted.len() - 1) as f64 * p).round() as usize];
+
+            let (p50, p90, p99) = processor.get_quantiles();
+            assert!(
+                (p50 - exact(0.5)).abs() < 0.05,
+                "p50 {} vs {}",
+                p50,
+                exact(0.5)
+            );
+            assert!(
+                (p90 - exact(0.9)).abs() < 0.05,
+                "p90 {} vs {}",
+                p90,
+                exact(0.9)
+            );
+            assert!(
+                (p99 - exact(0.99)).abs() < 0.1,
+                "p99 {} vs {}",
+                p99,
+                exact(0.99)
+            );
+        }
+    }
+
+    #[test]
+    fn test_magnitude_quantiles_small_samples() {
+        let processor = MagnitudeQuantilesAnalytics::new();
+        assert_eq!(processor.get_quantiles(), (0.0, 0.0, 0.0));
+
+        for (i, mag) in [3.0, 1.0, 2.0].iter().enumerate() {
+            let event = create_test_event_with_params(
+                &format!("test_{}", i),
+                *mag,
+                10.0,
+                35.0,
+                -120.0,
+                Utc::now(),
+                "California",
+            );
+            processor.update(&event).unwrap();
+        }
+
+        assert_eq!(processor.get_quantiles(), (2.0, 3.0, 3.0));
+
+        processor.clear();
+        assert_eq!(processor.get_quantiles(), (0.0, 0.0, 0.0));
+    }
+
     #[test]
     fn test_analytics_processor_trait_methods() {
         let processors: Vec<Box<dyn AnalyticsProcessor>> = vec![
@@ -1207,6 +2216,7 @@ mod tests {
             Box::new(GeographicHotspotsAnalytics::new()),
             Box::new(GutenbergRichterAnalytics::new()),
             Box::new(RiskAssessmentAnalytics::new()),
+            Box::new(MagnitudeQuantilesAnalytics::new()),
         ];
 
         let expected_names = vec![
@@ -1216,6 +2226,7 @@ mod tests {
             "geographic_hotspots",
             "gutenberg_richter",
             "risk_assessment",
+            "magnitude_quantiles",
         ];
 
         for (processor, expected_name) in processors.iter().zip(expected_names.iter()) {
@@ -1299,6 +2310,76 @@ mod tests {
         assert!(alaska_cluster.is_some());
     }
 
+    #[test]
+    fn test_equal_area_binning_merges_high_latitude_cells() {
+        let events = vec![
+            create_test_event_with_params("1", 3.0, 10.0, 80.0, 19.0, Utc::now(), "Svalbard"),
+            create_test_event_with_params("2", 3.0, 10.0, 80.0, 20.0, Utc::now(), "Svalbard"),
+            create_test_event_with_params("3", 3.0, 10.0, 80.1, 21.0, Utc::now(), "Svalbard"),
+            create_test_event_with_params("4", 3.0, 10.0, 0.0, 19.0, Utc::now(), "Gulf of Guinea"),
+            create_test_event_with_params("5", 3.0, 10.0, 0.0, 20.0, Utc::now(), "Gulf of Guinea"),
+        ];
+
+        let regular = GeographicHotspotsAnalytics::new();
+        assert_eq!(regular.binning(), GridBinning::Regular);
+        let equal_area = GeographicHotspotsAnalytics::new();
+        equal_area.set_binning(GridBinning::EqualArea);
+        for event in &events {
+            regular.update(event).unwrap();
+            equal_area.update(event).unwrap();
+        }
+
+        let polar = |clusters: Vec<(f64, f64, u32)>| -> Vec<(f64, f64, u32)> {
+            clusters.into_iter().filter(|c| c.0 > 45.0).collect()
+        };
+
+        // One-degree steps are separate 0.5-degree cells everywhere
+        let regular_clusters = regular.get_coordinate_clusters();
+        assert_eq!(
+            polar(regular_clusters.clone()),
+            vec![(80.0, 19.0, 1), (80.0, 20.0, 1), (80.0, 21.0, 1),]
+        );
+        assert_eq!(regular_clusters.len(), 5);
+
+        // At 80 degrees a cell spans ~2.9 degrees of longitude, while the
+        // equator keeps 0.5-degree cells
+        let equal_area_clusters = equal_area.get_coordinate_clusters();
+        let polar_clusters = polar(equal_area_clusters.clone());
+        assert_eq!(polar_clusters.len(), 1);
+        assert_eq!(polar_clusters[0].0, 80.0);
+        assert!((polar_clusters[0].1 - 20.0).abs() < 1.0);
+        assert_eq!(polar_clusters[0].2, 3);
+        assert_eq!(equal_area_clusters.len(), 3);
+
+        // Recomputing from a DataFrame assigns the same cells
+        let frame = df![
+            "flynn_region" => events.iter().map(|e| e.flynn_region.as_str()).collect::<Vec<_>>(),
+            "lat" => events.iter().map(|e| e.latitude).collect::<Vec<_>>(),
+            "lon" => events.iter().map(|e| e.longitude).collect::<Vec<_>>(),
+            "time" => events.iter().map(|e| e.time.timestamp_nanos_opt().unwrap()).collect::<Vec<_>>(),
+            "mag" => events.iter().map(|e| e.magnitude).collect::<Vec<_>>(),
+        ]
+        .unwrap()
+        .lazy()
+        .with_column(col("time").cast(DataType::Datetime(TimeUnit::Nanoseconds, None)));
+        equal_area.recompute(&frame).unwrap();
+        assert_eq!(equal_area.get_coordinate_clusters(), equal_area_clusters);
+        assert_eq!(equal_area.get_energy_grid().len(), 3);
+    }
+
+    #[test]
+    fn test_equal_area_binning_polar_row_is_one_cell() {
+        let binning = GridBinning::EqualArea;
+        for lon in [-180.0, -90.0, 0.0, 90.0, 180.0] {
+            let key = binning.key(89.9, lon);
+            assert_eq!(key, (180, 0));
+            let (lat, center_lon) = binning.center(key);
+            assert_eq!(lat, 90.0);
+            assert!((-180.0..=180.0).contains(&center_lon));
+        }
+        assert_eq!(binning.key(-89.9, 180.0), (-180, 0));
+    }
+
     #[test]
     fn test_magnitude_energy_conversion() {
         let test_cases = vec![
@@ -1,14 +1,35 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::time::Duration;
+
 use chrono::{DateTime, Utc};
+use futures_util::{stream, Stream, StreamExt};
 use geojson::JsonValue;
 use serde::{Deserialize, Serialize};
 
+use crate::error::validation::validate_depth;
 use crate::error::{ErrorContextExt, QuakeTrackerError, Result};
-use crate::seismic::SeismicEvent;
+use crate::seismic::{count_evaluation_modes, LocationUncertainty, SeismicEvent};
 use crate::AppState;
 
 pub(crate) static SEISMIC_URL: &str = "https://www.seismicportal.eu/fdsnws/event/1/query";
 pub(crate) static SEISMIC_WSS_URL: &str = "wss://www.seismicportal.eu/standing_order/websocket";
 
+/// Upper bound on events collected by [`EventSource::fetch_all_events`]
+const MAX_FETCH_ALL_EVENTS: usize = 100_000;
+/// Default cap on the size of a single REST response body
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 64 * 1024 * 1024;
+/// Number of parsed events handed to the state at once while ingesting
+const INGEST_BATCH_SIZE: usize = 1_000;
+/// Page size used when backfilling a time range
+const BACKFILL_PAGE_SIZE: i32 = 500;
+/// Attempts made while the service keeps rate limiting requests
+const MAX_RATE_LIMIT_ATTEMPTS: u32 = 3;
+/// Longest server-requested wait honored before retrying
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+/// Wait before retrying a 429 that doesn't say how long to back off
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(5);
+
 /// Client error type for Tauri command responses
 ///
 /// This error type is specifically designed for serialization to the frontend
@@ -62,34 +83,508 @@ async fn get_seismic_events_internal_impl(
     state: &AppState,
     query_params: QueryParams,
 ) -> Result<String> {
-    query_params
-        .validate()
-        .with_operation("validate_params", "client")?;
-
-    let response = reqwest::Client::new()
-        .get(SEISMIC_URL)
-        .query(&query_params)
-        .send()
-        .await
-        .with_operation("fetch_events", "emsc_api")?;
+    let source = rest_source(state)?;
+    let body = source.fetch_raw(&query_params).await?;
+    ingest_response(&source, state, body)
+}
+
+/// Store the events of a REST response body, returning the GeoJSON to hand
+/// to the frontend
+///
+/// The body is read in full because the frontend gets it back, but events
+/// are parsed from it in batches, so the whole catalog is never held parsed.
+/// A malformed feature fails the call after the batches before it were
+/// stored; those events are kept. When `source` rewrites events, each batch
+/// is serialized back as it is stored and returned instead of the raw body,
+/// so the frontend sees what was stored.
+fn ingest_response(source: &RestSource, state: &AppState, body: String) -> Result<String> {
+    // The service answers an empty query with 204 and no body
+    if body.trim().is_empty() {
+        return Ok(body);
+    }
+
+    const FEATURES_START: &str = r#"{"type":"FeatureCollection","features":["#;
+    let mut rewritten = source.rewrites_events().then(|| FEATURES_START.to_string());
+
+    let stored = ingest_feature_collection(body.as_bytes(), INGEST_BATCH_SIZE, |batch| {
+        let batch = source.prepare_events(batch)?;
+        if let Some(rewritten) = rewritten.as_mut() {
+            for event in &batch {
+                if rewritten.len() > FEATURES_START.len() {
+                    rewritten.push(',');
+                }
+                rewritten.push_str(&geojson::ser::to_feature_string(event)?);
+            }
+        }
+        let mut state = state.lock().map_err(|e| {
+            QuakeTrackerError::state(format!("Failed to acquire state lock: {}", e))
+        })?;
+        state
+            .add_events(batch)
+            .with_operation("store_events", "state")
+    })?;
+    log::debug!("Stored {} events from REST response", stored);
+
+    Ok(match rewritten {
+        Some(mut rewritten) => {
+            rewritten.push_str("]}");
+            rewritten
+        }
+        None => body,
+    })
+}
+
+/// Fetch the events matching `query_params` with all origins and count them
+/// by the evaluation mode of their best origin
+pub(crate) async fn get_evaluation_mode_counts_internal(
+    state: &AppState,
+    mut query_params: QueryParams,
+) -> ClientResult<BTreeMap<String, usize>> {
+    query_params.other_parameters.include_all_origins = Some(true);
+    let events = rest_source(state)?.fetch_events(&query_params).await?;
+
+    Ok(count_evaluation_modes(&events))
+}
 
-    let events = response.error_for_status()?
-        .text()
+/// Fetch `event_id` with all origins and return the uncertainties of its best
+/// origin, or `None` if the service reports no origins for it
+pub(crate) async fn get_event_uncertainty_internal(
+    state: &AppState,
+    event_id: String,
+) -> ClientResult<Option<LocationUncertainty>> {
+    let events = rest_source(state)?
+        .fetch_events(&QueryParams::for_event(event_id.clone()))
+        .await?;
+
+    let event = events
+        .into_iter()
+        .find(|event| event.id == event_id)
+        .ok_or_else(|| {
+            QuakeTrackerError::validation("event_id", format!("Event '{}' not found", event_id))
+        })?;
+
+    Ok(event.uncertainty())
+}
+
+/// Fetch every event between `start` and `end` from `source` and merge them
+/// into the catalog, returning how many new events were added
+pub(crate) async fn backfill_range_internal<S: EventSource>(
+    source: &S,
+    state: &AppState,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<usize> {
+    if start >= end {
+        return Err(QuakeTrackerError::validation(
+            "time_range",
+            "Backfill start must be before its end",
+        ));
+    }
+
+    let mut params = QueryParams::default();
+    params.time_constraints.start_time = Some(start);
+    params.time_constraints.end_time = Some(end);
+
+    let events = source
+        .fetch_all_events(params, BACKFILL_PAGE_SIZE)
         .await
-        .with_operation("read_response", "emsc_api")?;
+        .with_operation("backfill_range", "client")?;
+    let fetched = events.len();
+
+    let mut state = state
+        .lock()
+        .map_err(|e| QuakeTrackerError::state(format!("Failed to acquire state lock: {}", e)))?;
+    let added = state
+        .merge_events(events)
+        .with_operation("merge_backfilled_events", "state")?;
+    log::info!(
+        "Backfilled {} new events out of {} fetched between {} and {}",
+        added,
+        fetched,
+        start,
+        end
+    );
+
+    Ok(added)
+}
 
-    let parsed: Vec<SeismicEvent> = geojson::de::deserialize_feature_collection_str_to_vec(&events)
+/// Parse a GeoJSON FeatureCollection feature by feature, handing events to
+/// `store` in batches of at most `batch_size`
+///
+/// Only one batch of parsed events is held at a time, so memory stays bounded
+/// regardless of the collection size. Batches stored before a parse error are
+/// kept. Returns the number of events parsed.
+fn ingest_feature_collection<R, F>(reader: R, batch_size: usize, mut store: F) -> Result<usize>
+where
+    R: std::io::Read,
+    F: FnMut(Vec<SeismicEvent>) -> Result<()>,
+{
+    let features = geojson::de::deserialize_feature_collection::<SeismicEvent>(reader)
         .with_operation("parse_geojson", "client")?;
 
-    let mut state = state
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut total = 0;
+
+    for feature in features {
+        batch.push(feature.with_operation("parse_geojson", "client")?);
+        total += 1;
+
+        if batch.len() >= batch_size {
+            store(std::mem::replace(
+                &mut batch,
+                Vec::with_capacity(batch_size),
+            ))?;
+        }
+    }
+
+    if !batch.is_empty() {
+        store(batch)?;
+    }
+
+    Ok(total)
+}
+
+/// A queryable source of seismic events
+pub trait EventSource {
+    /// Fetch a single page of events matching `params`
+    async fn fetch_events(&self, params: &QueryParams) -> Result<Vec<SeismicEvent>>;
+
+    /// Fetch every event matching `base` by paging through it with `offset`
+    ///
+    /// Requests `page_size` events at a time until a short page is returned.
+    /// The total is capped at `MAX_FETCH_ALL_EVENTS`.
+    async fn fetch_all_events(
+        &self,
+        base: QueryParams,
+        page_size: i32,
+    ) -> Result<Vec<SeismicEvent>> {
+        if page_size <= 0 {
+            return Err(QuakeTrackerError::validation(
+                "page_size",
+                "Page size must be greater than 0",
+            ));
+        }
+
+        let mut params = base;
+        params.other_parameters.limit = Limit(page_size);
+        let mut offset = params.other_parameters.offset.unwrap_or(0);
+        let mut events = Vec::new();
+
+        loop {
+            params.other_parameters.offset = Some(offset);
+            let page = self
+                .fetch_events(&params)
+                .await
+                .with_operation("fetch_page", "client")?;
+            let page_len = page.len();
+            events.extend(page);
+
+            if events.len() >= MAX_FETCH_ALL_EVENTS {
+                log::warn!(
+                    "Stopping pagination after reaching {} events",
+                    MAX_FETCH_ALL_EVENTS
+                );
+                events.truncate(MAX_FETCH_ALL_EVENTS);
+                break;
+            }
+
+            if page_len < page_size as usize {
+                break;
+            }
+            offset += page_size;
+        }
+
+        Ok(events)
+    }
+}
+
+/// Unit a source reports event depths in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DepthUnit {
+    /// Kilometers, as used by EMSC
+    #[default]
+    Kilometers,
+    Meters,
+}
+
+impl DepthUnit {
+    pub fn to_km(self, depth: f64) -> f64 {
+        match self {
+            DepthUnit::Kilometers => depth,
+            DepthUnit::Meters => depth / 1000.0,
+        }
+    }
+}
+
+/// REST source configured from the state's `DataConfig`
+pub(crate) fn rest_source(state: &AppState) -> Result<RestSource> {
+    let state = state
         .lock()
         .map_err(|e| QuakeTrackerError::state(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(RestSource::default()
+        .with_max_response_bytes(state.max_response_bytes())
+        .with_depth_unit(state.depth_unit())
+        .with_best_origin_only(state.best_origin_only()))
+}
+
+/// Event source backed by the EMSC FDSN web service
+pub struct RestSource {
+    client: reqwest::Client,
+    url: String,
+    max_response_bytes: usize,
+    depth_unit: DepthUnit,
+    best_origin_only: bool,
+}
+
+impl Default for RestSource {
+    fn default() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: SEISMIC_URL.to_string(),
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            depth_unit: DepthUnit::default(),
+            best_origin_only: false,
+        }
+    }
+}
+
+impl RestSource {
+    /// Abort downloads whose body grows beyond `max_response_bytes`
+    pub fn with_max_response_bytes(mut self, max_response_bytes: usize) -> Self {
+        self.max_response_bytes = max_response_bytes;
+        self
+    }
+
+    /// Interpret depths reported by this source in `depth_unit`
+    pub fn with_depth_unit(mut self, depth_unit: DepthUnit) -> Self {
+        self.depth_unit = depth_unit;
+        self
+    }
+
+    /// Keep only the best origin of each fetched event, see
+    /// [`SeismicEvent::retain_best_origin`]
+    pub fn with_best_origin_only(mut self, best_origin_only: bool) -> Self {
+        self.best_origin_only = best_origin_only;
+        self
+    }
+
+    /// Whether `prepare_events` changes events, so they no longer match the
+    /// raw response body
+    pub fn rewrites_events(&self) -> bool {
+        self.best_origin_only || self.depth_unit != DepthUnit::Kilometers
+    }
+
+    /// Apply every per-source adjustment to freshly parsed events
+    pub fn prepare_events(&self, events: Vec<SeismicEvent>) -> Result<Vec<SeismicEvent>> {
+        Ok(self.prune_origins(self.normalize_depths(events)?))
+    }
+
+    /// Drop all but the best origin of freshly parsed events when
+    /// `best_origin_only` is set
+    pub fn prune_origins(&self, mut events: Vec<SeismicEvent>) -> Vec<SeismicEvent> {
+        if self.best_origin_only {
+            events.iter_mut().for_each(SeismicEvent::retain_best_origin);
+        }
+        events
+    }
+
+    /// Convert the depths of freshly parsed events to kilometers
+    ///
+    /// Converted depths are validated, so a source configured with the wrong
+    /// unit fails loudly instead of storing depths off by a factor of 1000.
+    pub fn normalize_depths(&self, mut events: Vec<SeismicEvent>) -> Result<Vec<SeismicEvent>> {
+        if self.depth_unit == DepthUnit::Kilometers {
+            return Ok(events);
+        }
+
+        for event in &mut events {
+            event.depth = self.depth_unit.to_km(event.depth);
+            validate_depth(event.depth)?;
+        }
+        Ok(events)
+    }
+
+    /// Fetch the raw GeoJSON response body for `params`
+    ///
+    /// Rate-limited responses are retried after the wait from `retry_after`,
+    /// up to `MAX_RATE_LIMIT_ATTEMPTS` attempts in total.
+    pub async fn fetch_raw(&self, params: &QueryParams) -> Result<String> {
+        params
+            .validate()
+            .with_operation("validate_params", "client")?;
 
-    state
-        .add_events(parsed)
-        .with_operation("store_events", "state")?;
+        with_rate_limit_retries(|| self.fetch_raw_once(params)).await
+    }
+
+    async fn fetch_raw_once(&self, params: &QueryParams) -> Result<Attempt<String>> {
+        let response = self
+            .client
+            .get(&self.url)
+            .query(params)
+            .send()
+            .await
+            .with_operation("fetch_events", "emsc_api")?;
+
+        if let Some(wait) = retry_after(response.status(), response.headers()) {
+            return Ok(Attempt::RetryAfter(wait));
+        }
+
+        let response = response.error_for_status()?;
+
+        // Reject early when the server announces an oversized body
+        if let Some(length) = response.content_length() {
+            if length > self.max_response_bytes as u64 {
+                return Err(response_too_large(self.max_response_bytes));
+            }
+        }
+
+        let chunks = stream::unfold(response, |mut response| async move {
+            response
+                .chunk()
+                .await
+                .transpose()
+                .map(|chunk| (chunk, response))
+        });
+
+        read_capped_body(chunks, self.max_response_bytes)
+            .await
+            .map(Attempt::Done)
+    }
+}
 
-    Ok(events)
+/// Outcome of a request the server may ask to repeat later
+enum Attempt<T> {
+    Done(T),
+    RetryAfter(Duration),
+}
+
+/// Run `attempt` until it completes, sleeping for the server-requested
+/// duration in between, and give up after `MAX_RATE_LIMIT_ATTEMPTS`
+async fn with_rate_limit_retries<T, F, Fut>(mut attempt: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Attempt<T>>>,
+{
+    let mut attempts = 0;
+
+    loop {
+        attempts += 1;
+        match attempt().await? {
+            Attempt::Done(value) => return Ok(value),
+            Attempt::RetryAfter(_) if attempts >= MAX_RATE_LIMIT_ATTEMPTS => {
+                return Err(QuakeTrackerError::external_service(
+                    "emsc_api",
+                    format!("Still rate limited after {} attempts", attempts),
+                ));
+            }
+            Attempt::RetryAfter(wait) => {
+                log::warn!(
+                    "Rate limited by EMSC, retrying in {:.1}s (attempt {}/{})",
+                    wait.as_secs_f64(),
+                    attempts,
+                    MAX_RATE_LIMIT_ATTEMPTS
+                );
+                tokio::time::sleep(wait).await;
+            }
+        }
+    }
+}
+
+/// Wait before retrying a rate-limited response. A 429 or 503 may request
+/// one through `Retry-After`, given either as seconds or as an HTTP date and
+/// capped at `MAX_RETRY_AFTER`. A 429 without a usable `Retry-After` waits
+/// `DEFAULT_RETRY_AFTER`, while such a 503 is not retried.
+fn retry_after(
+    status: reqwest::StatusCode,
+    headers: &reqwest::header::HeaderMap,
+) -> Option<Duration> {
+    if status != reqwest::StatusCode::TOO_MANY_REQUESTS
+        && status != reqwest::StatusCode::SERVICE_UNAVAILABLE
+    {
+        return None;
+    }
+
+    match requested_wait(headers) {
+        Some(wait) => Some(wait.min(MAX_RETRY_AFTER)),
+        None if status == reqwest::StatusCode::TOO_MANY_REQUESTS => Some(DEFAULT_RETRY_AFTER),
+        None => None,
+    }
+}
+
+/// Wait given by the `Retry-After` header, if present and well-formed
+fn requested_wait(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let value = headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+
+    match value.parse::<u64>() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => {
+            let date = DateTime::parse_from_rfc2822(value).ok()?;
+            Some(
+                (date.with_timezone(&Utc) - Utc::now())
+                    .to_std()
+                    .unwrap_or(Duration::ZERO),
+            )
+        }
+    }
+}
+
+fn response_too_large(max_bytes: usize) -> QuakeTrackerError {
+    QuakeTrackerError::resource_exhaustion(
+        "response_body",
+        format!(
+            "Response exceeds the {} byte limit, narrow the query or lower its limit",
+            max_bytes
+        ),
+    )
+}
+
+/// Collect a streamed response body, failing as soon as it exceeds
+/// `max_bytes` instead of buffering the whole download
+async fn read_capped_body<S, B, E>(chunks: S, max_bytes: usize) -> Result<String>
+where
+    S: Stream<Item = std::result::Result<B, E>>,
+    B: AsRef<[u8]>,
+    E: Into<QuakeTrackerError>,
+{
+    let mut chunks = std::pin::pin!(chunks);
+    let mut body = Vec::new();
+
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk.map_err(Into::into)?;
+        let chunk = chunk.as_ref();
+
+        if body.len() + chunk.len() > max_bytes {
+            return Err(response_too_large(max_bytes));
+        }
+        body.extend_from_slice(chunk);
+    }
+
+    String::from_utf8(body).map_err(|e| {
+        QuakeTrackerError::external_service(
+            "emsc_api",
+            format!("Response is not valid UTF-8: {}", e),
+        )
+    })
+}
+
+impl EventSource for RestSource {
+    async fn fetch_events(&self, params: &QueryParams) -> Result<Vec<SeismicEvent>> {
+        let body = self.fetch_raw(params).await?;
+
+        // The service answers an empty query with 204 and no body
+        if body.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let events = geojson::de::deserialize_feature_collection_str_to_vec(&body)
+            .with_operation("parse_geojson", "client")?;
+        self.prepare_events(events)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -106,12 +601,34 @@ struct InnerWssEvent {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(try_from = "InnerWssEvent", rename_all(serialize = "camelCase"))]
+#[serde(try_from = "InnerWssEvent")]
 pub struct WssEvent {
     pub action: WssAction,
     pub data: SeismicEvent,
 }
 
+impl WssEvent {
+    /// Parse a websocket message whose data is either a single feature or a
+    /// FeatureCollection batching several events under one action
+    pub fn parse_message(text: &str) -> Result<Vec<WssEvent>> {
+        let inner: InnerWssEvent = serde_json::from_str(text)?;
+
+        if inner.data.get("type").and_then(JsonValue::as_str) != Some("FeatureCollection") {
+            return Ok(vec![WssEvent::try_from(inner)?]);
+        }
+
+        let events: Vec<SeismicEvent> =
+            geojson::de::deserialize_feature_collection_str_to_vec(&inner.data.to_string())?;
+        Ok(events
+            .into_iter()
+            .map(|data| WssEvent {
+                action: inner.action.clone(),
+                data,
+            })
+            .collect())
+    }
+}
+
 impl TryFrom<InnerWssEvent> for WssEvent {
     type Error = geojson::Error;
 
@@ -127,7 +644,7 @@ impl TryFrom<InnerWssEvent> for WssEvent {
 }
 
 // Generated from: https://www.seismicportal.eu/fdsn-wsevent.html
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TimeConstraints {
     /// The start time of the query, in UTC format
     #[serde(rename = "start", skip_serializing_if = "Option::is_none")]
@@ -137,7 +654,7 @@ pub struct TimeConstraints {
     pub end_time: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct BoxAreaConstraints {
     /// The minimum latitude of the bounding box, in degrees
     #[serde(rename = "minlat", skip_serializing_if = "Option::is_none")]
@@ -153,7 +670,7 @@ pub struct BoxAreaConstraints {
     pub max_longitude: Option<f32>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CircleConstraints {
     /// The latitude of the center of the circle, in degrees
     #[serde(rename = "lat", skip_serializing_if = "Option::is_none")]
@@ -169,7 +686,7 @@ pub struct CircleConstraints {
     pub max_radius: Option<f32>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct OutputControl {
     /// The format of the output
     #[serde(rename = "format", default = "_get_json")]
@@ -179,6 +696,15 @@ struct OutputControl {
     no_data: String,
 }
 
+impl Default for OutputControl {
+    fn default() -> Self {
+        Self {
+            format: _get_json(),
+            no_data: _get_204(),
+        }
+    }
+}
+
 fn _get_json() -> String {
     "json".to_string()
 }
@@ -187,7 +713,7 @@ fn _get_204() -> String {
     "204".to_string()
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct OtherParameters {
     /// The minimum depth to include, in kilometers
     #[serde(rename = "mindepth", skip_serializing_if = "Option::is_none")]
@@ -205,8 +731,15 @@ pub struct OtherParameters {
     #[serde(rename = "magtype", skip_serializing_if = "Option::is_none")]
     pub magnitude_type: Option<String>,
     /// Whether to include all event origins
-    #[serde(rename = "includeallorigns", skip_serializing_if = "Option::is_none")]
-    pub include_all_orgins: Option<bool>,
+    ///
+    /// Earlier versions wrote this as `includeallorigns`, which the service
+    /// ignores. The old key is still accepted for persisted queries.
+    #[serde(
+        rename = "includeallorigins",
+        alias = "includeallorigns",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub include_all_origins: Option<bool>,
     /// Whether to include arrival information
     #[serde(rename = "includearrivals", skip_serializing_if = "Option::is_none")]
     pub include_arrivals: Option<bool>,
@@ -234,7 +767,128 @@ pub struct OtherParameters {
     pub updated_after: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Sort orders accepted by the FDSN `orderby` parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderBy {
+    #[serde(rename = "time")]
+    Time,
+    #[serde(rename = "time-asc")]
+    TimeAsc,
+    #[serde(rename = "magnitude")]
+    Magnitude,
+    #[serde(rename = "magnitude-asc")]
+    MagnitudeAsc,
+}
+
+impl OrderBy {
+    pub const ALL: [OrderBy; 4] = [
+        OrderBy::Time,
+        OrderBy::TimeAsc,
+        OrderBy::Magnitude,
+        OrderBy::MagnitudeAsc,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderBy::Time => "time",
+            OrderBy::TimeAsc => "time-asc",
+            OrderBy::Magnitude => "magnitude",
+            OrderBy::MagnitudeAsc => "magnitude-asc",
+        }
+    }
+}
+
+impl std::str::FromStr for OrderBy {
+    type Err = QuakeTrackerError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|order| order.as_str() == value)
+            .ok_or_else(|| {
+                let valid: Vec<&str> = Self::ALL.iter().map(OrderBy::as_str).collect();
+                QuakeTrackerError::validation(
+                    "order_by",
+                    format!(
+                        "Invalid order '{}', expected one of: {}",
+                        value,
+                        valid.join(", ")
+                    ),
+                )
+            })
+    }
+}
+
+/// Named shortcut for an EMSC `catalog`/`contributor` combination
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CatalogPreset {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub catalog: Option<&'static str>,
+    pub contributor: Option<&'static str>,
+}
+
+/// Known EMSC catalog and contributor presets
+pub const CATALOG_PRESETS: [CatalogPreset; 6] = [
+    CatalogPreset {
+        name: "emsc-rts",
+        description: "EMSC real-time catalog, all contributors",
+        catalog: Some("EMSC-RTS"),
+        contributor: None,
+    },
+    CatalogPreset {
+        name: "ingv",
+        description: "Istituto Nazionale di Geofisica e Vulcanologia (Italy)",
+        catalog: Some("EMSC-RTS"),
+        contributor: Some("INGV"),
+    },
+    CatalogPreset {
+        name: "ethz",
+        description: "Swiss Seismological Service (Switzerland)",
+        catalog: Some("EMSC-RTS"),
+        contributor: Some("ETHZ"),
+    },
+    CatalogPreset {
+        name: "noa",
+        description: "National Observatory of Athens (Greece)",
+        catalog: Some("EMSC-RTS"),
+        contributor: Some("NOA"),
+    },
+    CatalogPreset {
+        name: "koeri",
+        description: "Kandilli Observatory (Turkey)",
+        catalog: Some("EMSC-RTS"),
+        contributor: Some("KOERI"),
+    },
+    CatalogPreset {
+        name: "neic",
+        description: "USGS National Earthquake Information Center",
+        catalog: Some("EMSC-RTS"),
+        contributor: Some("NEIC"),
+    },
+];
+
+impl CatalogPreset {
+    /// Look up a preset by name, ignoring case
+    pub fn find(name: &str) -> Result<&'static CatalogPreset> {
+        CATALOG_PRESETS
+            .iter()
+            .find(|preset| preset.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                let valid: Vec<&str> = CATALOG_PRESETS.iter().map(|preset| preset.name).collect();
+                QuakeTrackerError::validation(
+                    "catalog_preset",
+                    format!(
+                        "Unknown catalog preset '{}', expected one of: {}",
+                        name,
+                        valid.join(", ")
+                    ),
+                )
+            })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct Limit(i32);
 
@@ -244,7 +898,7 @@ impl Default for Limit {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct QueryParams {
     #[serde(flatten)]
     pub time_constraints: TimeConstraints,
@@ -259,6 +913,29 @@ pub struct QueryParams {
 }
 
 impl QueryParams {
+    /// Query a single event by id, including all of its origins
+    pub fn for_event(event_id: impl Into<String>) -> Self {
+        let mut params = Self::default();
+        params.other_parameters.event_id = Some(event_id.into());
+        params.other_parameters.include_all_origins = Some(true);
+        params
+    }
+
+    /// Set `catalog` and `contributor` from a named preset, clearing a
+    /// contributor the preset does not specify
+    pub fn with_catalog_preset(mut self, name: &str) -> Result<Self> {
+        let preset = CatalogPreset::find(name)?;
+        self.other_parameters.catalog = preset.catalog.map(str::to_string);
+        self.other_parameters.contributor = preset.contributor.map(str::to_string);
+        Ok(self)
+    }
+
+    /// Set the result order using the typed FDSN values
+    pub fn with_order_by(mut self, order_by: OrderBy) -> Self {
+        self.other_parameters.order_by = Some(order_by.as_str().to_string());
+        self
+    }
+
     pub fn validate(&self) -> Result<()> {
         use crate::error::validation::*;
 
@@ -386,6 +1063,16 @@ impl QueryParams {
             }
         }
 
+        // Validate magnitude type
+        if let Some(ref magnitude_type) = self.other_parameters.magnitude_type {
+            validate_magnitude_type(magnitude_type)?;
+        }
+
+        // Validate ordering
+        if let Some(ref order_by) = self.other_parameters.order_by {
+            order_by.parse::<OrderBy>()?;
+        }
+
         // Validate limit
         if self.other_parameters.limit.0 <= 0 {
             return Err(QuakeTrackerError::validation(
@@ -420,8 +1107,156 @@ impl QueryParams {
     }
 }
 
+#[cfg(test)]
 mod test {
-    use crate::client::{QueryParams, WssAction, WssEvent};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use chrono::Utc;
+    use futures_util::stream;
+    use reqwest::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+    use reqwest::StatusCode;
+
+    use crate::client::{
+        backfill_range_internal, ingest_feature_collection, ingest_response, read_capped_body,
+        rest_source, retry_after, with_rate_limit_retries, Attempt, CatalogPreset, DepthUnit,
+        EventSource, OrderBy, QueryParams, RestSource, WssAction, WssEvent, CATALOG_PRESETS,
+        DEFAULT_RETRY_AFTER, INGEST_BATCH_SIZE,
+    };
+    use crate::error::{QuakeTrackerError, Result};
+    use crate::seismic::{MissingMagnitude, SeismicEvent};
+    use crate::state::{DataConfig, SeismicData};
+
+    /// Generates a FeatureCollection on the fly so the full document never
+    /// exists in memory
+    struct GeneratedCollection {
+        total: usize,
+        next: usize,
+        buffer: Vec<u8>,
+        position: usize,
+        finished: bool,
+    }
+
+    impl GeneratedCollection {
+        fn new(total: usize) -> Self {
+            Self {
+                total,
+                next: 0,
+                buffer: br#"{"type":"FeatureCollection","features":["#.to_vec(),
+                position: 0,
+                finished: false,
+            }
+        }
+
+        fn refill(&mut self) {
+            self.buffer.clear();
+            self.position = 0;
+
+            if self.next < self.total {
+                let separator = if self.next > 0 { "," } else { "" };
+                let feature = format!(
+                    r#"{}{{"type":"Feature","geometry":{{"type":"Point","coordinates":[22.36,38.49,-5]}},"id":"gen_{id}","properties":{{"source_id":"{id}","source_catalog":"EMSC-RTS","lastupdate":"2024-12-10T22:28:22.145984Z","time":"2024-12-10T22:25:50.4Z","flynn_region":"GREECE","lat":38.49,"lon":22.36,"depth":5,"evtype":"ke","auth":"THE","mag":2.1,"magtype":"ml","unid":"gen_{id}"}}}}"#,
+                    separator,
+                    id = self.next
+                );
+                self.buffer.extend_from_slice(feature.as_bytes());
+                self.next += 1;
+            } else if !self.finished {
+                self.buffer.extend_from_slice(b"]}");
+                self.finished = true;
+            }
+        }
+    }
+
+    impl std::io::Read for GeneratedCollection {
+        fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+            if self.position >= self.buffer.len() {
+                self.refill();
+            }
+            let available = &self.buffer[self.position..];
+            let count = available.len().min(out.len());
+            out[..count].copy_from_slice(&available[..count]);
+            self.position += count;
+            Ok(count)
+        }
+    }
+
+    /// Serves canned pages in order and records the offsets requested
+    struct PagedSource {
+        pages: Mutex<Vec<Vec<SeismicEvent>>>,
+        offsets: Mutex<Vec<Option<i32>>>,
+    }
+
+    impl PagedSource {
+        fn new(page_sizes: &[usize]) -> Self {
+            let mut next_id = 0;
+            let mut pages: Vec<Vec<SeismicEvent>> = page_sizes
+                .iter()
+                .map(|&size| {
+                    (0..size)
+                        .map(|_| {
+                            let mut event = SeismicEvent::test_event();
+                            event.id = format!("event_{}", next_id);
+                            next_id += 1;
+                            event
+                        })
+                        .collect()
+                })
+                .collect();
+            pages.reverse();
+
+            Self {
+                pages: Mutex::new(pages),
+                offsets: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    impl EventSource for PagedSource {
+        async fn fetch_events(&self, params: &QueryParams) -> Result<Vec<SeismicEvent>> {
+            self.offsets
+                .lock()
+                .unwrap()
+                .push(params.other_parameters.offset);
+            Ok(self.pages.lock().unwrap().pop().unwrap_or_default())
+        }
+    }
+
+    /// Replays canned status codes and `Retry-After` values, then succeeds
+    struct RateLimitedSource {
+        responses: Mutex<Vec<(StatusCode, Option<&'static str>)>>,
+        calls: Mutex<u32>,
+    }
+
+    impl RateLimitedSource {
+        fn new(mut responses: Vec<(StatusCode, Option<&'static str>)>) -> Self {
+            responses.reverse();
+            Self {
+                responses: Mutex::new(responses),
+                calls: Mutex::new(0),
+            }
+        }
+
+        async fn fetch(&self) -> Result<Attempt<String>> {
+            *self.calls.lock().unwrap() += 1;
+            let (status, header) = self
+                .responses
+                .lock()
+                .unwrap()
+                .pop()
+                .unwrap_or((StatusCode::OK, None));
+
+            let mut headers = HeaderMap::new();
+            if let Some(header) = header {
+                headers.insert(RETRY_AFTER, HeaderValue::from_static(header));
+            }
+
+            Ok(match retry_after(status, &headers) {
+                Some(wait) => Attempt::RetryAfter(wait),
+                None => Attempt::Done(status.to_string()),
+            })
+        }
+    }
 
     const EXAMPLE_WSS: &str = r##"
     {
@@ -468,9 +1303,554 @@ mod test {
         )
     }
 
+    #[test]
+    fn event_query_includes_origins() {
+        let params = QueryParams::for_event("20241210_0000315");
+        let serialized = serde_json::to_value(&params).unwrap();
+
+        assert_eq!(serialized["eventid"], "20241210_0000315");
+        assert_eq!(serialized["includeallorigins"], true);
+        assert!(serialized.get("includeallorigns").is_none());
+        assert_eq!(serialized["format"], "json");
+        assert!(params.validate().is_ok());
+
+        let request = reqwest::Client::new()
+            .get(super::SEISMIC_URL)
+            .query(&params)
+            .build()
+            .unwrap();
+        let query = request.url().query().unwrap();
+        assert!(query.contains("includeallorigins=true"), "{}", query);
+    }
+
+    #[test]
+    fn legacy_origins_key_still_deserializes() {
+        let params: QueryParams =
+            serde_json::from_str(r#"{"eventid":"20241210_0000315","includeallorigns":true}"#)
+                .unwrap();
+
+        assert_eq!(params.other_parameters.include_all_origins, Some(true));
+    }
+
     #[test]
     fn check_wss_serde() {
         let deserialized = serde_json::from_str::<WssEvent>(&EXAMPLE_WSS).unwrap();
         assert_eq!(deserialized.action, WssAction::Create);
     }
+
+    #[test]
+    fn validate_known_magnitude_type() {
+        let params = serde_json::from_str::<QueryParams>(r#"{"magtype":"mw"}"#).unwrap();
+
+        assert!(params.validate().is_ok());
+    }
+
+    #[test]
+    fn validate_magnitude_type_ignores_case() {
+        let params = serde_json::from_str::<QueryParams>(r#"{"magtype":"ML"}"#).unwrap();
+
+        assert!(params.validate().is_ok());
+    }
+
+    #[test]
+    fn validate_unknown_magnitude_type() {
+        let params = serde_json::from_str::<QueryParams>(r#"{"magtype":"mww2"}"#).unwrap();
+
+        let error = params.validate().unwrap_err();
+        assert_eq!(error.category(), "validation");
+        assert!(error.to_string().contains("mww2"));
+    }
+
+    #[test]
+    fn validate_valid_order_by() {
+        for order in ["time", "time-asc", "magnitude", "magnitude-asc"] {
+            let query = format!(r#"{{"orderby":"{}"}}"#, order);
+            let params = serde_json::from_str::<QueryParams>(&query).unwrap();
+
+            assert!(params.validate().is_ok(), "{} should be accepted", order);
+        }
+    }
+
+    #[test]
+    fn validate_invalid_order_by() {
+        let params = serde_json::from_str::<QueryParams>(r#"{"orderby":"depth"}"#).unwrap();
+
+        let error = params.validate().unwrap_err();
+        assert_eq!(error.category(), "validation");
+        assert!(error
+            .to_string()
+            .contains("time, time-asc, magnitude, magnitude-asc"));
+    }
+
+    #[test]
+    fn typed_order_by_serializes_to_fdsn() {
+        for order in OrderBy::ALL {
+            let params = serde_json::from_str::<QueryParams>("{}")
+                .unwrap()
+                .with_order_by(order);
+
+            let serialized = serde_json::to_value(&params).unwrap();
+            assert_eq!(serialized["orderby"], order.as_str());
+            assert_eq!(serde_json::to_value(order).unwrap(), order.as_str());
+            assert!(params.validate().is_ok());
+        }
+    }
+
+    #[test]
+    fn catalog_preset_expands_into_query() {
+        let params = QueryParams::default().with_catalog_preset("INGV").unwrap();
+        let serialized = serde_json::to_value(&params).unwrap();
+
+        assert_eq!(serialized["catalog"], "EMSC-RTS");
+        assert_eq!(serialized["contributor"], "INGV");
+        assert!(params.validate().is_ok());
+
+        let params = params.with_catalog_preset("emsc-rts").unwrap();
+        let serialized = serde_json::to_value(&params).unwrap();
+        assert_eq!(serialized["catalog"], "EMSC-RTS");
+        assert!(serialized.get("contributor").is_none());
+    }
+
+    #[test]
+    fn unknown_catalog_preset_lists_names() {
+        let error = CatalogPreset::find("nowhere").unwrap_err();
+
+        assert_eq!(error.category(), "validation");
+        for preset in CATALOG_PRESETS {
+            assert!(error.to_string().contains(preset.name));
+        }
+    }
+
+    #[tokio::test]
+    async fn fetch_all_events_follows_pages() {
+        let source = PagedSource::new(&[3, 3, 1]);
+        let base = serde_json::from_str::<QueryParams>("{}").unwrap();
+
+        let events = source.fetch_all_events(base, 3).await.unwrap();
+
+        assert_eq!(events.len(), 7);
+        assert_eq!(events[0].id, "event_0");
+        assert_eq!(events[6].id, "event_6");
+        assert_eq!(
+            *source.offsets.lock().unwrap(),
+            vec![Some(0), Some(3), Some(6)]
+        );
+    }
+
+    #[tokio::test]
+    async fn backfill_adds_only_new_events() {
+        let state = Mutex::new(SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        }));
+        let known = ["event_1", "event_3"].map(|id| {
+            let mut event = SeismicEvent::test_event();
+            event.id = id.to_string();
+            event
+        });
+        state.lock().unwrap().add_events(known.to_vec()).unwrap();
+
+        let source = PagedSource::new(&[5]);
+        let start = Utc::now() - chrono::TimeDelta::days(7);
+        let end = Utc::now();
+
+        let added = backfill_range_internal(&source, &state, start, end)
+            .await
+            .unwrap();
+
+        assert_eq!(added, 3);
+        assert_eq!(state.lock().unwrap().get_stats().total_events, 5);
+
+        let again = PagedSource::new(&[5]);
+        let added = backfill_range_internal(&again, &state, start, end)
+            .await
+            .unwrap();
+        assert_eq!(added, 0);
+
+        let error = backfill_range_internal(&source, &state, end, start)
+            .await
+            .unwrap_err();
+        assert_eq!(error.category(), "validation");
+    }
+
+    #[tokio::test]
+    async fn fetch_all_events_rejects_zero_page_size() {
+        let source = PagedSource::new(&[]);
+        let base = serde_json::from_str::<QueryParams>("{}").unwrap();
+
+        assert!(source.fetch_all_events(base, 0).await.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_limited_fetch_honors_retry_after() {
+        let source = RateLimitedSource::new(vec![(StatusCode::TOO_MANY_REQUESTS, Some("2"))]);
+        let start = tokio::time::Instant::now();
+
+        let body = with_rate_limit_retries(|| source.fetch()).await.unwrap();
+
+        assert_eq!(body, "200 OK");
+        assert_eq!(*source.calls.lock().unwrap(), 2);
+        assert!(start.elapsed() >= Duration::from_secs(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn persistent_rate_limit_is_external_service_error() {
+        let source = RateLimitedSource::new(vec![
+            (StatusCode::SERVICE_UNAVAILABLE, Some("1")),
+            (StatusCode::TOO_MANY_REQUESTS, Some("1")),
+            (StatusCode::TOO_MANY_REQUESTS, Some("1")),
+        ]);
+
+        let error = with_rate_limit_retries(|| source.fetch())
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.category(), "external_service");
+        assert_eq!(*source.calls.lock().unwrap(), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_limit_without_retry_after_uses_default_backoff() {
+        let source = RateLimitedSource::new(vec![(StatusCode::TOO_MANY_REQUESTS, None)]);
+        let start = tokio::time::Instant::now();
+
+        let body = with_rate_limit_retries(|| source.fetch()).await.unwrap();
+
+        assert_eq!(body, "200 OK");
+        assert_eq!(*source.calls.lock().unwrap(), 2);
+        assert!(start.elapsed() >= DEFAULT_RETRY_AFTER);
+    }
+
+    #[test]
+    fn retry_after_requires_rate_limit_status() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("5"));
+        let mut malformed = HeaderMap::new();
+        malformed.insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+
+        assert_eq!(retry_after(StatusCode::BAD_GATEWAY, &headers), None);
+        assert_eq!(
+            retry_after(StatusCode::TOO_MANY_REQUESTS, &HeaderMap::new()),
+            Some(DEFAULT_RETRY_AFTER)
+        );
+        assert_eq!(
+            retry_after(StatusCode::TOO_MANY_REQUESTS, &malformed),
+            Some(DEFAULT_RETRY_AFTER)
+        );
+        assert_eq!(
+            retry_after(StatusCode::SERVICE_UNAVAILABLE, &HeaderMap::new()),
+            None
+        );
+        assert_eq!(
+            retry_after(StatusCode::SERVICE_UNAVAILABLE, &headers),
+            Some(Duration::from_secs(5))
+        );
+    }
+
+    #[tokio::test]
+    async fn capped_body_within_limit() {
+        let chunks = stream::iter(vec![
+            Ok::<_, QuakeTrackerError>(b"{\"type\":".to_vec()),
+            Ok(b"\"FeatureCollection\"}".to_vec()),
+        ]);
+
+        let body = read_capped_body(chunks, 1024).await.unwrap();
+
+        assert_eq!(body, "{\"type\":\"FeatureCollection\"}");
+    }
+
+    #[tokio::test]
+    async fn capped_body_rejects_oversized_response() {
+        let chunks = stream::iter((0..8).map(|_| Ok::<_, QuakeTrackerError>(vec![b' '; 1024])));
+
+        let error = read_capped_body(chunks, 4096).await.unwrap_err();
+
+        assert_eq!(error.category(), "resource_exhaustion");
+    }
+
+    #[test]
+    fn rest_source_follows_config() {
+        let state = Mutex::new(SeismicData::new());
+        state.lock().unwrap().set_max_response_bytes(4096).unwrap();
+        state.lock().unwrap().set_depth_unit(DepthUnit::Meters);
+
+        let source = rest_source(&state).unwrap();
+
+        assert_eq!(source.max_response_bytes, 4096);
+        assert_eq!(source.depth_unit, DepthUnit::Meters);
+        let error = state.lock().unwrap().set_max_response_bytes(0).unwrap_err();
+        assert_eq!(error.category(), "validation");
+    }
+
+    #[test]
+    fn ingest_large_collection_in_bounded_batches() {
+        let mut batch_sizes = Vec::new();
+        let mut last_id = String::new();
+
+        let total = ingest_feature_collection(GeneratedCollection::new(25_000), 1_000, |batch| {
+            batch_sizes.push(batch.len());
+            last_id = batch.last().unwrap().id.clone();
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(total, 25_000);
+        assert_eq!(batch_sizes.len(), 25);
+        assert!(batch_sizes.iter().all(|&size| size == 1_000));
+        assert_eq!(last_id, "gen_24999");
+    }
+
+    #[test]
+    fn ingest_keeps_partial_final_batch() {
+        let mut batch_sizes = Vec::new();
+
+        let total = ingest_feature_collection(GeneratedCollection::new(2_500), 1_000, |batch| {
+            batch_sizes.push(batch.len());
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(total, 2_500);
+        assert_eq!(batch_sizes, vec![1_000, 1_000, 500]);
+    }
+
+    #[test]
+    fn ingest_rejects_malformed_feature() {
+        let body =
+            r#"{"type":"FeatureCollection","features":[{"type":"Feature","properties":{}}]}"#;
+
+        let result = ingest_feature_collection(body.as_bytes(), 10, |_| Ok(()));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn ingest_response_keeps_batches_before_malformed_feature() {
+        let feature = |id: usize| {
+            format!(
+                r#"{{"type":"Feature","geometry":{{"type":"Point","coordinates":[22.36,38.49,-12.5]}},"id":"{id}","properties":{{"source_id":"{id}","source_catalog":"EMSC-RTS","lastupdate":"2024-12-10T22:28:22.145984Z","time":"2024-12-10T22:25:50.4Z","flynn_region":"GREECE","lat":38.49,"lon":22.36,"depth":12.5,"evtype":"ke","auth":"THE","mag":2.1,"magtype":"ml","unid":"{id}"}}}}"#
+            )
+        };
+        let mut features: Vec<String> = (0..INGEST_BATCH_SIZE + 1).map(feature).collect();
+        features.push(r#"{"type":"Feature","properties":{}}"#.to_string());
+        let body = format!(
+            r#"{{"type":"FeatureCollection","features":[{}]}}"#,
+            features.join(",")
+        );
+        let state = Mutex::new(SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        }));
+
+        let result = ingest_response(&RestSource::default(), &state, body);
+
+        assert!(result.is_err());
+        // The full batch before the error stays stored, the partial one doesn't
+        assert_eq!(
+            state.lock().unwrap().get_stats().total_events,
+            INGEST_BATCH_SIZE
+        );
+    }
+
+    #[test]
+    fn ingest_response_accepts_empty_body() {
+        let state = Mutex::new(SeismicData::new());
+
+        let returned = ingest_response(&RestSource::default(), &state, String::new()).unwrap();
+
+        assert!(returned.is_empty());
+        assert_eq!(state.lock().unwrap().get_stats().total_events, 0);
+    }
+
+    #[test]
+    fn ingest_response_rewrites_every_batch() {
+        let feature = |id: usize| {
+            format!(
+                r#"{{"type":"Feature","geometry":{{"type":"Point","coordinates":[22.36,38.49,-12500]}},"id":"{id}","properties":{{"source_id":"{id}","source_catalog":"EMSC-RTS","lastupdate":"2024-12-10T22:28:22.145984Z","time":"2024-12-10T22:25:50.4Z","flynn_region":"GREECE","lat":38.49,"lon":22.36,"depth":12500,"evtype":"ke","auth":"THE","mag":2.1,"magtype":"ml","unid":"{id}"}}}}"#
+            )
+        };
+        let features: Vec<String> = (0..INGEST_BATCH_SIZE + 2).map(feature).collect();
+        let body = format!(
+            r#"{{"type":"FeatureCollection","features":[{}]}}"#,
+            features.join(",")
+        );
+        let state = Mutex::new(SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        }));
+        let source = RestSource::default().with_depth_unit(DepthUnit::Meters);
+
+        let returned = ingest_response(&source, &state, body).unwrap();
+
+        let returned: Vec<SeismicEvent> =
+            geojson::de::deserialize_feature_collection_str_to_vec(&returned).unwrap();
+        assert_eq!(returned.len(), INGEST_BATCH_SIZE + 2);
+        assert!(returned.iter().all(|event| event.depth == 12.5));
+        assert_eq!(
+            state.lock().unwrap().get_stats().total_events,
+            INGEST_BATCH_SIZE + 2
+        );
+    }
+
+    #[test]
+    fn ingest_converts_meter_depths_to_km() {
+        let body = r#"{"type":"FeatureCollection","features":[{"type":"Feature","geometry":{"type":"Point","coordinates":[22.36,38.49,-12.5]},"id":"20241210_0000315","properties":{"source_id":"1741829","source_catalog":"EMSC-RTS","lastupdate":"2024-12-10T22:28:22.145984Z","time":"2024-12-10T22:25:50.4Z","flynn_region":"GREECE","lat":38.49,"lon":22.36,"depth":12500,"evtype":"ke","auth":"THE","mag":2.1,"magtype":"ml","unid":"20241210_0000315"}}]}"#;
+        let source = RestSource::default().with_depth_unit(DepthUnit::Meters);
+        let mut state = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+
+        ingest_feature_collection(body.as_bytes(), 10, |batch| {
+            state.add_events(source.normalize_depths(batch)?)
+        })
+        .unwrap();
+
+        let events = state.get_events().unwrap();
+        assert_eq!(events.len(), 1);
+        assert!((events[0].depth - 12.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn prune_origins_keeps_only_best_origin() {
+        let origin = |source_id: &str, stations: i32, gap: f64| {
+            serde_json::json!({
+                "Source_id": source_id,
+                "Source_catalog": "EMSC-RTS",
+                "Lastupdate": "2024-12-10T22:28:22Z",
+                "Time": "2024-12-10T22:25:50Z",
+                "Lat": 38.49,
+                "Lon": 22.36,
+                "Depth": 12.5,
+                "Nsta": stations,
+                "Gap": gap,
+                "Sdepth": 1.5
+            })
+        };
+        let body = serde_json::json!({
+            "type": "FeatureCollection",
+            "features": [{
+                "type": "Feature",
+                "geometry": { "type": "Point", "coordinates": [22.36, 38.49, -12.5] },
+                "id": "20241210_0000315",
+                "properties": {
+                    "source_id": "1741829", "source_catalog": "EMSC-RTS",
+                    "lastupdate": "2024-12-10T22:28:22.145984Z", "time": "2024-12-10T22:25:50.4Z",
+                    "flynn_region": "GREECE", "lat": 38.49, "lon": 22.36, "depth": 12.5,
+                    "evtype": "ke", "auth": "THE", "mag": 2.1, "magtype": "ml",
+                    "unid": "20241210_0000315",
+                    "origins": {
+                        "geometry": { "type": "Point", "coordinates": [22.36, 38.49] },
+                        "origins": [
+                            origin("1741826", 8, 210.0),
+                            origin("1741829", 42, 65.0),
+                            origin("1741833", 12, 180.0)
+                        ]
+                    }
+                }
+            }]
+        })
+        .to_string();
+
+        let fetch = |source: RestSource| {
+            let mut fetched = Vec::new();
+            ingest_feature_collection(body.as_bytes(), 10, |batch| {
+                fetched.extend(source.prune_origins(batch));
+                Ok(())
+            })
+            .unwrap();
+            fetched.pop().unwrap()
+        };
+
+        let full = fetch(RestSource::default());
+        assert_eq!(full.origins.as_ref().unwrap().origins.len(), 3);
+
+        let pruned = fetch(RestSource::default().with_best_origin_only(true));
+        let origins = &pruned.origins.as_ref().unwrap().origins;
+        assert_eq!(origins.len(), 1);
+        assert_eq!(origins[0].source_id, "1741829");
+        assert_eq!(origins[0].number_of_stations, Some(42));
+        assert_eq!(origins[0].azimuthal_gap, Some(65.0));
+        assert_eq!(
+            pruned.uncertainty().unwrap().depth_uncertainty,
+            full.uncertainty().unwrap().depth_uncertainty
+        );
+
+        // The frontend gets the pruned events rather than the raw body
+        let state = Mutex::new(SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        }));
+        state.lock().unwrap().set_best_origin_only(true);
+        let returned = ingest_response(&rest_source(&state).unwrap(), &state, body).unwrap();
+        let returned: Vec<SeismicEvent> =
+            geojson::de::deserialize_feature_collection_str_to_vec(&returned).unwrap();
+        assert_eq!(returned.len(), 1);
+        assert_eq!(returned[0].origins.as_ref().unwrap().origins.len(), 1);
+        assert_eq!(state.lock().unwrap().get_stats().total_events, 1);
+    }
+
+    #[test]
+    fn null_magnitude_does_not_fail_batch() {
+        let feature = |id: &str, mag: serde_json::Value| {
+            serde_json::json!({
+                "type": "Feature",
+                "geometry": { "type": "Point", "coordinates": [22.36, 38.49, -12.5] },
+                "id": id,
+                "properties": {
+                    "source_id": "1741829", "source_catalog": "EMSC-RTS",
+                    "lastupdate": "2024-12-10T22:28:22.145984Z", "time": "2024-12-10T22:25:50.4Z",
+                    "flynn_region": "GREECE", "lat": 38.49, "lon": 22.36, "depth": 12.5,
+                    "evtype": "ke", "auth": "THE", "mag": mag, "magtype": "ml",
+                    "unid": id
+                }
+            })
+        };
+        let body = serde_json::json!({
+            "type": "FeatureCollection",
+            "features": [
+                feature("first", serde_json::json!(2.1)),
+                feature("unmeasured", serde_json::Value::Null),
+                feature("last", serde_json::json!(3.4))
+            ]
+        })
+        .to_string();
+
+        let fetch = |missing_magnitude: MissingMagnitude| {
+            let mut state = SeismicData::with_config(DataConfig {
+                auto_cleanup: false,
+                missing_magnitude,
+                ..Default::default()
+            });
+            let source = RestSource::default();
+            let parsed = ingest_feature_collection(body.as_bytes(), 10, |batch| {
+                state.add_events(source.prepare_events(batch)?)
+            })
+            .unwrap();
+            assert_eq!(parsed, 3);
+            state.get_events().unwrap()
+        };
+
+        let skipped = fetch(MissingMagnitude::Skip);
+        let ids: Vec<_> = skipped.iter().map(|event| event.id.as_str()).collect();
+        assert_eq!(ids, ["first", "last"]);
+        assert_eq!(skipped[0].magnitude, 2.1);
+        assert_eq!(skipped[1].magnitude, 3.4);
+
+        let filled = fetch(MissingMagnitude::Fill(0.0));
+        let magnitudes: Vec<_> = filled.iter().map(|event| event.magnitude).collect();
+        assert_eq!(magnitudes, [2.1, 0.0, 3.4]);
+    }
+
+    #[test]
+    fn normalize_depths_rejects_out_of_range_conversion() {
+        let mut event = SeismicEvent::test_event();
+        event.depth = 5_000_000.0;
+
+        let result = RestSource::default()
+            .with_depth_unit(DepthUnit::Meters)
+            .normalize_depths(vec![event]);
+
+        assert_eq!(result.unwrap_err().category(), "validation");
+    }
 }
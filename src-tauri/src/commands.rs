@@ -1,13 +1,18 @@
-use chrono::NaiveDate;
-use futures_util::StreamExt;
+use std::collections::BTreeMap;
+
+use chrono::{FixedOffset, NaiveDate};
 use tauri::ipc::Channel;
-use tokio::time::{sleep, Duration};
-use tokio_tungstenite::connect_async;
-use tokio_tungstenite::tungstenite::client::IntoClientRequest;
-use tokio_tungstenite::tungstenite::Message;
 
-use crate::client::{ClientResult, QueryParams, WssEvent, SEISMIC_WSS_URL};
-use crate::{analytics, client, AppState};
+use crate::analytics::incremental::ColumnInfo;
+use crate::analytics::processors::GridBinning;
+use crate::client::{CatalogPreset, ClientError, ClientResult, DepthUnit, QueryParams, WssEvent};
+use crate::error::QuakeTrackerError;
+use crate::filter::EventFilter;
+use crate::listener::{EventPayload, ListenerState, ListenerStatus, ReconnectConfig};
+use crate::report::CatalogReport;
+use crate::seismic::{LocalizedEvent, LocationUncertainty, MissingMagnitude, SeismicEvent};
+use crate::state::{ColumnarEvents, CoverageGap, MergePolicy, NearestNeighborSummary, Period};
+use crate::{analytics, client, demo, listener, AppState};
 
 #[tauri::command]
 pub fn get_magnitude_distribution(
@@ -16,6 +21,22 @@ pub fn get_magnitude_distribution(
     analytics::get_magnitude_distribution_internal(state.inner())
 }
 
+#[tauri::command]
+pub fn get_magnitude_distribution_above(
+    state: tauri::State<'_, AppState>,
+    min_magnitude: Option<f64>,
+) -> Result<Vec<(String, u32)>, String> {
+    analytics::get_magnitude_distribution_above_internal(state.inner(), min_magnitude)
+}
+
+#[tauri::command]
+pub fn set_auto_magnitude_bins(
+    state: tauri::State<'_, AppState>,
+    enabled: bool,
+) -> Result<(), String> {
+    analytics::set_auto_magnitude_bins_internal(state.inner(), enabled)
+}
+
 #[tauri::command]
 pub fn get_count_by_year(
     state: tauri::State<'_, AppState>,
@@ -24,15 +45,72 @@ pub fn get_count_by_year(
 }
 
 #[tauri::command]
-pub fn get_mag_depth_pairs(state: tauri::State<'_, AppState>) -> Result<Vec<(f64, f64)>, String> {
-    analytics::get_mag_depth_pairs_internal(state.inner())
+pub fn get_mag_depth_pairs(state: tauri::State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let result = analytics::get_mag_depth_pairs_internal(state.inner())?;
+    analytics::with_precision(state.inner(), &result)
+}
+
+#[tauri::command]
+pub fn get_mag_depth_arrow(
+    state: tauri::State<'_, AppState>,
+) -> ClientResult<tauri::ipc::Response> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(tauri::ipc::Response::new(state.get_mag_depth_arrow()?))
 }
 
 #[tauri::command]
 pub fn get_advanced_analytics(
     state: tauri::State<'_, AppState>,
 ) -> Result<serde_json::Value, String> {
-    analytics::get_advanced_analytics_internal(state.inner())
+    let result = analytics::get_advanced_analytics_internal(state.inner())?;
+    analytics::with_precision(state.inner(), &result)
+}
+
+#[tauri::command]
+pub fn get_advanced_analytics_with(
+    state: tauri::State<'_, AppState>,
+    region_limit: Option<usize>,
+) -> Result<serde_json::Value, String> {
+    let result = analytics::get_advanced_analytics_with_internal(
+        state.inner(),
+        region_limit.unwrap_or(analytics::incremental::DEFAULT_REGION_LIMIT),
+    )?;
+    analytics::with_precision(state.inner(), &result)
+}
+
+#[tauri::command]
+pub fn get_analytics_since(
+    state: tauri::State<'_, AppState>,
+    since: chrono::DateTime<chrono::Utc>,
+) -> ClientResult<serde_json::Value> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    let result = state.get_analytics_since(since)?;
+    analytics::rounded_json(&result, state.precision_decimals()).map_err(ClientError::Internal)
+}
+
+#[tauri::command]
+pub fn get_analytics_in_polygon(
+    state: tauri::State<'_, AppState>,
+    polygon: Vec<(f64, f64)>,
+) -> ClientResult<serde_json::Value> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    let result = state.get_analytics_in_polygon(polygon)?;
+    analytics::rounded_json(&result, state.precision_decimals()).map_err(ClientError::Internal)
+}
+
+#[tauri::command]
+pub fn analyze_geojson(
+    state: tauri::State<'_, AppState>,
+    geojson: String,
+) -> ClientResult<serde_json::Value> {
+    let result = analytics::analyze_geojson_internal(&geojson)?;
+    analytics::with_precision(state.inner(), &result).map_err(ClientError::Internal)
 }
 
 #[tauri::command]
@@ -45,6 +123,16 @@ pub fn get_data_stats(state: tauri::State<'_, AppState>) -> Result<serde_json::V
     serde_json::to_value(stats).map_err(|e| format!("Failed to serialize stats: {}", e))
 }
 
+#[tauri::command]
+pub fn get_thinned_aftershocks(
+    state: tauri::State<'_, AppState>,
+) -> ClientResult<BTreeMap<String, usize>> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.thinned_aftershocks())
+}
+
 #[tauri::command]
 pub async fn get_seismic_events(
     state: tauri::State<'_, AppState>,
@@ -61,134 +149,309 @@ pub async fn get_seismic_events(
     Ok(tauri::ipc::Response::new(events))
 }
 
+#[tauri::command]
+pub async fn get_event_uncertainty(
+    state: tauri::State<'_, AppState>,
+    event_id: String,
+) -> ClientResult<Option<LocationUncertainty>> {
+    client::get_event_uncertainty_internal(state.inner(), event_id).await
+}
+
+#[tauri::command]
+pub async fn get_evaluation_mode_counts(
+    state: tauri::State<'_, AppState>,
+    query_params: QueryParams,
+) -> ClientResult<BTreeMap<String, usize>> {
+    client::get_evaluation_mode_counts_internal(state.inner(), query_params).await
+}
+
+#[tauri::command]
+pub fn list_catalog_presets() -> Vec<CatalogPreset> {
+    client::CATALOG_PRESETS.to_vec()
+}
+
+#[tauri::command]
+pub async fn backfill_range(
+    state: tauri::State<'_, AppState>,
+    start: chrono::DateTime<chrono::Utc>,
+    end: chrono::DateTime<chrono::Utc>,
+) -> ClientResult<usize> {
+    let source = client::rest_source(state.inner())?;
+    Ok(client::backfill_range_internal(&source, state.inner(), start, end).await?)
+}
+
 // https://www.seismicportal.eu/realtime.html
 #[tauri::command]
 pub async fn listen_to_seismic_events(
     state: tauri::State<'_, AppState>,
-    on_event: Channel<WssEvent>,
+    listener: tauri::State<'_, ListenerState>,
+    on_event: Channel<EventPayload>,
+    on_status: Option<Channel<ListenerStatus>>,
 ) -> ClientResult<()> {
-    log::info!("Starting WebSocket connection to EMSC with retry logic");
-
-    const MAX_RETRIES: u32 = 5;
-    const INITIAL_DELAY_MS: u64 = 1000;
-
-    let mut retry_count = 0;
-    let mut delay = INITIAL_DELAY_MS;
-
-    while retry_count < MAX_RETRIES {
-        match connect_and_listen(&state, &on_event).await {
-            Ok(_) => {
-                log::debug!("WebSocket connection closed gracefully");
-                return Ok(());
-            }
-            Err(e) => {
-                retry_count += 1;
-                log::error!(
-                    "WebSocket connection failed (attempt {}/{}): {}",
-                    retry_count,
-                    MAX_RETRIES,
-                    e
-                );
-
-                if retry_count >= MAX_RETRIES {
-                    log::error!("Max retry attempts reached, giving up");
-                    return Err(e);
-                }
-
-                log::debug!("Retrying in {}ms...", delay);
-                sleep(Duration::from_millis(delay)).await;
-
-                // Exponential backoff with cap at 30 seconds
-                delay = std::cmp::min(delay * 2, 30000);
-            }
-        }
-    }
+    listener::listen_internal(
+        state.inner(),
+        listener.inner(),
+        &on_event,
+        on_status.as_ref(),
+    )
+    .await
+}
 
-    Err(client::ClientError::Network(
-        "Failed to connect after all retries".to_string(),
-    ))
+#[tauri::command]
+pub fn stop_listening(listener: tauri::State<'_, ListenerState>) {
+    listener.request_stop();
+}
+
+#[tauri::command]
+pub async fn replay_events(
+    state: tauri::State<'_, AppState>,
+    listener: tauri::State<'_, ListenerState>,
+    channel: Channel<WssEvent>,
+    speed: f64,
+) -> ClientResult<usize> {
+    listener::replay_internal(state.inner(), listener.inner(), &channel, speed).await
+}
+
+#[tauri::command]
+pub fn stop_replay(listener: tauri::State<'_, ListenerState>) {
+    listener.request_replay_stop();
 }
 
-async fn connect_and_listen(
-    state: &tauri::State<'_, AppState>,
-    on_event: &Channel<WssEvent>,
+#[tauri::command]
+pub fn set_reconnect_config(
+    listener: tauri::State<'_, ListenerState>,
+    config: ReconnectConfig,
 ) -> ClientResult<()> {
-    let request = SEISMIC_WSS_URL.into_client_request().map_err(|e| {
-        crate::client::ClientError::Network(format!("Invalid WebSocket URL: {}", e))
-    })?;
-
-    let (mut stream, _response) = connect_async(request).await.map_err(|e| {
-        crate::client::ClientError::Network(format!("WebSocket connection failed: {}", e))
-    })?;
-
-    log::info!("WebSocket connected successfully");
-
-    while let Some(msg) = stream.next().await {
-        match msg {
-            Ok(Message::Text(text)) => {
-                match handle_websocket_message(&text, state, on_event).await {
-                    Ok(_) => {}
-                    Err(e) => {
-                        log::error!("Error handling WebSocket message: {}", e);
-                    }
-                }
-            }
-            Ok(Message::Close(_)) => {
-                log::info!("WebSocket closed by server");
-                break;
-            }
-            Ok(_) => {
-                log::warn!("Received unexpected message");
-            }
-            Err(e) => {
-                log::error!("WebSocket error: {}", e);
-                return Err(crate::client::ClientError::Network(format!(
-                    "WebSocket error: {}",
-                    e
-                )));
-            }
-        }
-    }
+    Ok(listener.set_reconnect_config(config)?)
+}
 
-    Ok(())
+#[tauri::command]
+pub fn set_event_coalescing(
+    listener: tauri::State<'_, ListenerState>,
+    window_ms: Option<u64>,
+) -> ClientResult<()> {
+    Ok(listener.set_coalesce_window_ms(window_ms)?)
 }
 
-async fn handle_websocket_message(
-    text: &str,
-    state: &tauri::State<'_, AppState>,
-    on_event: &Channel<WssEvent>,
+#[tauri::command]
+pub fn set_listener_send_retries(
+    listener: tauri::State<'_, ListenerState>,
+    retries: u32,
 ) -> ClientResult<()> {
-    log::trace!("Received WebSocket message: {}", text);
+    Ok(listener.set_send_retries(retries)?)
+}
 
-    let wss_event: WssEvent = serde_json::from_str(text).map_err(|e| {
-        crate::client::ClientError::Parse(format!("Failed to parse WebSocket message: {}", e))
-    })?;
+#[tauri::command]
+pub fn set_min_emit_magnitude(
+    listener: tauri::State<'_, ListenerState>,
+    magnitude: Option<f64>,
+) -> ClientResult<()> {
+    Ok(listener.set_min_emit_magnitude(magnitude)?)
+}
 
-    log::debug!("Parsed WebSocket event: {:?}", wss_event);
+#[tauri::command]
+pub fn get_events_after_seq(
+    state: tauri::State<'_, AppState>,
+    seq: u64,
+) -> ClientResult<Vec<(u64, SeismicEvent)>> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.get_events_after_seq(seq)?)
+}
 
-    // Add event to state
-    {
-        let mut state_guard = state.lock().map_err(|e| {
-            crate::client::ClientError::Internal(format!("Failed to acquire state lock: {}", e))
-        })?;
+#[tauri::command]
+pub fn get_events_in_magnitude_range(
+    state: tauri::State<'_, AppState>,
+    min_magnitude: f64,
+    max_magnitude: f64,
+) -> ClientResult<Vec<SeismicEvent>> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.get_events_in_magnitude_range(min_magnitude, max_magnitude)?)
+}
 
-        state_guard
-            .add_or_update_event(wss_event.data.clone())
-            .map_err(|e| {
-                crate::client::ClientError::Internal(format!("Failed to add event to state: {}", e))
-            })?;
-    }
+#[tauri::command]
+pub fn get_events_in_polygon(
+    state: tauri::State<'_, AppState>,
+    polygon: Vec<(f64, f64)>,
+) -> ClientResult<Vec<SeismicEvent>> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.get_events_in_polygon(polygon)?)
+}
 
-    // Send event to frontend
-    if let Err(e) = on_event.send(wss_event) {
-        log::error!("Failed to send event to frontend: {}", e);
-        return Err(crate::client::ClientError::Internal(format!(
-            "Failed to send event to frontend: {}",
-            e
-        )));
-    }
+#[tauri::command]
+pub fn get_events_local_time(
+    state: tauri::State<'_, AppState>,
+    utc_offset_hours: Option<i32>,
+) -> ClientResult<Vec<LocalizedEvent>> {
+    let offset = utc_offset_hours
+        .map(|hours| {
+            FixedOffset::east_opt(hours * 3600)
+                .filter(|_| (-12..=14).contains(&hours))
+                .ok_or_else(|| {
+                    QuakeTrackerError::validation(
+                        "utc_offset_hours",
+                        format!(
+                            "UTC offset must be between -12 and +14 hours, got {}",
+                            hours
+                        ),
+                    )
+                })
+        })
+        .transpose()?;
 
-    Ok(())
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state
+        .get_chronological_events()?
+        .into_iter()
+        .map(|event| LocalizedEvent::new(event, offset))
+        .collect())
+}
+
+#[tauri::command]
+pub fn flag_magnitude_outliers(
+    state: tauri::State<'_, AppState>,
+    z_threshold: f64,
+) -> ClientResult<Vec<String>> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.flag_magnitude_outliers(z_threshold)?)
+}
+
+#[tauri::command]
+pub fn get_events_with_distance(
+    state: tauri::State<'_, AppState>,
+    latitude: f64,
+    longitude: f64,
+    filter: Option<EventFilter>,
+) -> ClientResult<Vec<(SeismicEvent, f64, f64)>> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    let filter = filter.unwrap_or_default();
+    Ok(state.get_events_with_distance(latitude, longitude, &filter)?)
+}
+
+#[tauri::command]
+pub fn get_recent_events(
+    state: tauri::State<'_, AppState>,
+    count: usize,
+) -> Result<Vec<SeismicEvent>, String> {
+    analytics::get_recent_events_internal(state.inner(), count)
+}
+
+#[tauri::command]
+pub fn get_events_columnar(
+    state: tauri::State<'_, AppState>,
+    filter: Option<EventFilter>,
+) -> ClientResult<ColumnarEvents> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.get_events_columnar(filter.unwrap_or_default())?)
+}
+
+#[tauri::command]
+pub fn get_data_bounds(
+    state: tauri::State<'_, AppState>,
+) -> ClientResult<Option<(f64, f64, f64, f64)>> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.get_bounding_box()?)
+}
+
+#[tauri::command]
+pub fn get_depth_by_magtype(
+    state: tauri::State<'_, AppState>,
+) -> ClientResult<Vec<(String, f64, f64)>> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.get_depth_by_magtype()?)
+}
+
+#[tauri::command]
+pub fn get_region_last_activity(
+    state: tauri::State<'_, AppState>,
+) -> ClientResult<Vec<(String, chrono::DateTime<chrono::Utc>, u32)>> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.get_region_last_activity()?)
+}
+
+#[tauri::command]
+pub fn get_quality_events(
+    state: tauri::State<'_, AppState>,
+    min_stations: i32,
+    max_gap: f64,
+    include_missing: Option<bool>,
+) -> ClientResult<Vec<SeismicEvent>> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.get_quality_events_with(min_stations, max_gap, include_missing.unwrap_or(false))?)
+}
+
+#[tauri::command]
+pub fn get_nearest_neighbor_summary(
+    state: tauri::State<'_, AppState>,
+) -> ClientResult<Option<NearestNeighborSummary>> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.get_nearest_neighbor_summary()?)
+}
+
+#[tauri::command]
+pub fn get_activity_centroid(
+    state: tauri::State<'_, AppState>,
+    filter: Option<EventFilter>,
+    energy_weighted: Option<bool>,
+) -> ClientResult<Option<(f64, f64)>> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    let filter = filter.unwrap_or_default();
+    Ok(state.get_activity_centroid(&filter, energy_weighted.unwrap_or(false))?)
+}
+
+#[tauri::command]
+pub fn get_largest_event_energy_fraction(
+    state: tauri::State<'_, AppState>,
+    filter: Option<EventFilter>,
+) -> ClientResult<f64> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.largest_event_energy_fraction(filter.unwrap_or_default())?)
+}
+
+#[tauri::command]
+pub fn stream_events(
+    state: tauri::State<'_, AppState>,
+    channel: Channel<Vec<SeismicEvent>>,
+    chunk_size: usize,
+    filter: Option<EventFilter>,
+) -> ClientResult<usize> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    let filter = filter.unwrap_or_default();
+
+    Ok(state.for_each_event_chunk(&filter, chunk_size, |chunk| {
+        channel.send(chunk).map_err(|e| {
+            QuakeTrackerError::internal(format!("Failed to send events to frontend: {}", e))
+        })
+    })?)
 }
 
 #[tauri::command]
@@ -201,6 +464,287 @@ pub fn recompute_analytics(state: tauri::State<'_, AppState>) -> Result<(), Stri
         .map_err(|e| format!("Failed to recompute analytics: {}", e))
 }
 
+#[tauri::command]
+pub fn reset_analytics(state: tauri::State<'_, AppState>) -> ClientResult<()> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.reset_analytics_cache()?)
+}
+
+#[tauri::command]
+pub fn set_precision_decimals(
+    state: tauri::State<'_, AppState>,
+    decimals: Option<u32>,
+) -> ClientResult<()> {
+    let mut state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.set_precision_decimals(decimals)?)
+}
+
+#[tauri::command]
+pub fn set_enabled_analytics(
+    state: tauri::State<'_, AppState>,
+    names: Vec<String>,
+) -> ClientResult<()> {
+    let mut state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.set_enabled_analytics(names.into_iter().collect())?)
+}
+
+#[tauri::command]
+pub fn set_max_risk_horizon_days(state: tauri::State<'_, AppState>, days: f64) -> ClientResult<()> {
+    let mut state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.set_max_risk_horizon_days(days)?)
+}
+
+#[tauri::command]
+pub fn set_round_realtime_magnitudes(
+    state: tauri::State<'_, AppState>,
+    enabled: bool,
+) -> ClientResult<()> {
+    let mut state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    state.set_round_realtime_magnitudes(enabled);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_merge_policy(
+    state: tauri::State<'_, AppState>,
+    policy: MergePolicy,
+) -> ClientResult<()> {
+    let mut state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    state.set_merge_policy(policy);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_missing_magnitude_policy(
+    state: tauri::State<'_, AppState>,
+    policy: MissingMagnitude,
+) -> ClientResult<()> {
+    let mut state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.set_missing_magnitude(policy)?)
+}
+
+#[tauri::command]
+pub fn set_max_response_bytes(
+    state: tauri::State<'_, AppState>,
+    max_response_bytes: usize,
+) -> ClientResult<()> {
+    let mut state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.set_max_response_bytes(max_response_bytes)?)
+}
+
+#[tauri::command]
+pub fn set_depth_unit(
+    state: tauri::State<'_, AppState>,
+    depth_unit: DepthUnit,
+) -> ClientResult<()> {
+    let mut state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    state.set_depth_unit(depth_unit);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_best_origin_only(
+    state: tauri::State<'_, AppState>,
+    best_origin_only: bool,
+) -> ClientResult<()> {
+    let mut state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    state.set_best_origin_only(best_origin_only);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn generate_report(state: tauri::State<'_, AppState>) -> ClientResult<CatalogReport> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.generate_report()?)
+}
+
+#[tauri::command]
+pub fn generate_report_markdown(state: tauri::State<'_, AppState>) -> ClientResult<String> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.generate_report()?.to_markdown())
+}
+
+#[tauri::command]
+pub fn get_counts_by_period(
+    state: tauri::State<'_, AppState>,
+    period: Period,
+) -> ClientResult<Vec<(chrono::DateTime<chrono::Utc>, u32)>> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.get_counts_by_period(period)?)
+}
+
+#[tauri::command]
+pub fn detect_coverage_gaps(
+    state: tauri::State<'_, AppState>,
+    min_gap_hours: f64,
+) -> ClientResult<Vec<CoverageGap>> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.detect_coverage_gaps(min_gap_hours)?)
+}
+
+#[tauri::command]
+pub fn get_region_timeseries(
+    state: tauri::State<'_, AppState>,
+    region: String,
+    period: Period,
+) -> ClientResult<Vec<(chrono::DateTime<chrono::Utc>, u32)>> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.get_region_timeseries(&region, period)?)
+}
+
+#[tauri::command]
+pub fn get_count_energy_correlation(
+    state: tauri::State<'_, AppState>,
+    window_days: u32,
+) -> ClientResult<f64> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.get_count_energy_correlation(window_days)?)
+}
+
+#[tauri::command]
+pub fn get_rolling_max_magnitude(
+    state: tauri::State<'_, AppState>,
+    window_days: u32,
+) -> ClientResult<Vec<(NaiveDate, f64)>> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.get_rolling_max_magnitude(window_days)?)
+}
+
+#[tauri::command]
+pub fn export_csv(
+    state: tauri::State<'_, AppState>,
+    columns: Option<Vec<String>>,
+    grid_degrees: Option<f64>,
+) -> ClientResult<tauri::ipc::Response> {
+    let columns = columns.unwrap_or_default();
+    let columns: Vec<&str> = columns.iter().map(String::as_str).collect();
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(tauri::ipc::Response::new(
+        state.to_csv_with_grid(&columns, grid_degrees)?,
+    ))
+}
+
+#[tauri::command]
+pub fn export_parquet(
+    state: tauri::State<'_, AppState>,
+    columns: Option<Vec<String>>,
+) -> ClientResult<tauri::ipc::Response> {
+    let columns = columns.unwrap_or_default();
+    let columns: Vec<&str> = columns.iter().map(String::as_str).collect();
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(tauri::ipc::Response::new(state.to_parquet(&columns)?))
+}
+
+#[tauri::command]
+pub fn import_parquet(
+    state: tauri::State<'_, AppState>,
+    request: tauri::ipc::Request<'_>,
+) -> ClientResult<usize> {
+    let tauri::ipc::InvokeBody::Raw(parquet) = request.body() else {
+        return Err(QuakeTrackerError::validation(
+            "body",
+            "Expected the Parquet file contents as raw bytes",
+        )
+        .into());
+    };
+    let mut state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    Ok(state.load_parquet(parquet)?)
+}
+
+#[tauri::command]
+pub fn replace_all_events(
+    state: tauri::State<'_, AppState>,
+    events: Vec<SeismicEvent>,
+) -> ClientResult<usize> {
+    let mut state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    state.replace_all(events)?;
+    Ok(state.get_stats().total_events)
+}
+
+#[tauri::command]
+pub fn load_demo_data(
+    state: tauri::State<'_, AppState>,
+    seed: Option<u64>,
+    count: Option<usize>,
+    b_value: Option<f64>,
+    clear: Option<bool>,
+) -> ClientResult<usize> {
+    let b_value = b_value.unwrap_or(1.0);
+    if !b_value.is_finite() || b_value <= 0.0 {
+        return Err(QuakeTrackerError::validation(
+            "b_value",
+            format!("b-value must be a positive number, got {}", b_value),
+        )
+        .into());
+    }
+
+    let mut events =
+        demo::generate_synthetic_catalog(seed.unwrap_or(42), count.unwrap_or(1000), b_value);
+    // Shift the catalog so it ends now and stays within the retention window
+    if let Some(last) = events.last() {
+        let shift = chrono::Utc::now() - last.time;
+        for event in &mut events {
+            event.time += shift;
+            event.last_update += shift;
+        }
+    }
+
+    let mut state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    if clear.unwrap_or(false) {
+        state.clear();
+    }
+    Ok(state.import_events(events)?)
+}
+
+#[tauri::command]
+pub fn get_schema(state: tauri::State<'_, AppState>) -> Result<Vec<ColumnInfo>, String> {
+    analytics::get_schema_internal(state.inner())
+}
+
 #[tauri::command]
 pub fn get_hourly_frequency(state: tauri::State<'_, AppState>) -> Result<Vec<(u32, u32)>, String> {
     analytics::get_hourly_frequency_internal(state.inner())
@@ -225,31 +769,149 @@ pub fn get_region_hotspots(
     analytics::get_region_hotspots_internal(state.inner())
 }
 
+#[tauri::command]
+pub fn get_weighted_region_hotspots(
+    state: tauri::State<'_, AppState>,
+    half_life_days: f64,
+) -> Result<serde_json::Value, String> {
+    let result = analytics::get_weighted_region_hotspots_internal(state.inner(), half_life_days)?;
+    analytics::with_precision(state.inner(), &result)
+}
+
 #[tauri::command]
 pub fn get_coordinate_clusters(
     state: tauri::State<'_, AppState>,
-) -> Result<Vec<(f64, f64, u32)>, String> {
-    analytics::get_coordinate_clusters_internal(state.inner())
+) -> Result<serde_json::Value, String> {
+    let result = analytics::get_coordinate_clusters_internal(state.inner())?;
+    analytics::with_precision(state.inner(), &result)
+}
+
+#[tauri::command]
+pub fn get_energy_grid(state: tauri::State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let result = analytics::get_energy_grid_internal(state.inner())?;
+    analytics::with_precision(state.inner(), &result)
+}
+
+#[tauri::command]
+pub fn set_grid_binning(
+    state: tauri::State<'_, AppState>,
+    binning: GridBinning,
+) -> Result<(), String> {
+    analytics::set_grid_binning_internal(state.inner(), binning)
 }
 
 #[tauri::command]
-pub fn get_b_value(state: tauri::State<'_, AppState>) -> Result<f64, String> {
-    analytics::get_b_value_internal(state.inner())
+pub fn get_b_value(state: tauri::State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let result = analytics::get_b_value_internal(state.inner())?;
+    analytics::with_precision(state.inner(), &result)
+}
+
+#[tauri::command]
+pub fn get_annual_rate_above(
+    state: tauri::State<'_, AppState>,
+    magnitude: Option<f64>,
+) -> Result<serde_json::Value, String> {
+    let result = analytics::get_annual_rate_above_internal(state.inner(), magnitude)?;
+    analytics::with_precision(state.inner(), &result)
+}
+
+#[tauri::command]
+pub fn get_expected_max_magnitude(
+    state: tauri::State<'_, AppState>,
+    years: f64,
+) -> Result<serde_json::Value, String> {
+    let result = analytics::get_expected_max_magnitude_internal(state.inner(), years)?;
+    analytics::with_precision(state.inner(), &result)
+}
+
+#[tauri::command]
+pub fn get_recurrence_interval(
+    state: tauri::State<'_, AppState>,
+    magnitude: f64,
+) -> ClientResult<serde_json::Value> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    let years = state.recurrence_interval_years(magnitude)?;
+    // JSON has no infinity, so an event that never recurs is sent as null
+    let years = years.is_finite().then_some(years);
+    analytics::rounded_json(&years, state.precision_decimals()).map_err(ClientError::Internal)
+}
+
+#[tauri::command]
+pub fn get_time_since_last_above(
+    state: tauri::State<'_, AppState>,
+    magnitude: f64,
+) -> ClientResult<Option<i64>> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    let elapsed = state.time_since_last_above(magnitude)?;
+    Ok(elapsed.map(|elapsed| elapsed.num_seconds()))
+}
+
+#[tauri::command]
+pub fn get_b_value_by_depth(
+    state: tauri::State<'_, AppState>,
+    bin_km: f64,
+    min_events: usize,
+) -> ClientResult<serde_json::Value> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    let result = state.get_b_value_by_depth(bin_km, min_events)?;
+    analytics::rounded_json(&result, state.precision_decimals()).map_err(ClientError::Internal)
+}
+
+#[tauri::command]
+pub fn get_modal_values(state: tauri::State<'_, AppState>) -> ClientResult<serde_json::Value> {
+    let state = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+    let result = state.get_modal_values()?;
+    analytics::rounded_json(&result, state.precision_decimals()).map_err(ClientError::Internal)
 }
 
 #[tauri::command]
 pub fn get_magnitude_frequency_data(
     state: tauri::State<'_, AppState>,
-) -> Result<Vec<(f64, u32, u32)>, String> {
-    analytics::get_magnitude_frequency_data_internal(state.inner())
+) -> Result<serde_json::Value, String> {
+    let result = analytics::get_magnitude_frequency_data_internal(state.inner())?;
+    analytics::with_precision(state.inner(), &result)
 }
 
 #[tauri::command]
-pub fn get_risk_metrics(state: tauri::State<'_, AppState>) -> Result<(f64, f64, f64, f64), String> {
-    analytics::get_risk_metrics_internal(state.inner())
+pub fn get_magnitude_frequency_loglog(
+    state: tauri::State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    let result = analytics::get_magnitude_frequency_loglog_internal(state.inner())?;
+    analytics::with_precision(state.inner(), &result)
 }
 
 #[tauri::command]
-pub fn get_total_energy(state: tauri::State<'_, AppState>) -> Result<f64, String> {
-    analytics::get_total_energy_internal(state.inner())
+pub fn get_risk_metrics(state: tauri::State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let result = analytics::get_risk_metrics_internal(state.inner())?;
+    analytics::with_precision(state.inner(), &result)
+}
+
+#[tauri::command]
+pub fn get_total_energy(state: tauri::State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let result = analytics::get_total_energy_internal(state.inner())?;
+    analytics::with_precision(state.inner(), &result)
+}
+
+#[tauri::command]
+pub fn get_total_energy_scaled(
+    state: tauri::State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    let result = analytics::get_total_energy_scaled_internal(state.inner())?;
+    analytics::with_precision(state.inner(), &result)
+}
+
+#[tauri::command]
+pub fn get_magnitude_quantiles(
+    state: tauri::State<'_, AppState>,
+) -> Result<serde_json::Value, String> {
+    let result = analytics::get_magnitude_quantiles_internal(state.inner())?;
+    analytics::with_precision(state.inner(), &result)
 }
@@ -0,0 +1,125 @@
+use chrono::{DateTime, TimeDelta};
+
+use crate::seismic::SeismicEvent;
+
+/// Smallest magnitude in a synthetic catalog, matching the completeness
+/// magnitude assumed by the Gutenberg-Richter analytics
+pub const SYNTHETIC_MIN_MAGNITUDE: f64 = 2.0;
+/// Magnitudes are reported to one decimal, like EMSC does
+const MAGNITUDE_STEP: f64 = 0.1;
+/// Mean time between synthetic events
+const MEAN_INTERVAL_SECONDS: f64 = 3600.0;
+const MAX_DEPTH_KM: f64 = 100.0;
+
+/// Deterministic linear congruential generator, so catalogs only depend on
+/// their seed
+struct SeededRng(u64);
+
+impl SeededRng {
+    /// Uniform sample in `[0, 1)`
+    fn next_f64(&mut self) -> f64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Reproducible catalog of `n` events for demos and tests
+///
+/// Magnitudes follow a Gutenberg-Richter distribution with the given
+/// `b_value` from [`SYNTHETIC_MIN_MAGNITUDE`] up, epicenters are spread
+/// uniformly over the globe and origin times form a Poisson process starting
+/// on 2024-01-01, so times strictly increase. The same seed always gives the
+/// same catalog.
+pub fn generate_synthetic_catalog(seed: u64, n: usize, b_value: f64) -> Vec<SeismicEvent> {
+    let mut rng = SeededRng(seed);
+    let mut time = DateTime::from_timestamp(1_704_067_200, 0).expect("valid start timestamp");
+
+    (0..n)
+        .map(|index| {
+            // Sampled above half a step below the minimum, so rounding fills
+            // the lowest magnitude bin completely
+            let continuous = SYNTHETIC_MIN_MAGNITUDE
+                - MAGNITUDE_STEP / 2.0
+                - (1.0 - rng.next_f64()).log10() / b_value;
+            let magnitude = (continuous / MAGNITUDE_STEP).round() * MAGNITUDE_STEP;
+            let latitude = (2.0 * rng.next_f64() - 1.0).asin().to_degrees();
+            let longitude = rng.next_f64() * 360.0 - 180.0;
+            let depth = rng.next_f64() * MAX_DEPTH_KM;
+
+            let interval = -(1.0 - rng.next_f64()).ln() * MEAN_INTERVAL_SECONDS;
+            time += TimeDelta::milliseconds((interval * 1000.0) as i64 + 1);
+
+            SeismicEvent {
+                geometry: geo_types::Point::new(longitude, latitude),
+                source_id: index.to_string(),
+                source_catalog: "SYNTHETIC".to_string(),
+                last_update: time,
+                time,
+                latitude,
+                longitude,
+                depth,
+                event_type: "ke".to_string(),
+                author: "SYNTHETIC".to_string(),
+                magnitude,
+                magnitude_type: "ml".to_string(),
+                flynn_region: "SYNTHETIC REGION".to_string(),
+                id: format!("synthetic_{}_{:06}", seed, index),
+                origins: None,
+                arrivals: None,
+                coordinate_mismatch: false,
+                magnitude_missing: false,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Aki-Utsu maximum likelihood b-value for magnitudes binned at
+    /// `MAGNITUDE_STEP`
+    fn estimate_b_value(events: &[SeismicEvent]) -> f64 {
+        let mean = events.iter().map(|event| event.magnitude).sum::<f64>() / events.len() as f64;
+        std::f64::consts::LOG10_E / (mean - (SYNTHETIC_MIN_MAGNITUDE - MAGNITUDE_STEP / 2.0))
+    }
+
+    #[test]
+    fn test_synthetic_catalog_matches_requested_b_value() {
+        for b_value in [0.8, 1.0, 1.3] {
+            let events = generate_synthetic_catalog(7, 5000, b_value);
+            assert_eq!(events.len(), 5000);
+
+            let estimated = estimate_b_value(&events);
+            assert!(
+                (estimated - b_value).abs() < 0.05,
+                "requested b = {}, estimated {}",
+                b_value,
+                estimated
+            );
+        }
+    }
+
+    #[test]
+    fn test_synthetic_catalog_is_reproducible() {
+        let first = generate_synthetic_catalog(42, 200, 1.0);
+        let second = generate_synthetic_catalog(42, 200, 1.0);
+        let other = generate_synthetic_catalog(43, 200, 1.0);
+
+        let magnitudes = |events: &[SeismicEvent]| -> Vec<f64> {
+            events.iter().map(|event| event.magnitude).collect()
+        };
+        assert_eq!(magnitudes(&first), magnitudes(&second));
+        assert_ne!(magnitudes(&first), magnitudes(&other));
+
+        assert!(first.windows(2).all(|pair| pair[0].time < pair[1].time));
+        assert!(first.iter().all(|event| {
+            event.magnitude >= SYNTHETIC_MIN_MAGNITUDE - 1e-9
+                && (-90.0..=90.0).contains(&event.latitude)
+                && (-180.0..180.0).contains(&event.longitude)
+        }));
+    }
+}
@@ -284,6 +284,34 @@ pub mod validation {
         Ok(())
     }
 
+    /// Magnitude types understood by the EMSC FDSN service
+    pub const KNOWN_MAGNITUDE_TYPES: &[&str] = &[
+        "m", "mb", "mblg", "mc", "md", "me", "mi", "ml", "mlv", "ms", "msbb", "mw", "mwb", "mwc",
+        "mwp", "mwr", "mww",
+    ];
+
+    pub fn validate_magnitude_type(magnitude_type: &str) -> Result<()> {
+        validate_magnitude_type_in(magnitude_type, KNOWN_MAGNITUDE_TYPES)
+    }
+
+    /// Validate a magnitude type against a custom set, case-insensitively
+    pub fn validate_magnitude_type_in(magnitude_type: &str, allowed: &[&str]) -> Result<()> {
+        if !allowed
+            .iter()
+            .any(|known| known.eq_ignore_ascii_case(magnitude_type))
+        {
+            return Err(QuakeTrackerError::validation(
+                "magnitude_type",
+                format!(
+                    "Unknown magnitude type '{}', expected one of: {}",
+                    magnitude_type,
+                    allowed.join(", ")
+                ),
+            ));
+        }
+        Ok(())
+    }
+
     pub fn validate_event_id(id: &str) -> Result<()> {
         if id.is_empty() {
             return Err(QuakeTrackerError::validation(
@@ -335,6 +363,10 @@ mod tests {
 
         assert!(validation::validate_longitude(120.0).is_ok());
         assert!(validation::validate_longitude(200.0).is_err());
+
+        assert!(validation::validate_magnitude_type("mww").is_ok());
+        assert!(validation::validate_magnitude_type_in("mjma", &["mjma"]).is_ok());
+        assert!(validation::validate_magnitude_type_in("mw", &["mjma"]).is_err());
     }
 
     #[test]
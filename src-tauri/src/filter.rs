@@ -0,0 +1,168 @@
+use chrono::{DateTime, Utc};
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::error::validation::{
+    validate_depth, validate_latitude, validate_longitude, validate_magnitude,
+};
+use crate::error::{QuakeTrackerError, Result};
+
+/// Optional constraints for selecting stored events
+///
+/// Every field is optional and unset fields don't constrain the selection.
+/// All bounds are inclusive.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventFilter {
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub min_magnitude: Option<f64>,
+    pub max_magnitude: Option<f64>,
+    pub min_depth: Option<f64>,
+    pub max_depth: Option<f64>,
+    pub min_latitude: Option<f64>,
+    pub max_latitude: Option<f64>,
+    pub min_longitude: Option<f64>,
+    pub max_longitude: Option<f64>,
+    /// Exact Flynn region name
+    pub region: Option<String>,
+}
+
+impl EventFilter {
+    pub fn validate(&self) -> Result<()> {
+        if let (Some(start), Some(end)) = (self.start_time, self.end_time) {
+            if start > end {
+                return Err(QuakeTrackerError::validation(
+                    "time_range",
+                    "Start time must be before end time",
+                ));
+            }
+        }
+
+        for magnitude in [self.min_magnitude, self.max_magnitude]
+            .into_iter()
+            .flatten()
+        {
+            validate_magnitude(magnitude)?;
+        }
+        for depth in [self.min_depth, self.max_depth].into_iter().flatten() {
+            validate_depth(depth)?;
+        }
+        for latitude in [self.min_latitude, self.max_latitude].into_iter().flatten() {
+            validate_latitude(latitude)?;
+        }
+        for longitude in [self.min_longitude, self.max_longitude]
+            .into_iter()
+            .flatten()
+        {
+            validate_longitude(longitude)?;
+        }
+
+        check_range(
+            "magnitude_range",
+            "magnitude",
+            self.min_magnitude,
+            self.max_magnitude,
+        )?;
+        check_range("depth_range", "depth", self.min_depth, self.max_depth)?;
+        check_range(
+            "latitude_range",
+            "latitude",
+            self.min_latitude,
+            self.max_latitude,
+        )?;
+        check_range(
+            "longitude_range",
+            "longitude",
+            self.min_longitude,
+            self.max_longitude,
+        )?;
+
+        Ok(())
+    }
+
+    /// Combined Polars predicate, or `None` when nothing is constrained
+    pub fn to_expr(&self) -> Option<Expr> {
+        let time_ns = |time: DateTime<Utc>| time.timestamp_nanos_opt().unwrap_or(0);
+
+        let predicates = [
+            self.start_time.map(|t| col("time").gt_eq(lit(time_ns(t)))),
+            self.end_time.map(|t| col("time").lt_eq(lit(time_ns(t)))),
+            self.min_magnitude.map(|v| col("mag").gt_eq(lit(v))),
+            self.max_magnitude.map(|v| col("mag").lt_eq(lit(v))),
+            self.min_depth.map(|v| col("depth").gt_eq(lit(v))),
+            self.max_depth.map(|v| col("depth").lt_eq(lit(v))),
+            self.min_latitude.map(|v| col("lat").gt_eq(lit(v))),
+            self.max_latitude.map(|v| col("lat").lt_eq(lit(v))),
+            self.min_longitude.map(|v| col("lon").gt_eq(lit(v))),
+            self.max_longitude.map(|v| col("lon").lt_eq(lit(v))),
+            self.region
+                .as_ref()
+                .map(|region| col("flynn_region").eq(lit(region.clone()))),
+        ];
+
+        predicates
+            .into_iter()
+            .flatten()
+            .reduce(|acc, expr| acc.and(expr))
+    }
+
+    /// Restrict a lazy frame of events to the ones matching this filter
+    pub fn apply(&self, dataframe: LazyFrame) -> LazyFrame {
+        match self.to_expr() {
+            Some(predicate) => dataframe.filter(predicate),
+            None => dataframe,
+        }
+    }
+}
+
+fn check_range(field: &str, name: &str, min: Option<f64>, max: Option<f64>) -> Result<()> {
+    if let (Some(min), Some(max)) = (min, max) {
+        if min > max {
+            return Err(QuakeTrackerError::validation(
+                field,
+                format!("Minimum {} must not exceed maximum {}", name, name),
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_filter_has_no_predicate() {
+        let filter = EventFilter::default();
+
+        assert!(filter.validate().is_ok());
+        assert!(filter.to_expr().is_none());
+    }
+
+    #[test]
+    fn test_filter_validation() {
+        let inverted = EventFilter {
+            min_magnitude: Some(5.0),
+            max_magnitude: Some(3.0),
+            ..Default::default()
+        };
+        assert_eq!(inverted.validate().unwrap_err().category(), "validation");
+
+        let out_of_range = EventFilter {
+            min_latitude: Some(-95.0),
+            ..Default::default()
+        };
+        assert!(out_of_range.validate().is_err());
+    }
+
+    #[test]
+    fn test_filter_deserializes_partial_json() {
+        let filter: EventFilter =
+            serde_json::from_str(r#"{"min_magnitude": 4.5, "region": "JAPAN"}"#).unwrap();
+
+        assert_eq!(filter.min_magnitude, Some(4.5));
+        assert_eq!(filter.region.as_deref(), Some("JAPAN"));
+        assert_eq!(filter.max_depth, None);
+    }
+}
@@ -1,8 +1,13 @@
 mod analytics;
 mod client;
 mod commands;
+mod demo;
 mod error;
+mod filter;
+mod listener;
+mod report;
 mod seismic;
+mod spatial;
 mod state;
 
 #[cfg(test)]
@@ -11,6 +16,7 @@ pub(crate) mod test_utils;
 use std::error::Error;
 use std::sync::Mutex;
 
+use listener::ListenerState;
 use state::SeismicData;
 use tauri::{App, Manager, Runtime};
 pub type AppState = Mutex<SeismicData>;
@@ -31,22 +37,92 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .invoke_handler(tauri::generate_handler![
             commands::get_seismic_events,
+            commands::backfill_range,
+            commands::get_event_uncertainty,
+            commands::get_evaluation_mode_counts,
+            commands::list_catalog_presets,
             commands::listen_to_seismic_events,
+            commands::stop_listening,
+            commands::replay_events,
+            commands::stop_replay,
+            commands::set_reconnect_config,
+            commands::set_event_coalescing,
+            commands::set_listener_send_retries,
+            commands::set_min_emit_magnitude,
             commands::get_magnitude_distribution,
+            commands::get_magnitude_distribution_above,
+            commands::set_auto_magnitude_bins,
             commands::get_count_by_year,
             commands::get_mag_depth_pairs,
+            commands::get_mag_depth_arrow,
             commands::get_advanced_analytics,
+            commands::get_advanced_analytics_with,
+            commands::get_analytics_since,
+            commands::get_analytics_in_polygon,
+            commands::analyze_geojson,
             commands::get_data_stats,
+            commands::get_thinned_aftershocks,
+            commands::get_events_after_seq,
+            commands::get_events_in_magnitude_range,
+            commands::get_events_in_polygon,
+            commands::get_events_local_time,
+            commands::flag_magnitude_outliers,
+            commands::get_events_with_distance,
+            commands::get_recent_events,
+            commands::get_events_columnar,
+            commands::get_data_bounds,
+            commands::get_depth_by_magtype,
+            commands::get_region_last_activity,
+            commands::get_quality_events,
+            commands::get_nearest_neighbor_summary,
+            commands::get_activity_centroid,
+            commands::get_largest_event_energy_fraction,
+            commands::stream_events,
             commands::recompute_analytics,
+            commands::reset_analytics,
+            commands::set_precision_decimals,
+            commands::set_round_realtime_magnitudes,
+            commands::set_merge_policy,
+            commands::set_missing_magnitude_policy,
+            commands::set_max_response_bytes,
+            commands::set_depth_unit,
+            commands::set_best_origin_only,
+            commands::set_enabled_analytics,
+            commands::set_max_risk_horizon_days,
+            commands::generate_report,
+            commands::generate_report_markdown,
+            commands::export_csv,
+            commands::export_parquet,
+            commands::import_parquet,
+            commands::replace_all_events,
+            commands::load_demo_data,
+            commands::get_schema,
+            commands::get_counts_by_period,
+            commands::get_region_timeseries,
+            commands::detect_coverage_gaps,
+            commands::get_count_energy_correlation,
+            commands::get_rolling_max_magnitude,
             commands::get_hourly_frequency,
             commands::get_monthly_frequency,
             commands::get_weekly_frequency,
             commands::get_region_hotspots,
+            commands::get_weighted_region_hotspots,
             commands::get_coordinate_clusters,
+            commands::get_energy_grid,
+            commands::set_grid_binning,
             commands::get_b_value,
+            commands::get_annual_rate_above,
+            commands::get_expected_max_magnitude,
+            commands::get_recurrence_interval,
+            commands::get_time_since_last_above,
+            commands::get_b_value_by_depth,
+            commands::get_modal_values,
             commands::get_magnitude_frequency_data,
+            commands::get_magnitude_frequency_loglog,
             commands::get_risk_metrics,
             commands::get_total_energy,
+            commands::get_total_energy_scaled,
+            commands::get_magnitude_quantiles,
         ])
         .setup(setup)
         .run(tauri::generate_context!())
@@ -61,5 +137,6 @@ fn setup<R: Runtime>(app: &mut App<R>) -> Result<(), Box<dyn Error>> {
     // }
 
     app.manage(Mutex::new(SeismicData::default()));
+    app.manage(ListenerState::default());
     Ok(())
 }
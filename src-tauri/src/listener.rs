@@ -0,0 +1,1389 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use futures_util::{Stream, StreamExt};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use tauri::ipc::Channel;
+use tokio::sync::Notify;
+use tokio::time::{interval, sleep, sleep_until, Duration, Instant, MissedTickBehavior};
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::{self, Message};
+
+use crate::client::{ClientError, ClientResult, WssAction, WssEvent, SEISMIC_WSS_URL};
+use crate::error::validation::validate_magnitude;
+use crate::error::{QuakeTrackerError, Result};
+use crate::AppState;
+
+/// How often a heartbeat is emitted while the connection is open
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+/// Longest window over which events may be coalesced into one batch
+const MAX_COALESCE_WINDOW_MS: u64 = 5000;
+/// Default silence after which an open connection is considered dead
+const DEFAULT_IDLE_TIMEOUT_MS: u64 = 300_000;
+/// Default number of times a failed send to the frontend is repeated
+const DEFAULT_SEND_RETRIES: u32 = 1;
+/// Upper bound on the configurable send retries
+const MAX_SEND_RETRIES: u32 = 5;
+
+/// Connection state of the EMSC WebSocket listener
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConnectionState {
+    Connected,
+    Reconnecting,
+    Disconnected,
+}
+
+/// Status update sent to the frontend alongside the event stream
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ListenerStatus {
+    /// The connection moved to a new state
+    StateChanged { state: ConnectionState },
+    /// Periodic liveness signal, sent even when no events arrive
+    Heartbeat {
+        state: ConnectionState,
+        seconds_since_last_event: u64,
+    },
+}
+
+/// Event data sent to the frontend, either one event at a time or, with
+/// coalescing enabled, as a batch of the events received within the window
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum EventPayload {
+    Single(Box<WssEvent>),
+    Batch(Vec<WssEvent>),
+}
+
+/// Reconnection policy for the EMSC WebSocket listener
+///
+/// Failed connection attempts are retried with exponential backoff, starting
+/// at `initial_delay_ms` and doubling up to `max_delay_ms`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReconnectConfig {
+    /// Maximum number of connection attempts (0 = retry forever)
+    pub max_retries: u32,
+    /// Delay before the first retry, in milliseconds
+    pub initial_delay_ms: u64,
+    /// Upper bound for the backoff delay, in milliseconds
+    pub max_delay_ms: u64,
+    /// Reconnect when an open connection receives nothing for this long, in
+    /// milliseconds (0 = never), to recover from half-open connections
+    #[serde(default = "default_idle_timeout_ms")]
+    pub idle_timeout_ms: u64,
+}
+
+fn default_idle_timeout_ms() -> u64 {
+    DEFAULT_IDLE_TIMEOUT_MS
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            initial_delay_ms: 1000,
+            max_delay_ms: 30000,
+            idle_timeout_ms: DEFAULT_IDLE_TIMEOUT_MS,
+        }
+    }
+}
+
+impl ReconnectConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.initial_delay_ms == 0 {
+            return Err(QuakeTrackerError::validation(
+                "initial_delay_ms",
+                "Initial delay must be greater than 0",
+            ));
+        }
+        if self.max_delay_ms < self.initial_delay_ms {
+            return Err(QuakeTrackerError::validation(
+                "max_delay_ms",
+                "Maximum delay must not be less than the initial delay",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether the listener should keep retrying forever
+    pub fn is_infinite(&self) -> bool {
+        self.max_retries == 0
+    }
+
+    /// Silence after which the connection is dropped, if enabled
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        (self.idle_timeout_ms > 0).then(|| Duration::from_millis(self.idle_timeout_ms))
+    }
+
+    /// Backoff delay to wait after the given failed attempt (1-based)
+    pub fn delay_for_attempt(&self, attempt: u32) -> Duration {
+        let factor = 2u64.saturating_pow(attempt.saturating_sub(1));
+        let delay = self.initial_delay_ms.saturating_mul(factor);
+        Duration::from_millis(delay.min(self.max_delay_ms))
+    }
+}
+
+/// Cancellation flag that tasks can poll or wait on
+#[derive(Default)]
+struct StopSignal {
+    requested: AtomicBool,
+    notify: Notify,
+}
+
+impl StopSignal {
+    fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    fn reset(&self) {
+        self.requested.store(false, Ordering::SeqCst);
+    }
+
+    /// Resolves once a stop has been requested
+    async fn wait(&self) {
+        loop {
+            let notified = self.notify.notified();
+            tokio::pin!(notified);
+            // Register before checking the flag so a concurrent request isn't missed
+            notified.as_mut().enable();
+
+            if self.is_requested() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Runtime settings shared between the listener task and the commands that
+/// adjust it
+pub struct ListenerState {
+    reconnect: RwLock<ReconnectConfig>,
+    coalesce_window_ms: RwLock<Option<u64>>,
+    send_retries: RwLock<u32>,
+    min_emit_magnitude: RwLock<Option<f64>>,
+    stop: StopSignal,
+    replay_stop: StopSignal,
+}
+
+impl Default for ListenerState {
+    fn default() -> Self {
+        Self {
+            reconnect: RwLock::default(),
+            coalesce_window_ms: RwLock::default(),
+            send_retries: RwLock::new(DEFAULT_SEND_RETRIES),
+            min_emit_magnitude: RwLock::default(),
+            stop: StopSignal::default(),
+            replay_stop: StopSignal::default(),
+        }
+    }
+}
+
+impl ListenerState {
+    pub fn reconnect_config(&self) -> ReconnectConfig {
+        self.reconnect.read().clone()
+    }
+
+    pub fn set_reconnect_config(&self, config: ReconnectConfig) -> Result<()> {
+        config.validate()?;
+        *self.reconnect.write() = config;
+        Ok(())
+    }
+
+    /// Window within which events are batched into one frontend message, or
+    /// `None` to send every event individually
+    pub fn coalesce_window(&self) -> Option<Duration> {
+        self.coalesce_window_ms.read().map(Duration::from_millis)
+    }
+
+    pub fn set_coalesce_window_ms(&self, window_ms: Option<u64>) -> Result<()> {
+        if let Some(window_ms) = window_ms {
+            if !(1..=MAX_COALESCE_WINDOW_MS).contains(&window_ms) {
+                return Err(QuakeTrackerError::validation(
+                    "window_ms",
+                    format!(
+                        "Coalescing window must be between 1 and {} ms",
+                        MAX_COALESCE_WINDOW_MS
+                    ),
+                ));
+            }
+        }
+        *self.coalesce_window_ms.write() = window_ms;
+        Ok(())
+    }
+
+    /// How many times a send to the frontend is repeated before the payload
+    /// is dropped. Events are stored before they are sent, so a dropped
+    /// payload only misses the live update.
+    pub fn send_retries(&self) -> u32 {
+        *self.send_retries.read()
+    }
+
+    pub fn set_send_retries(&self, retries: u32) -> Result<()> {
+        if retries > MAX_SEND_RETRIES {
+            return Err(QuakeTrackerError::validation(
+                "retries",
+                format!("Send retries must be at most {}", MAX_SEND_RETRIES),
+            ));
+        }
+        *self.send_retries.write() = retries;
+        Ok(())
+    }
+
+    /// Events below this magnitude are stored but not sent to the frontend,
+    /// or `None` to send every event
+    pub fn min_emit_magnitude(&self) -> Option<f64> {
+        *self.min_emit_magnitude.read()
+    }
+
+    /// Takes effect immediately, also for a running listener
+    pub fn set_min_emit_magnitude(&self, magnitude: Option<f64>) -> Result<()> {
+        if let Some(magnitude) = magnitude {
+            validate_magnitude(magnitude)?;
+        }
+        *self.min_emit_magnitude.write() = magnitude;
+        Ok(())
+    }
+
+    /// Ask a running listener to disconnect after the message in flight
+    pub fn request_stop(&self) {
+        self.stop.request();
+    }
+
+    pub fn is_stop_requested(&self) -> bool {
+        self.stop.is_requested()
+    }
+
+    /// Clear a previous stop request before starting a new listener
+    fn reset_stop(&self) {
+        self.stop.reset();
+    }
+
+    /// Resolves once a stop has been requested
+    async fn stopped(&self) {
+        self.stop.wait().await
+    }
+
+    /// Ask a running replay to stop before its next event
+    pub fn request_replay_stop(&self) {
+        self.replay_stop.request();
+    }
+}
+
+/// Listen to the EMSC WebSocket, reconnecting according to the listener's
+/// reconnect policy
+pub(crate) async fn listen_internal(
+    state: &AppState,
+    listener: &ListenerState,
+    on_event: &Channel<EventPayload>,
+    on_status: Option<&Channel<ListenerStatus>>,
+) -> ClientResult<()> {
+    log::info!("Starting WebSocket connection to EMSC with retry logic");
+
+    listener.reset_stop();
+    run_with_reconnect(listener, on_status, || {
+        connect_and_listen(state, listener, on_event, on_status)
+    })
+    .await
+}
+
+fn emit_status(on_status: Option<&Channel<ListenerStatus>>, status: ListenerStatus) {
+    if let Some(channel) = on_status {
+        if let Err(e) = channel.send(status) {
+            log::warn!("Failed to send listener status to frontend: {}", e);
+        }
+    }
+}
+
+fn emit_state(on_status: Option<&Channel<ListenerStatus>>, state: ConnectionState) {
+    emit_status(on_status, ListenerStatus::StateChanged { state });
+}
+
+/// How a connection that was established ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StreamEnd {
+    /// Closed by the server or by a stop request
+    Closed,
+    /// Nothing arrived within the idle timeout
+    IdleTimeout,
+}
+
+/// Drive `connect` until it completes cleanly, the retry budget is spent or a
+/// stop is requested. An idle timeout reconnects straight away and resets the
+/// retry budget, since the connection itself worked.
+async fn run_with_reconnect<F, Fut>(
+    listener: &ListenerState,
+    on_status: Option<&Channel<ListenerStatus>>,
+    mut connect: F,
+) -> ClientResult<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ClientResult<StreamEnd>>,
+{
+    let config = listener.reconnect_config();
+    let mut attempt = 0;
+
+    loop {
+        if listener.is_stop_requested() {
+            log::info!("WebSocket listener stopped");
+            emit_state(on_status, ConnectionState::Disconnected);
+            return Ok(());
+        }
+
+        match connect().await {
+            Ok(StreamEnd::Closed) => {
+                log::debug!("WebSocket connection closed gracefully");
+                emit_state(on_status, ConnectionState::Disconnected);
+                return Ok(());
+            }
+            Ok(StreamEnd::IdleTimeout) => {
+                attempt = 0;
+                emit_state(on_status, ConnectionState::Reconnecting);
+            }
+            Err(e) => {
+                attempt += 1;
+                if config.is_infinite() {
+                    log::error!("WebSocket connection failed (attempt {}): {}", attempt, e);
+                } else {
+                    log::error!(
+                        "WebSocket connection failed (attempt {}/{}): {}",
+                        attempt,
+                        config.max_retries,
+                        e
+                    );
+
+                    if attempt >= config.max_retries {
+                        log::error!("Max retry attempts reached, giving up");
+                        emit_state(on_status, ConnectionState::Disconnected);
+                        return Err(e);
+                    }
+                }
+
+                emit_state(on_status, ConnectionState::Reconnecting);
+                let delay = config.delay_for_attempt(attempt);
+                log::debug!("Retrying in {}ms...", delay.as_millis());
+                tokio::select! {
+                    _ = sleep(delay) => {}
+                    _ = listener.stopped() => {}
+                }
+            }
+        }
+    }
+}
+
+async fn connect_and_listen(
+    state: &AppState,
+    listener: &ListenerState,
+    on_event: &Channel<EventPayload>,
+    on_status: Option<&Channel<ListenerStatus>>,
+) -> ClientResult<StreamEnd> {
+    let request = SEISMIC_WSS_URL
+        .into_client_request()
+        .map_err(|e| ClientError::Network(format!("Invalid WebSocket URL: {}", e)))?;
+
+    let (mut stream, _response) = connect_async(request)
+        .await
+        .map_err(|e| ClientError::Network(format!("WebSocket connection failed: {}", e)))?;
+
+    log::info!("WebSocket connected successfully");
+    emit_state(on_status, ConnectionState::Connected);
+
+    let result = listen_on_stream(
+        &mut stream,
+        state,
+        listener,
+        on_event,
+        on_status,
+        HEARTBEAT_INTERVAL,
+    )
+    .await;
+
+    if listener.is_stop_requested() {
+        if let Err(e) = stream.close(None).await {
+            log::warn!("Failed to close WebSocket cleanly: {}", e);
+        }
+    }
+
+    result
+}
+
+/// Forwards stored events to the frontend, holding them back for the
+/// coalescing window when one is configured
+struct EventEmitter<'a> {
+    channel: &'a Channel<EventPayload>,
+    window: Option<Duration>,
+    retries: u32,
+    pending: Vec<WssEvent>,
+    deadline: Option<Instant>,
+}
+
+impl<'a> EventEmitter<'a> {
+    fn new(channel: &'a Channel<EventPayload>, window: Option<Duration>, retries: u32) -> Self {
+        Self {
+            channel,
+            window,
+            retries,
+            pending: Vec::new(),
+            deadline: None,
+        }
+    }
+
+    fn emit(&mut self, events: Vec<WssEvent>) {
+        let Some(window) = self.window else {
+            for event in events {
+                self.send(EventPayload::Single(Box::new(event)));
+            }
+            return;
+        };
+
+        if !events.is_empty() && self.deadline.is_none() {
+            self.deadline = Some(Instant::now() + window);
+        }
+        self.pending.extend(events);
+    }
+
+    /// Send everything held back so far as one batch
+    fn flush(&mut self) {
+        self.deadline = None;
+        if self.pending.is_empty() {
+            return;
+        }
+        let batch = std::mem::take(&mut self.pending);
+        self.send(EventPayload::Batch(batch));
+    }
+
+    /// Send `payload`, repeating failed sends up to `retries` times
+    ///
+    /// The events are already stored, so a payload that still can't be
+    /// delivered is logged and dropped instead of stopping the listener.
+    fn send(&self, payload: EventPayload) {
+        for attempt in 0..=self.retries {
+            match self.channel.send(payload.clone()) {
+                Ok(()) => return,
+                Err(e) if attempt < self.retries => {
+                    log::warn!("Failed to send event to frontend, retrying: {}", e);
+                }
+                Err(e) => {
+                    log::error!("Dropping event the frontend channel didn't accept: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Consume messages from an open connection, emitting a heartbeat every
+/// `heartbeat_interval` so the frontend can tell a quiet feed from a dead one
+///
+/// Ends with `StreamEnd::IdleTimeout` when nothing arrives within the
+/// configured idle timeout, so a connection that went silent without a close
+/// frame is reconnected.
+async fn listen_on_stream<S>(
+    stream: &mut S,
+    state: &AppState,
+    listener: &ListenerState,
+    on_event: &Channel<EventPayload>,
+    on_status: Option<&Channel<ListenerStatus>>,
+    heartbeat_interval: Duration,
+) -> ClientResult<StreamEnd>
+where
+    S: Stream<Item = std::result::Result<Message, tungstenite::Error>> + Unpin,
+{
+    let mut last_event = Instant::now();
+    let mut emitter = EventEmitter::new(
+        on_event,
+        listener.coalesce_window(),
+        listener.send_retries(),
+    );
+    let mut heartbeat = interval(heartbeat_interval);
+    heartbeat.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    // The first tick of an interval completes immediately
+    heartbeat.reset();
+    let idle_timeout = listener.reconnect_config().idle_timeout();
+    let mut idle_deadline = idle_timeout.map(|timeout| Instant::now() + timeout);
+
+    loop {
+        tokio::select! {
+            msg = stream.next() => {
+                let Some(msg) = msg else {
+                    break;
+                };
+                // Any frame, pings included, shows the connection is alive
+                idle_deadline = idle_timeout.map(|timeout| Instant::now() + timeout);
+
+                match msg {
+                    Ok(Message::Text(text)) => match handle_websocket_message(
+                        &text,
+                        state,
+                        listener.min_emit_magnitude(),
+                    ) {
+                        Ok(events) => {
+                            last_event = Instant::now();
+                            emitter.emit(events);
+                        }
+                        // A malformed payload only loses that event, keep listening
+                        Err(ClientError::Parse(e)) => {
+                            log::warn!("Skipping malformed WebSocket message: {}", e);
+                        }
+                        Err(e) => {
+                            log::error!("Error handling WebSocket message: {}", e);
+                        }
+                    },
+                    Ok(Message::Close(_)) => {
+                        log::info!("WebSocket closed by server");
+                        break;
+                    }
+                    Ok(_) => {
+                        log::warn!("Received unexpected message");
+                    }
+                    Err(e) => {
+                        log::error!("WebSocket error: {}", e);
+                        return Err(ClientError::Network(format!("WebSocket error: {}", e)));
+                    }
+                }
+            }
+            _ = sleep_until(emitter.deadline.unwrap_or_else(Instant::now)), if emitter.deadline.is_some() => {
+                emitter.flush();
+            }
+            _ = heartbeat.tick() => {
+                emit_status(
+                    on_status,
+                    ListenerStatus::Heartbeat {
+                        state: ConnectionState::Connected,
+                        seconds_since_last_event: last_event.elapsed().as_secs(),
+                    },
+                );
+            }
+            _ = sleep_until(idle_deadline.unwrap_or_else(Instant::now)), if idle_deadline.is_some() => {
+                log::warn!("No WebSocket message within the idle timeout, reconnecting");
+                emitter.flush();
+                return Ok(StreamEnd::IdleTimeout);
+            }
+            _ = listener.stopped() => {
+                log::info!("Stop requested, closing WebSocket");
+                break;
+            }
+        }
+    }
+
+    // Don't drop events still waiting for their window to close
+    emitter.flush();
+
+    Ok(StreamEnd::Closed)
+}
+
+/// Parse a message and store its events, returning the ones at or above
+/// `min_emit_magnitude` for emission
+fn handle_websocket_message(
+    text: &str,
+    state: &AppState,
+    min_emit_magnitude: Option<f64>,
+) -> ClientResult<Vec<WssEvent>> {
+    log::trace!("Received WebSocket message: {}", text);
+
+    let mut wss_events = WssEvent::parse_message(text)
+        .map_err(|e| ClientError::Parse(format!("Failed to parse WebSocket message: {}", e)))?;
+
+    log::debug!(
+        "Parsed {} WebSocket event(s): {:?}",
+        wss_events.len(),
+        wss_events
+    );
+
+    // Add events to state
+    {
+        let mut state_guard = state
+            .lock()
+            .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?;
+
+        // Resolved here as well as on storage, so skipped events aren't emitted
+        let missing_magnitude = state_guard.missing_magnitude();
+        let before = wss_events.len();
+        wss_events.retain_mut(|wss_event| missing_magnitude.resolve(&mut wss_event.data));
+        if wss_events.len() < before {
+            log::warn!(
+                "Skipped {} WebSocket event(s) without a magnitude",
+                before - wss_events.len()
+            );
+        }
+
+        // A failing event is skipped so the rest of the batch is still
+        // stored and emitted
+        let round_magnitudes = state_guard.rounds_realtime_magnitudes();
+        wss_events.retain_mut(|wss_event| {
+            if round_magnitudes {
+                // The REST catalog reports one decimal, so match it to keep
+                // both sources in the same magnitude buckets
+                wss_event.data.magnitude = (wss_event.data.magnitude * 10.0).round() / 10.0;
+            }
+            match state_guard.add_or_update_event(wss_event.data.clone()) {
+                Ok(_) => true,
+                Err(e) => {
+                    log::error!(
+                        "Failed to add WebSocket event {} to state: {}",
+                        wss_event.data.id,
+                        e
+                    );
+                    false
+                }
+            }
+        });
+    }
+
+    if let Some(min_magnitude) = min_emit_magnitude {
+        wss_events.retain(|wss_event| wss_event.data.magnitude >= min_magnitude);
+    }
+
+    Ok(wss_events)
+}
+
+/// Emit the stored events over `channel` in chronological order as `Create`
+/// actions, waiting between events for their real time difference divided by
+/// `speed`. Stops early when a replay stop is requested and returns the
+/// number of events sent.
+pub(crate) async fn replay_internal(
+    state: &AppState,
+    listener: &ListenerState,
+    channel: &Channel<WssEvent>,
+    speed: f64,
+) -> ClientResult<usize> {
+    if !speed.is_finite() || speed <= 0.0 {
+        return Err(QuakeTrackerError::validation(
+            "speed",
+            format!("Replay speed must be a positive number, got {}", speed),
+        )
+        .into());
+    }
+
+    let events = state
+        .lock()
+        .map_err(|e| ClientError::Internal(format!("Failed to acquire state lock: {}", e)))?
+        .get_chronological_events()?;
+    log::info!("Replaying {} events at {}x speed", events.len(), speed);
+
+    listener.replay_stop.reset();
+    let mut previous_time: Option<chrono::DateTime<chrono::Utc>> = None;
+    let mut sent = 0;
+    for event in events {
+        if let Some(previous_time) = previous_time {
+            let gap = (event.time - previous_time).to_std().unwrap_or_default();
+            tokio::select! {
+                _ = sleep(gap.div_f64(speed)) => {}
+                _ = listener.replay_stop.wait() => {}
+            }
+        }
+        if listener.replay_stop.is_requested() {
+            log::info!("Replay stopped after {} events", sent);
+            break;
+        }
+
+        previous_time = Some(event.time);
+        channel
+            .send(WssEvent {
+                action: WssAction::Create,
+                data: event,
+            })
+            .map_err(|e| {
+                ClientError::Internal(format!("Failed to send event to frontend: {}", e))
+            })?;
+        sent += 1;
+    }
+
+    Ok(sent)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicU32;
+    use std::sync::{Arc, Mutex};
+
+    use futures_util::stream;
+
+    use super::*;
+    use crate::seismic::SeismicEvent;
+    use crate::state::{DataConfig, SeismicData};
+
+    fn status_recorder() -> (Channel<ListenerStatus>, Arc<Mutex<Vec<ListenerStatus>>>) {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        let channel = Channel::new(move |body: tauri::ipc::InvokeResponseBody| {
+            sink.lock().unwrap().push(body.deserialize().unwrap());
+            Ok(())
+        });
+        (channel, received)
+    }
+
+    fn listener_with(max_retries: u32, delay_ms: u64) -> ListenerState {
+        let listener = ListenerState::default();
+        listener
+            .set_reconnect_config(ReconnectConfig {
+                max_retries,
+                initial_delay_ms: delay_ms,
+                max_delay_ms: delay_ms,
+                ..Default::default()
+            })
+            .unwrap();
+        listener
+    }
+
+    #[test]
+    fn test_backoff_sequence() {
+        let config = ReconnectConfig::default();
+
+        let delays: Vec<u128> = (1..=7)
+            .map(|attempt| config.delay_for_attempt(attempt).as_millis())
+            .collect();
+
+        assert_eq!(delays, vec![1000, 2000, 4000, 8000, 16000, 30000, 30000]);
+        assert_eq!(config.delay_for_attempt(200).as_millis(), 30000);
+    }
+
+    #[test]
+    fn test_reconnect_config_validation() {
+        assert!(ReconnectConfig::default().validate().is_ok());
+
+        let zero_delay = ReconnectConfig {
+            initial_delay_ms: 0,
+            ..Default::default()
+        };
+        assert!(zero_delay.validate().is_err());
+
+        let inverted = ReconnectConfig {
+            initial_delay_ms: 5000,
+            max_delay_ms: 1000,
+            ..Default::default()
+        };
+        assert!(inverted.validate().is_err());
+
+        let listener = ListenerState::default();
+        assert!(listener.set_reconnect_config(inverted).is_err());
+        assert_eq!(listener.reconnect_config(), ReconnectConfig::default());
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries() {
+        let attempts = AtomicU32::new(0);
+
+        let (on_status, statuses) = status_recorder();
+
+        let result = run_with_reconnect(&listener_with(3, 1), Some(&on_status), || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async { Err(ClientError::Network("refused".to_string())) }
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        let states: Vec<ConnectionState> = statuses
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|status| match status {
+                ListenerStatus::StateChanged { state } => Some(*state),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            states,
+            vec![
+                ConnectionState::Reconnecting,
+                ConnectionState::Reconnecting,
+                ConnectionState::Disconnected,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_infinite_retries_past_five() {
+        let attempts = AtomicU32::new(0);
+
+        let result = run_with_reconnect(&listener_with(0, 1), None, || {
+            let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+            async move {
+                if attempt < 12 {
+                    Err(ClientError::Network("refused".to_string()))
+                } else {
+                    Ok(StreamEnd::Closed)
+                }
+            }
+        })
+        .await;
+
+        assert!(result.is_ok());
+        assert_eq!(attempts.load(Ordering::SeqCst), 12);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_heartbeat_reports_idle_time() {
+        let state: AppState = Mutex::new(SeismicData::new());
+        let on_event = Channel::new(|_| Ok(()));
+        let (on_status, statuses) = status_recorder();
+        let mut idle_stream = stream::pending::<std::result::Result<Message, tungstenite::Error>>();
+
+        let result = tokio::time::timeout(
+            Duration::from_secs(35),
+            listen_on_stream(
+                &mut idle_stream,
+                &state,
+                &ListenerState::default(),
+                &on_event,
+                Some(&on_status),
+                Duration::from_secs(10),
+            ),
+        )
+        .await;
+
+        // The stream never ends, so only the timeout stops the loop
+        assert!(result.is_err());
+
+        let idle: Vec<u64> = statuses
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|status| match status {
+                ListenerStatus::Heartbeat {
+                    state: ConnectionState::Connected,
+                    seconds_since_last_event,
+                } => Some(*seconds_since_last_event),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(idle, vec![10, 20, 30]);
+    }
+
+    fn unlimited_state() -> AppState {
+        Mutex::new(SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        }))
+    }
+
+    /// A websocket message creating one event with the given magnitude
+    fn wss_message(magnitude: f64) -> String {
+        format!(
+            r#"{{"action":"create","data":{{"type":"Feature",
+            "geometry":{{"type":"Point","coordinates":[7.8865,46.0554,-8.0]}},
+            "id":"20241214_0000249",
+            "properties":{{"source_id":"1744000","source_catalog":"EMSC-RTS",
+            "lastupdate":"2024-12-15T18:26:38.787209Z","time":"2024-12-14T09:39:47.2Z",
+            "flynn_region":"SWITZERLAND","lat":46.0554,"lon":7.8865,"depth":8.0,
+            "evtype":"ke","auth":"ETHZ","mag":{},"magtype":"ml",
+            "unid":"20241214_0000249"}}}}}}"#,
+            magnitude
+        )
+    }
+
+    #[test]
+    fn test_rounded_realtime_magnitudes_match_rest_buckets() {
+        // The REST catalog reports the event as M2.4, the websocket as M2.37
+        let rest_event = WssEvent::parse_message(&wss_message(2.4)).unwrap()[0]
+            .data
+            .clone();
+        let rest = unlimited_state();
+        rest.lock().unwrap().add_events(vec![rest_event]).unwrap();
+        let rest_buckets = rest
+            .lock()
+            .unwrap()
+            .get_analytics()
+            .get_magnitude_distribution()
+            .unwrap();
+
+        let raw = unlimited_state();
+        assert!(!raw.lock().unwrap().rounds_realtime_magnitudes());
+        handle_websocket_message(&wss_message(2.37), &raw, None).unwrap();
+        let raw_buckets = raw
+            .lock()
+            .unwrap()
+            .get_analytics()
+            .get_magnitude_distribution()
+            .unwrap();
+        assert_ne!(raw_buckets, rest_buckets);
+
+        let rounded = unlimited_state();
+        rounded.lock().unwrap().set_round_realtime_magnitudes(true);
+        let emitted = handle_websocket_message(&wss_message(2.37), &rounded, None).unwrap();
+        assert_eq!(emitted[0].data.magnitude, 2.4);
+
+        let data = rounded.lock().unwrap();
+        assert_eq!(data.get_events().unwrap()[0].magnitude, 2.4);
+        assert_eq!(
+            data.get_analytics().get_magnitude_distribution().unwrap(),
+            rest_buckets
+        );
+    }
+
+    /// Records the JSON of everything sent over the event channel
+    fn event_recorder() -> (Channel<EventPayload>, Arc<Mutex<Vec<serde_json::Value>>>) {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        let channel = Channel::new(move |body: tauri::ipc::InvokeResponseBody| {
+            sink.lock().unwrap().push(body.deserialize().unwrap());
+            Ok(())
+        });
+        (channel, received)
+    }
+
+    /// Records the JSON of everything sent over a replay channel
+    fn replay_recorder() -> (Channel<WssEvent>, Arc<Mutex<Vec<serde_json::Value>>>) {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        let channel = Channel::new(move |body: tauri::ipc::InvokeResponseBody| {
+            sink.lock().unwrap().push(body.deserialize().unwrap());
+            Ok(())
+        });
+        (channel, received)
+    }
+
+    fn replay_state() -> AppState {
+        let state = unlimited_state();
+        let base = chrono::Utc::now();
+        let mut data = state.lock().unwrap();
+        for (id, hours) in [("second", 2), ("third", 5), ("first", 0)] {
+            let mut event = SeismicEvent::test_event();
+            event.id = id.to_string();
+            event.time = base + chrono::TimeDelta::hours(hours);
+            data.add_or_update_event(event).unwrap();
+        }
+        drop(data);
+        state
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replay_sends_events_in_chronological_order() {
+        let state = replay_state();
+        let listener = ListenerState::default();
+        let (channel, received) = replay_recorder();
+
+        let sent = replay_internal(&state, &listener, &channel, 3600.0)
+            .await
+            .unwrap();
+
+        assert_eq!(sent, 3);
+        let received = received.lock().unwrap();
+        assert!(received.iter().all(|value| value["action"] == "create"));
+        let ids: Vec<_> = received
+            .iter()
+            .map(|value| value["data"]["unid"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(ids, vec!["first", "second", "third"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replay_stops_when_requested() {
+        let state = replay_state();
+        let listener = ListenerState::default();
+        let (channel, received) = replay_recorder();
+
+        // At 1x the second event is two hours away, so the stop lands first
+        let (sent, _) = tokio::join!(replay_internal(&state, &listener, &channel, 1.0), async {
+            sleep(Duration::from_secs(60)).await;
+            listener.request_replay_stop();
+        });
+
+        assert_eq!(sent.unwrap(), 1);
+        assert_eq!(received.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_replay_rejects_invalid_speed() {
+        let state = unlimited_state();
+        let listener = ListenerState::default();
+        let (channel, _) = replay_recorder();
+
+        for speed in [0.0, -1.0, f64::NAN] {
+            assert!(replay_internal(&state, &listener, &channel, speed)
+                .await
+                .is_err());
+        }
+    }
+
+    fn feature(id: &str) -> String {
+        format!(
+            r#"{{"type":"Feature","geometry":{{"type":"Point","coordinates":[22.36,38.49,-5]}},"id":"{id}","properties":{{"source_id":"1741829","source_catalog":"EMSC-RTS","lastupdate":"2024-12-10T22:28:22.145984Z","time":"2024-12-10T22:25:50.4Z","flynn_region":"GREECE","lat":38.49,"lon":22.36,"depth":5,"evtype":"ke","auth":"THE","mag":2.1,"magtype":"ml","unid":"{id}"}}}}"#
+        )
+    }
+
+    fn create_message(data: &str) -> Message {
+        Message::text(format!(r#"{{"action":"create","data":{}}}"#, data))
+    }
+
+    async fn listen_to_end<S>(
+        messages: &mut S,
+        state: &AppState,
+        listener: &ListenerState,
+        on_event: &Channel<EventPayload>,
+    ) where
+        S: Stream<Item = std::result::Result<Message, tungstenite::Error>> + Unpin,
+    {
+        let result = listen_on_stream(
+            messages,
+            state,
+            listener,
+            on_event,
+            None,
+            HEARTBEAT_INTERVAL,
+        )
+        .await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_malformed_message_is_skipped() {
+        let state = unlimited_state();
+        let (on_event, emitted) = event_recorder();
+
+        let missing_properties = r#"{"type":"Feature","geometry":{"type":"Point","coordinates":[22.36,38.49,-5]},"id":"20241210_0000313","properties":{"flynn_region":"GREECE"}}"#;
+        let mut messages = stream::iter(
+            vec![
+                create_message(missing_properties),
+                create_message(r#"{"type":"Feature"}"#),
+                Message::text("not json"),
+                create_message(&feature("20241210_0000314")),
+            ]
+            .into_iter()
+            .map(Ok),
+        );
+
+        listen_to_end(&mut messages, &state, &ListenerState::default(), &on_event).await;
+
+        assert_eq!(state.lock().unwrap().get_stats().total_events, 1);
+        assert_eq!(emitted.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_batched_message_ingests_all_features() {
+        let state = unlimited_state();
+        let (on_event, emitted) = event_recorder();
+
+        let batch = format!(
+            r#"{{"type":"FeatureCollection","features":[{},{}]}}"#,
+            feature("20241210_0000314"),
+            feature("20241210_0000315")
+        );
+        let mut messages = stream::iter(
+            vec![
+                create_message(&batch),
+                // Single features are still accepted
+                create_message(&feature("20241210_0000316")),
+            ]
+            .into_iter()
+            .map(Ok),
+        );
+
+        listen_to_end(&mut messages, &state, &ListenerState::default(), &on_event).await;
+
+        assert_eq!(state.lock().unwrap().get_stats().total_events, 3);
+        let ids: Vec<String> = emitted
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|event| event["data"]["unid"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            ids,
+            vec!["20241210_0000314", "20241210_0000315", "20241210_0000316"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_send_keeps_listening() {
+        // Channel that rejects its first send only
+        let failing_recorder = || {
+            let received = Arc::new(Mutex::new(Vec::new()));
+            let sink = received.clone();
+            let failed = AtomicBool::new(false);
+            let channel = Channel::new(move |body: tauri::ipc::InvokeResponseBody| {
+                if !failed.swap(true, Ordering::SeqCst) {
+                    return Err(tauri::Error::FailedToReceiveMessage);
+                }
+                let event: serde_json::Value = body.deserialize().unwrap();
+                sink.lock()
+                    .unwrap()
+                    .push(event["data"]["unid"].as_str().unwrap().to_string());
+                Ok(())
+            });
+            (channel, received)
+        };
+        let messages = || {
+            stream::iter(
+                ["a", "b", "c"]
+                    .map(|id| create_message(&feature(id)))
+                    .into_iter()
+                    .map(Ok),
+            )
+        };
+
+        // The failed send is retried by default
+        let state = unlimited_state();
+        let (on_event, emitted) = failing_recorder();
+        listen_to_end(
+            &mut messages(),
+            &state,
+            &ListenerState::default(),
+            &on_event,
+        )
+        .await;
+        assert_eq!(*emitted.lock().unwrap(), ["a", "b", "c"]);
+
+        // Without retries only the first event misses the frontend
+        let state = unlimited_state();
+        let listener = ListenerState::default();
+        listener.set_send_retries(0).unwrap();
+        let (on_event, emitted) = failing_recorder();
+        listen_to_end(&mut messages(), &state, &listener, &on_event).await;
+        assert_eq!(*emitted.lock().unwrap(), ["b", "c"]);
+        assert_eq!(state.lock().unwrap().get_stats().total_events, 3);
+
+        assert!(listener.set_send_retries(MAX_SEND_RETRIES + 1).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_events_below_emit_floor_are_stored_only() {
+        let state = unlimited_state();
+        let (on_event, emitted) = event_recorder();
+        let listener = ListenerState::default();
+        listener.set_min_emit_magnitude(Some(4.0)).unwrap();
+
+        let with_magnitude = |id: &str, magnitude: f64| {
+            create_message(&feature(id).replace(r#""mag":2.1"#, &format!(r#""mag":{magnitude}"#)))
+        };
+        let mut messages = stream::iter(
+            vec![
+                with_magnitude("micro", 1.0),
+                with_magnitude("significant", 5.0),
+            ]
+            .into_iter()
+            .map(Ok),
+        );
+
+        listen_to_end(&mut messages, &state, &listener, &on_event).await;
+
+        let ids: Vec<String> = emitted
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|event| event["data"]["unid"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(ids, ["significant"]);
+        assert_eq!(state.lock().unwrap().get_stats().total_events, 2);
+
+        assert!(listener.set_min_emit_magnitude(Some(12.0)).is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_coalescing_batches_rapid_events() {
+        let state = unlimited_state();
+        let (on_event, emitted) = event_recorder();
+        let listener = ListenerState::default();
+        listener.set_coalesce_window_ms(Some(100)).unwrap();
+
+        // Three events in a burst, a pause, then two more
+        let schedule = vec![(0, "a"), (10, "b"), (10, "c"), (500, "d"), (10, "e")];
+        let messages = stream::iter(schedule).then(|(delay_ms, id)| async move {
+            sleep(Duration::from_millis(delay_ms)).await;
+            Ok::<_, tungstenite::Error>(create_message(&feature(id)))
+        });
+        let mut messages = std::pin::pin!(messages);
+
+        listen_to_end(&mut messages, &state, &listener, &on_event).await;
+
+        let batches: Vec<Vec<String>> = emitted
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|batch| {
+                batch
+                    .as_array()
+                    .expect("coalesced payload should be an array")
+                    .iter()
+                    .map(|event| event["data"]["unid"].as_str().unwrap().to_string())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(batches, vec![vec!["a", "b", "c"], vec!["d", "e"]]);
+        assert_eq!(state.lock().unwrap().get_stats().total_events, 5);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_silent_connection_reconnects_after_idle_timeout() {
+        let state: AppState = Mutex::new(SeismicData::new());
+        let listener = ListenerState::default();
+        listener
+            .set_reconnect_config(ReconnectConfig {
+                max_retries: 3,
+                initial_delay_ms: 1,
+                max_delay_ms: 1,
+                idle_timeout_ms: 60_000,
+            })
+            .unwrap();
+        let on_event = Channel::new(|_| Ok(()));
+        let attempts = AtomicU32::new(0);
+        let started = Instant::now();
+
+        let result = run_with_reconnect(&listener, None, || {
+            let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+            let (state, listener, on_event) = (&state, &listener, &on_event);
+            async move {
+                if attempt > 1 {
+                    return Ok(StreamEnd::Closed);
+                }
+                // One ping, then silence without a close frame
+                let mut half_open = stream::iter(vec![Ok(Message::Ping(Vec::new().into()))])
+                    .chain(stream::pending());
+                listen_on_stream(
+                    &mut half_open,
+                    state,
+                    listener,
+                    on_event,
+                    None,
+                    HEARTBEAT_INTERVAL,
+                )
+                .await
+            }
+        })
+        .await;
+
+        assert!(result.is_ok());
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_secs(60), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(61), "{:?}", elapsed);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_idle_timeouts_do_not_use_retry_budget() {
+        let state: AppState = Mutex::new(SeismicData::new());
+        let listener = ListenerState::default();
+        listener
+            .set_reconnect_config(ReconnectConfig {
+                max_retries: 2,
+                initial_delay_ms: 1,
+                max_delay_ms: 1,
+                idle_timeout_ms: 60_000,
+            })
+            .unwrap();
+        let on_event = Channel::new(|_| Ok(()));
+        let attempts = AtomicU32::new(0);
+
+        let result = run_with_reconnect(&listener, None, || {
+            let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+            let (state, listener, on_event) = (&state, &listener, &on_event);
+            async move {
+                match attempt {
+                    // A refused connection between quiet periods
+                    3 => Err(ClientError::Network("refused".to_string())),
+                    7 => Ok(StreamEnd::Closed),
+                    _ => {
+                        let mut silent = stream::pending();
+                        listen_on_stream(
+                            &mut silent,
+                            state,
+                            listener,
+                            on_event,
+                            None,
+                            HEARTBEAT_INTERVAL,
+                        )
+                        .await
+                    }
+                }
+            }
+        })
+        .await;
+
+        assert!(result.is_ok());
+        assert_eq!(attempts.load(Ordering::SeqCst), 7);
+    }
+
+    #[test]
+    fn test_idle_timeout_defaults_for_older_configs() {
+        let config: ReconnectConfig = serde_json::from_str(
+            r#"{"max_retries":5,"initial_delay_ms":1000,"max_delay_ms":30000}"#,
+        )
+        .unwrap();
+        assert_eq!(config, ReconnectConfig::default());
+
+        let disabled = ReconnectConfig {
+            idle_timeout_ms: 0,
+            ..Default::default()
+        };
+        assert_eq!(disabled.idle_timeout(), None);
+    }
+
+    #[test]
+    fn test_coalesce_window_validation() {
+        let listener = ListenerState::default();
+        assert_eq!(listener.coalesce_window(), None);
+
+        assert!(listener.set_coalesce_window_ms(Some(0)).is_err());
+        assert!(listener.set_coalesce_window_ms(Some(60_000)).is_err());
+        assert_eq!(listener.coalesce_window(), None);
+
+        listener.set_coalesce_window_ms(Some(250)).unwrap();
+        assert_eq!(listener.coalesce_window(), Some(Duration::from_millis(250)));
+        listener.set_coalesce_window_ms(None).unwrap();
+        assert_eq!(listener.coalesce_window(), None);
+    }
+
+    #[tokio::test]
+    async fn test_stop_ends_open_connection() {
+        let state: AppState = Mutex::new(SeismicData::new());
+        let listener = listener_with(0, 1);
+        let on_event = Channel::new(|_| Ok(()));
+        let (on_status, statuses) = status_recorder();
+
+        let listen = run_with_reconnect(&listener, Some(&on_status), || async {
+            let mut open_stream =
+                stream::pending::<std::result::Result<Message, tungstenite::Error>>();
+            listen_on_stream(
+                &mut open_stream,
+                &state,
+                &listener,
+                &on_event,
+                None,
+                HEARTBEAT_INTERVAL,
+            )
+            .await
+        });
+        let stop = async {
+            sleep(Duration::from_millis(20)).await;
+            listener.request_stop();
+        };
+
+        let (result, _) = tokio::join!(tokio::time::timeout(Duration::from_secs(1), listen), stop);
+
+        assert!(result.expect("listener did not stop in time").is_ok());
+        assert_eq!(
+            statuses.lock().unwrap().last(),
+            Some(&ListenerStatus::StateChanged {
+                state: ConnectionState::Disconnected
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stop_interrupts_backoff() {
+        let listener = listener_with(0, 60_000);
+        let attempts = AtomicU32::new(0);
+
+        let listen = run_with_reconnect(&listener, None, || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async { Err(ClientError::Network("refused".to_string())) }
+        });
+        let stop = async {
+            sleep(Duration::from_millis(20)).await;
+            listener.request_stop();
+        };
+
+        let (result, _) = tokio::join!(tokio::time::timeout(Duration::from_secs(1), listen), stop);
+
+        assert!(result.expect("listener did not stop in time").is_ok());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+}
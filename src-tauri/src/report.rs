@@ -0,0 +1,76 @@
+use std::fmt::Write;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Number of regions listed in a catalog report
+pub const REPORT_TOP_REGIONS: usize = 5;
+
+/// Shareable summary of the loaded catalog combining the headline analytics
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CatalogReport {
+    pub total_events: usize,
+    pub first_event: Option<DateTime<Utc>>,
+    pub last_event: Option<DateTime<Utc>>,
+    pub min_magnitude: Option<f64>,
+    pub max_magnitude: Option<f64>,
+    pub b_value: f64,
+    pub b_value_uncertainty: f64,
+    pub completeness_magnitude: f64,
+    pub top_regions: Vec<(String, u32)>,
+    pub total_energy_joules: f64,
+}
+
+impl CatalogReport {
+    /// Render the report as a Markdown document
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = String::new();
+        let time_format = "%Y-%m-%d %H:%M UTC";
+
+        let time_span = match (self.first_event, self.last_event) {
+            (Some(first), Some(last)) => format!(
+                "{} – {}",
+                first.format(time_format),
+                last.format(time_format)
+            ),
+            _ => "n/a".to_string(),
+        };
+        let magnitude_range = match (self.min_magnitude, self.max_magnitude) {
+            (Some(min), Some(max)) => format!("M{:.1} – M{:.1}", min, max),
+            _ => "n/a".to_string(),
+        };
+
+        // Writing to a String cannot fail
+        let _ = writeln!(markdown, "# Seismic Catalog Report\n");
+        let _ = writeln!(markdown, "| Metric | Value |");
+        let _ = writeln!(markdown, "|---|---|");
+        let _ = writeln!(markdown, "| Total events | {} |", self.total_events);
+        let _ = writeln!(markdown, "| Time span | {} |", time_span);
+        let _ = writeln!(markdown, "| Magnitude range | {} |", magnitude_range);
+        let _ = writeln!(
+            markdown,
+            "| b-value | {:.2} ± {:.2} |",
+            self.b_value, self.b_value_uncertainty
+        );
+        let _ = writeln!(
+            markdown,
+            "| Completeness magnitude | M{:.1} |",
+            self.completeness_magnitude
+        );
+        let _ = writeln!(
+            markdown,
+            "| Total energy | {:.2e} J |",
+            self.total_energy_joules
+        );
+
+        let _ = writeln!(markdown, "\n## Top Regions\n");
+        if self.top_regions.is_empty() {
+            let _ = writeln!(markdown, "No events recorded.");
+        }
+        for (rank, (region, count)) in self.top_regions.iter().enumerate() {
+            let _ = writeln!(markdown, "{}. {} ({} events)", rank + 1, region, count);
+        }
+
+        markdown
+    }
+}
@@ -1,19 +1,31 @@
+use std::collections::BTreeMap;
 use std::io::Cursor;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, FixedOffset, NaiveDateTime, Utc};
 use geojson::de::deserialize_geometry;
 use geojson::ser::serialize_geometry;
 use serde::{Deserialize, Serialize};
 
 // Generated from: https://www.emsc-csem.org/Files/epos/specifications/Specs_fdsnevent-WS.pdf
 
+/// Largest accepted difference between the geometry and the `lat`/`lon`
+/// properties, in degrees
+const COORDINATE_TOLERANCE_DEGREES: f64 = 0.01;
+/// Largest accepted difference between the geometry's third coordinate and the
+/// `depth` property, in kilometers
+const DEPTH_TOLERANCE_KM: f64 = 1.0;
+
+/// Magnitude types in order of preference: moment magnitude, then surface
+/// wave, body wave and local magnitudes
+pub const DEFAULT_MAGNITUDE_PRIORITY: &[&str] = &["mw", "ms", "mb", "ml"];
+/// Evaluation mode reported for events whose best origin doesn't give one
+pub const UNKNOWN_EVALUATION_MODE: &str = "unknown";
+
 /// Main event feature representing an earthquake event
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "RawSeismicEvent")]
 pub struct SeismicEvent {
-    #[serde(
-        serialize_with = "serialize_geometry",
-        deserialize_with = "deserialize_geometry"
-    )]
+    #[serde(serialize_with = "serialize_geometry")]
     pub geometry: geo_types::Point<f64>,
     #[serde(rename = "source_id")]
     pub source_id: String,
@@ -44,9 +56,248 @@ pub struct SeismicEvent {
     pub origins: Option<OriginCollection>,
     #[serde(default)]
     pub arrivals: Option<Vec<Arrival>>,
+    /// Whether the geometry disagreed with the `lat`, `lon` or `depth`
+    /// properties when parsed. Not stored, so always false for events read
+    /// back from the catalog.
+    #[serde(default)]
+    pub coordinate_mismatch: bool,
+    /// Whether EMSC reported a null `mag`, in which case `magnitude` is NaN
+    /// until [`MissingMagnitude`] drops or fills the event on ingestion
+    #[serde(default)]
+    pub magnitude_missing: bool,
+}
+
+/// What ingestion does with events EMSC reports with a null magnitude
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingMagnitude {
+    /// Drop the event, logging how many were dropped
+    #[default]
+    Skip,
+    /// Keep the event with the given placeholder magnitude
+    Fill(f64),
+}
+
+impl MissingMagnitude {
+    /// Fill in the magnitude of `event` if it is missing, returning whether
+    /// the event is kept
+    pub fn resolve(self, event: &mut SeismicEvent) -> bool {
+        if !event.magnitude_missing {
+            return true;
+        }
+        match self {
+            MissingMagnitude::Skip => false,
+            MissingMagnitude::Fill(magnitude) => {
+                event.magnitude = magnitude;
+                true
+            }
+        }
+    }
+
+    /// Drop or fill the events without a magnitude
+    pub fn apply(self, mut events: Vec<SeismicEvent>) -> Vec<SeismicEvent> {
+        let before = events.len();
+        events.retain_mut(|event| self.resolve(event));
+        let skipped = before - events.len();
+        if skipped > 0 {
+            log::warn!("Skipped {} event(s) without a magnitude", skipped);
+        }
+        events
+    }
+}
+
+/// Event as received from EMSC, before its geometry is checked against the
+/// coordinate properties
+#[derive(Deserialize)]
+struct RawSeismicEvent {
+    #[serde(deserialize_with = "deserialize_point_with_depth")]
+    geometry: (geo_types::Point<f64>, Option<f64>),
+    source_id: String,
+    source_catalog: String,
+    #[serde(rename = "lastupdate", deserialize_with = "deserialize_seismic_time")]
+    last_update: DateTime<Utc>,
+    #[serde(deserialize_with = "deserialize_seismic_time")]
+    time: DateTime<Utc>,
+    #[serde(rename = "lat")]
+    latitude: f64,
+    #[serde(rename = "lon")]
+    longitude: f64,
+    depth: f64,
+    #[serde(rename = "evtype")]
+    event_type: String,
+    #[serde(rename = "auth")]
+    author: String,
+    #[serde(rename = "mag")]
+    magnitude: Option<f64>,
+    #[serde(rename = "magtype")]
+    magnitude_type: String,
+    flynn_region: String,
+    #[serde(rename = "unid")]
+    id: String,
+    origins: Option<OriginCollection>,
+    #[serde(default)]
+    arrivals: Option<Vec<Arrival>>,
+}
+
+impl From<RawSeismicEvent> for SeismicEvent {
+    fn from(raw: RawSeismicEvent) -> Self {
+        let (geometry, geometry_depth) = raw.geometry;
+        // EMSC encodes depth as a negative elevation in the geometry
+        let depth_mismatch = geometry_depth
+            .is_some_and(|elevation| (-elevation - raw.depth).abs() > DEPTH_TOLERANCE_KM);
+        let position_mismatch = (geometry.y() - raw.latitude).abs() > COORDINATE_TOLERANCE_DEGREES
+            || (geometry.x() - raw.longitude).abs() > COORDINATE_TOLERANCE_DEGREES;
+
+        if depth_mismatch || position_mismatch {
+            log::warn!(
+                "Event {} geometry {:?} (z {:?}) disagrees with properties lat {} lon {} depth {}",
+                raw.id,
+                geometry,
+                geometry_depth,
+                raw.latitude,
+                raw.longitude,
+                raw.depth
+            );
+        }
+
+        Self {
+            geometry,
+            source_id: raw.source_id,
+            source_catalog: raw.source_catalog,
+            last_update: raw.last_update,
+            time: raw.time,
+            latitude: raw.latitude,
+            longitude: raw.longitude,
+            depth: raw.depth,
+            event_type: raw.event_type,
+            author: raw.author,
+            magnitude: raw.magnitude.unwrap_or(f64::NAN),
+            magnitude_type: raw.magnitude_type,
+            flynn_region: raw.flynn_region,
+            id: raw.id,
+            origins: raw.origins,
+            arrivals: raw.arrivals,
+            coordinate_mismatch: depth_mismatch || position_mismatch,
+            magnitude_missing: raw.magnitude.is_none(),
+        }
+    }
+}
+
+/// Deserialize a GeoJSON point, keeping its optional third coordinate
+fn deserialize_point_with_depth<'de, D>(
+    deserializer: D,
+) -> Result<(geo_types::Point<f64>, Option<f64>), D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let geometry = geojson::Geometry::deserialize(deserializer)?;
+    match geometry.value {
+        geojson::Value::Point(position) if position.len() >= 2 => Ok((
+            geo_types::Point::new(position[0], position[1]),
+            position.get(2).copied(),
+        )),
+        other => Err(serde::de::Error::custom(format!(
+            "Expected a Point geometry, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+/// Parse an event timestamp as sent by EMSC, which doesn't always stick to
+/// RFC 3339: fractional seconds and the trailing `Z` may be missing and the
+/// date and time may be separated by a space. Times without an offset are
+/// taken as UTC.
+pub fn parse_seismic_time(value: &str) -> crate::error::Result<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.to_utc());
+    }
+
+    let naive = value.strip_suffix(['Z', 'z']).unwrap_or(value);
+    let time = NaiveDateTime::parse_from_str(naive, "%Y-%m-%dT%H:%M:%S%.f")
+        .or_else(|_| NaiveDateTime::parse_from_str(naive, "%Y-%m-%d %H:%M:%S%.f"))?;
+    Ok(time.and_utc())
+}
+
+/// Deserialize a timestamp with `parse_seismic_time`
+fn deserialize_seismic_time<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    parse_seismic_time(&value).map_err(serde::de::Error::custom)
 }
 
 impl SeismicEvent {
+    /// The origin the event's solution was taken from, falling back to the
+    /// first origin. Origins are only present when fetched with
+    /// `includeallorigins`.
+    pub fn best_origin(&self) -> Option<&Origin> {
+        let origins = &self.origins.as_ref()?.origins;
+        origins
+            .iter()
+            .find(|origin| {
+                origin.source_id == self.source_id && origin.source_catalog == self.source_catalog
+            })
+            .or_else(|| origins.first())
+    }
+
+    /// Drop every origin except the best one, shrinking events fetched with
+    /// all origins while keeping the uncertainties and station coverage that
+    /// are read from the best origin
+    pub fn retain_best_origin(&mut self) {
+        let best = self.best_origin().cloned();
+        if let Some(collection) = self.origins.as_mut() {
+            collection.origins = best.into_iter().collect();
+        }
+    }
+
+    /// Evaluation mode of the best origin, such as `manual` or `automatic`
+    pub fn evaluation_mode(&self) -> Option<&str> {
+        self.best_origin()?.evaluation_mode.as_deref()
+    }
+
+    /// Location and depth uncertainties reported by the best origin
+    pub fn uncertainty(&self) -> Option<LocationUncertainty> {
+        self.best_origin().map(LocationUncertainty::from)
+    }
+
+    /// Magnitude value and type picked by [`DEFAULT_MAGNITUDE_PRIORITY`]
+    pub fn preferred_magnitude(&self) -> (f64, String) {
+        self.preferred_magnitude_by(DEFAULT_MAGNITUDE_PRIORITY)
+    }
+
+    /// Magnitude value and type picked from the origins' magnitudes by
+    /// `priority`, falling back to the event's own magnitude when origins are
+    /// missing or report none of the listed types
+    ///
+    /// Types match case-insensitively and each entry also covers its
+    /// variants, so `mw` matches `Mww` and `mwr`. The best origin is searched
+    /// before the others.
+    pub fn preferred_magnitude_by(&self, priority: &[&str]) -> (f64, String) {
+        let Some(collection) = &self.origins else {
+            return (self.magnitude, self.magnitude_type.clone());
+        };
+        // Searching the best origin again as part of all origins is harmless
+        let origins: Vec<&Origin> = self
+            .best_origin()
+            .into_iter()
+            .chain(&collection.origins)
+            .collect();
+
+        priority
+            .iter()
+            .find_map(|preferred| {
+                let preferred = preferred.to_lowercase();
+                origins
+                    .iter()
+                    .flat_map(|origin| &origin.mags)
+                    .find(|mag| mag.magnitude_type.to_lowercase().starts_with(&preferred))
+            })
+            .map(|mag| (mag.value, mag.magnitude_type.clone()))
+            .unwrap_or_else(|| (self.magnitude, self.magnitude_type.clone()))
+    }
+
     #[cfg(test)]
     pub(crate) fn test_event() -> Self {
         let js = r##"
@@ -85,6 +336,22 @@ impl SeismicEvent {
     }
 }
 
+/// Number of events per evaluation mode of their best origin
+///
+/// Modes are lowercased so `Manual` and `manual` are counted together, and
+/// events without origin detail are counted as [`UNKNOWN_EVALUATION_MODE`].
+pub fn count_evaluation_modes(events: &[SeismicEvent]) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for event in events {
+        let mode = event
+            .evaluation_mode()
+            .map(str::to_lowercase)
+            .unwrap_or_else(|| UNKNOWN_EVALUATION_MODE.to_string());
+        *counts.entry(mode).or_insert(0) += 1;
+    }
+    counts
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OriginCollection {
     #[serde(
@@ -103,9 +370,9 @@ pub struct Origin {
     pub source_id: String,
     #[serde(rename = "Source_catalog")]
     pub source_catalog: String,
-    #[serde(rename = "Lastupdate")]
+    #[serde(rename = "Lastupdate", deserialize_with = "deserialize_seismic_time")]
     pub last_update: DateTime<Utc>,
-    #[serde(rename = "Time")]
+    #[serde(rename = "Time", deserialize_with = "deserialize_seismic_time")]
     pub time: DateTime<Utc>,
     #[serde(rename = "Lat")]
     pub latitude: f64,
@@ -147,6 +414,65 @@ pub struct Origin {
     pub mags: Vec<Magnitude>,
 }
 
+/// Uncertainties of an origin's location solution
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LocationUncertainty {
+    /// Depth uncertainty in kilometers
+    pub depth_uncertainty: Option<f64>,
+    /// Semi-major axis of the horizontal error ellipse in kilometers
+    pub semi_major_axis: Option<f64>,
+    /// Semi-minor axis of the horizontal error ellipse in kilometers
+    pub semi_minor_axis: Option<f64>,
+    /// Azimuth of the major axis in degrees
+    pub major_axis_azimuth: Option<f64>,
+    /// Origin time uncertainty in seconds
+    pub time_uncertainty: Option<f64>,
+    /// Largest azimuthal gap between stations in degrees
+    pub azimuthal_gap: Option<f64>,
+    /// RMS of the travel time residuals in seconds
+    pub standard_error: Option<f64>,
+}
+
+impl From<&Origin> for LocationUncertainty {
+    fn from(origin: &Origin) -> Self {
+        Self {
+            depth_uncertainty: origin.depth_uncertainty,
+            semi_major_axis: origin.semi_major_axis,
+            semi_minor_axis: origin.semi_minor_axis,
+            major_axis_azimuth: origin.major_axis_azimuth,
+            time_uncertainty: origin.time_uncertainty,
+            azimuthal_gap: origin.azimuthal_gap,
+            standard_error: origin.standard_error,
+        }
+    }
+}
+
+/// Event as sent to the frontend, with its origin time converted to local time
+#[derive(Debug, Clone, Serialize)]
+pub struct LocalizedEvent {
+    #[serde(flatten)]
+    pub event: SeismicEvent,
+    pub local_time: DateTime<FixedOffset>,
+}
+
+impl LocalizedEvent {
+    /// Attach the local origin time using `offset`, or the event's nautical
+    /// time zone (15° of longitude per hour) when none is given
+    pub fn new(event: SeismicEvent, offset: Option<FixedOffset>) -> Self {
+        let offset = offset.unwrap_or_else(|| nautical_offset(event.longitude));
+        Self {
+            local_time: event.time.with_timezone(&offset),
+            event,
+        }
+    }
+}
+
+/// Approximate UTC offset for a longitude, ignoring political time zones
+fn nautical_offset(longitude: f64) -> FixedOffset {
+    let hours = (longitude / 15.0).round().clamp(-12.0, 12.0) as i32;
+    FixedOffset::east_opt(hours * 3600).expect("offsets within 12 hours are valid")
+}
+
 /// Magnitude object representing earthquake magnitude details
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Magnitude {
@@ -215,10 +541,16 @@ pub struct StamagObject {
     // Placeholder for potential fields
 }
 
+#[cfg(test)]
 mod test {
-    use chrono::{DateTime, NaiveDate, Utc};
+    use std::io::Cursor;
+
+    use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
 
-    use crate::seismic::SeismicEvent;
+    use crate::seismic::{
+        count_evaluation_modes, parse_seismic_time, LocalizedEvent, Origin, OriginCollection,
+        SeismicEvent, UNKNOWN_EVALUATION_MODE,
+    };
 
     const EXAMPLE_JSON: &'static str = r##"
     {
@@ -304,4 +636,215 @@ mod test {
         assert_eq!(feature_collection[0].time, FIRST_DATE);
         assert!(feature_collection[1].origins.is_none());
     }
+
+    #[test]
+    fn test_parse_seismic_time_variants() {
+        assert_eq!(
+            parse_seismic_time("2024-12-10T22:28:31.49Z").unwrap(),
+            FIRST_DATE
+        );
+
+        let whole_seconds = FIRST_DATE - chrono::TimeDelta::milliseconds(490);
+        assert_eq!(
+            parse_seismic_time("2024-12-10T22:28:31Z").unwrap(),
+            whole_seconds
+        );
+        assert_eq!(
+            parse_seismic_time("2024-12-10T22:28:31").unwrap(),
+            whole_seconds
+        );
+        assert_eq!(
+            parse_seismic_time("2024-12-10 22:28:31").unwrap(),
+            whole_seconds
+        );
+        assert_eq!(
+            parse_seismic_time("2024-12-10 22:28:31.49").unwrap(),
+            FIRST_DATE
+        );
+        assert_eq!(
+            parse_seismic_time("2024-12-10T23:28:31.49+01:00").unwrap(),
+            FIRST_DATE
+        );
+
+        let err = parse_seismic_time("10/12/2024 22:28").unwrap_err();
+        assert_eq!(err.category(), "datetime");
+    }
+
+    #[test]
+    fn test_event_without_fractional_seconds_deserializes() {
+        let json = EXAMPLE_JSON.replace("2024-12-10T22:28:31.49Z", "2024-12-10 22:28:31");
+        let events: Vec<SeismicEvent> =
+            geojson::de::deserialize_feature_collection_str_to_vec(&json).unwrap();
+        assert_eq!(
+            events[0].time,
+            FIRST_DATE - chrono::TimeDelta::milliseconds(490)
+        );
+    }
+
+    fn origin(source_id: &str, depth_uncertainty: f64) -> serde_json::Value {
+        serde_json::json!({
+            "Source_id": source_id,
+            "Source_catalog": "EMSC-RTS",
+            "Lastupdate": "2024-12-10T22:30:25.164009Z",
+            "Time": "2024-12-10T22:28:31.49Z",
+            "Lat": 18.8232,
+            "Lon": -155.4875,
+            "Depth": 16.1,
+            "Sdepth": depth_uncertainty,
+            "Smajor": 1.2,
+            "Sminor": 0.8,
+            "azimut": 135.0
+        })
+    }
+
+    #[test]
+    fn uncertainty_from_best_origin() {
+        let mut event = SeismicEvent::test_event();
+        assert!(event.best_origin().is_none());
+        assert!(event.uncertainty().is_none());
+
+        let collection: OriginCollection = serde_json::from_value(serde_json::json!({
+            "geometry": { "type": "Point", "coordinates": [-155.4875, 18.8232] },
+            "origins": [origin("other", 3.5), origin(&event.source_id, 0.4)]
+        }))
+        .unwrap();
+        event.origins = Some(collection);
+
+        let uncertainty = event.uncertainty().unwrap();
+        assert_eq!(uncertainty.depth_uncertainty, Some(0.4));
+        assert_eq!(uncertainty.semi_major_axis, Some(1.2));
+        assert_eq!(uncertainty.semi_minor_axis, Some(0.8));
+        assert_eq!(uncertainty.major_axis_azimuth, Some(135.0));
+        assert_eq!(uncertainty.azimuthal_gap, None);
+
+        event.origins.as_mut().unwrap().origins.remove(1);
+        assert_eq!(event.uncertainty().unwrap().depth_uncertainty, Some(3.5));
+    }
+
+    fn feature_with_geometry(longitude: f64, latitude: f64, elevation: f64) -> SeismicEvent {
+        let js = format!(
+            r#"{{"type":"Feature","geometry":{{"type":"Point","coordinates":[{longitude},{latitude},{elevation}]}},"id":"20241210_0000315","properties":{{"source_id":"1741830","source_catalog":"EMSC-RTS","lastupdate":"2024-12-10T22:30:25.164009Z","time":"2024-12-10T22:28:31.49Z","flynn_region":"HAWAII REGION, HAWAII","lat":18.8232,"lon":-155.4875,"depth":16.1,"evtype":"ke","auth":"HV","mag":2,"magtype":"md","unid":"20241210_0000315"}}}}"#
+        );
+        geojson::de::deserialize_single_feature(Cursor::new(js)).unwrap()
+    }
+
+    fn origin_with_mags(source_id: &str, mags: &[(&str, f64)]) -> Origin {
+        serde_json::from_value(serde_json::json!({
+            "Source_id": source_id,
+            "Source_catalog": "EMSC-RTS",
+            "Lastupdate": "2024-12-10T22:30:25Z",
+            "Time": "2024-12-10T22:28:31Z",
+            "Lat": 18.8232,
+            "Lon": -155.4875,
+            "Depth": 16.1,
+            "mags": mags
+                .iter()
+                .map(|(kind, value)| serde_json::json!({ "Value": value, "Type": kind }))
+                .collect::<Vec<_>>(),
+        }))
+        .unwrap()
+    }
+
+    fn event_with_origins(origins: Vec<Origin>) -> SeismicEvent {
+        let mut event = SeismicEvent::test_event();
+        event.origins = Some(OriginCollection {
+            geometry: event.geometry,
+            origins,
+        });
+        event
+    }
+
+    fn origin_with_mode(source_id: &str, mode: &str) -> Origin {
+        let mut origin = origin_with_mags(source_id, &[]);
+        origin.evaluation_mode = Some(mode.to_string());
+        origin
+    }
+
+    #[test]
+    fn test_count_evaluation_modes() {
+        let events = vec![
+            event_with_origins(vec![origin_with_mode("1741830", "manual")]),
+            event_with_origins(vec![
+                origin_with_mode("other", "manual"),
+                origin_with_mode("1741830", "Automatic"),
+            ]),
+            event_with_origins(vec![origin_with_mode("1741830", "automatic")]),
+            event_with_origins(vec![origin_with_mode("1741830", "MANUAL")]),
+            SeismicEvent::test_event(),
+        ];
+
+        let counts = count_evaluation_modes(&events);
+
+        assert_eq!(counts.len(), 3);
+        assert_eq!(counts["manual"], 2);
+        assert_eq!(counts["automatic"], 2, "the best origin decides the mode");
+        assert_eq!(counts[UNKNOWN_EVALUATION_MODE], 1);
+    }
+
+    #[test]
+    fn test_preferred_magnitude_picks_moment_magnitude() {
+        let event = event_with_origins(vec![
+            origin_with_mags("other", &[("ML", 4.1)]),
+            origin_with_mags("1741830", &[("ml", 4.0), ("Mww", 4.4)]),
+        ]);
+
+        assert_eq!(event.preferred_magnitude(), (4.4, "Mww".to_string()));
+        assert_eq!(
+            event.preferred_magnitude_by(&["ml"]),
+            (4.0, "ml".to_string()),
+            "the best origin is searched first"
+        );
+    }
+
+    #[test]
+    fn test_preferred_magnitude_falls_back_to_event() {
+        let event = SeismicEvent::test_event();
+        assert_eq!(event.preferred_magnitude(), (2.0, "md".to_string()));
+
+        let event = event_with_origins(vec![origin_with_mags("1741830", &[("md", 2.3)])]);
+        assert_eq!(event.preferred_magnitude(), (2.0, "md".to_string()));
+    }
+
+    #[test]
+    fn test_consistent_geometry_is_not_flagged() {
+        assert!(!SeismicEvent::test_event().coordinate_mismatch);
+        assert!(!feature_with_geometry(-155.4875, 18.8232, -16.5).coordinate_mismatch);
+    }
+
+    #[test]
+    fn test_geometry_mismatch_is_flagged() {
+        let event = feature_with_geometry(-155.4875, 18.8232, -42.0);
+
+        assert!(event.coordinate_mismatch);
+        // The property stays authoritative
+        assert_eq!(event.depth, 16.1);
+
+        assert!(feature_with_geometry(-150.0, 18.8232, -16.1).coordinate_mismatch);
+    }
+
+    #[test]
+    fn test_local_time_follows_longitude() {
+        let mut event = SeismicEvent::test_event();
+        event.longitude = 150.2;
+
+        let localized = LocalizedEvent::new(event.clone(), None);
+        let json = serde_json::to_value(&localized).unwrap();
+
+        assert_eq!(
+            localized.local_time.naive_local(),
+            event.time.naive_utc() + chrono::TimeDelta::hours(10)
+        );
+        assert!(json["local_time"].as_str().unwrap().ends_with("+10:00"));
+        assert_eq!(json["unid"], event.id);
+    }
+
+    #[test]
+    fn test_local_time_uses_configured_offset() {
+        let event = SeismicEvent::test_event();
+        let offset = FixedOffset::east_opt(-3 * 3600).unwrap();
+
+        let localized = LocalizedEvent::new(event, Some(offset));
+
+        assert_eq!(localized.local_time.offset(), &offset);
+    }
 }
@@ -0,0 +1,305 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+pub(crate) const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Tolerance for pruning, so rounding never drops a point on the boundary
+const PRUNE_SLACK_KM: f64 = 1e-9;
+
+/// Index over epicenters answering nearest and radius queries
+///
+/// Results are `(position, distance_km)` pairs ordered nearest first, where
+/// `position` is the index of the point in the slice the index was built from.
+pub trait SpatialIndex: Send + Sync {
+    /// The `count` points closest to a location
+    fn nearest(&self, latitude: f64, longitude: f64, count: usize) -> Vec<(usize, f64)>;
+
+    /// Every point within `radius_km` of a location
+    fn within(&self, latitude: f64, longitude: f64, radius_km: f64) -> Vec<(usize, f64)>;
+}
+
+/// Great-circle distance between two points in kilometers
+pub(crate) fn haversine_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let d_lat = (lat2 - lat1).to_radians();
+    let d_lon = (lon2 - lon1).to_radians();
+    let a = (d_lat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().min(1.0).asin()
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    position: usize,
+    latitude: f64,
+    longitude: f64,
+    /// Point on the unit sphere
+    xyz: [f64; 3],
+}
+
+/// KD-tree over epicenters
+///
+/// Points are split on their unit-sphere coordinates rather than on latitude
+/// and longitude, so queries stay exact across the antimeridian and near the
+/// poles. The tree is stored implicitly: each slice is split at its median.
+#[derive(Debug, Clone)]
+pub struct KdTree {
+    nodes: Vec<Node>,
+}
+
+impl KdTree {
+    /// Build a tree from `(latitude, longitude)` points
+    pub fn build(points: &[(f64, f64)]) -> Self {
+        let mut nodes: Vec<Node> = points
+            .iter()
+            .enumerate()
+            .map(|(position, &(latitude, longitude))| Node {
+                position,
+                latitude,
+                longitude,
+                xyz: unit_vector(latitude, longitude),
+            })
+            .collect();
+        Self::split(&mut nodes, 0);
+        Self { nodes }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    fn split(nodes: &mut [Node], depth: usize) {
+        if nodes.len() <= 1 {
+            return;
+        }
+        let axis = depth % 3;
+        let mid = nodes.len() / 2;
+        nodes.select_nth_unstable_by(mid, |a, b| a.xyz[axis].total_cmp(&b.xyz[axis]));
+        let (left, right) = nodes.split_at_mut(mid);
+        Self::split(left, depth + 1);
+        Self::split(&mut right[1..], depth + 1);
+    }
+
+    fn search_nearest(
+        nodes: &[Node],
+        depth: usize,
+        query: &Query,
+        count: usize,
+        best: &mut BinaryHeap<Candidate>,
+    ) {
+        if nodes.is_empty() {
+            return;
+        }
+        let mid = nodes.len() / 2;
+        let node = &nodes[mid];
+
+        let distance = query.distance_to(node);
+        if best.len() < count {
+            best.push(Candidate(distance, node.position));
+        } else if best.peek().is_some_and(|worst| distance < worst.0) {
+            best.pop();
+            best.push(Candidate(distance, node.position));
+        }
+
+        let (near, far, gap) = query.sides(nodes, mid, depth);
+        Self::search_nearest(near, depth + 1, query, count, best);
+        let reachable = best.len() < count
+            || best
+                .peek()
+                .is_some_and(|worst| chord_to_km(gap) - PRUNE_SLACK_KM <= worst.0);
+        if reachable {
+            Self::search_nearest(far, depth + 1, query, count, best);
+        }
+    }
+
+    fn search_within(
+        nodes: &[Node],
+        depth: usize,
+        query: &Query,
+        radius_km: f64,
+        found: &mut Vec<(usize, f64)>,
+    ) {
+        if nodes.is_empty() {
+            return;
+        }
+        let mid = nodes.len() / 2;
+        let node = &nodes[mid];
+
+        let distance = query.distance_to(node);
+        if distance <= radius_km {
+            found.push((node.position, distance));
+        }
+
+        let (near, far, gap) = query.sides(nodes, mid, depth);
+        Self::search_within(near, depth + 1, query, radius_km, found);
+        if chord_to_km(gap) - PRUNE_SLACK_KM <= radius_km {
+            Self::search_within(far, depth + 1, query, radius_km, found);
+        }
+    }
+}
+
+impl SpatialIndex for KdTree {
+    fn nearest(&self, latitude: f64, longitude: f64, count: usize) -> Vec<(usize, f64)> {
+        if count == 0 {
+            return Vec::new();
+        }
+        let query = Query::new(latitude, longitude);
+        let mut best = BinaryHeap::with_capacity(count.min(self.nodes.len()) + 1);
+        Self::search_nearest(&self.nodes, 0, &query, count, &mut best);
+        best.into_sorted_vec()
+            .into_iter()
+            .map(|Candidate(distance, position)| (position, distance))
+            .collect()
+    }
+
+    fn within(&self, latitude: f64, longitude: f64, radius_km: f64) -> Vec<(usize, f64)> {
+        let query = Query::new(latitude, longitude);
+        let mut found = Vec::new();
+        Self::search_within(&self.nodes, 0, &query, radius_km, &mut found);
+        found.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        found
+    }
+}
+
+struct Query {
+    latitude: f64,
+    longitude: f64,
+    xyz: [f64; 3],
+}
+
+impl Query {
+    fn new(latitude: f64, longitude: f64) -> Self {
+        Self {
+            latitude,
+            longitude,
+            xyz: unit_vector(latitude, longitude),
+        }
+    }
+
+    fn distance_to(&self, node: &Node) -> f64 {
+        haversine_km(self.latitude, self.longitude, node.latitude, node.longitude)
+    }
+
+    /// Children of the node at `mid` ordered (query side, other side), with
+    /// the chord distance from the query to the splitting plane
+    fn sides<'a>(
+        &self,
+        nodes: &'a [Node],
+        mid: usize,
+        depth: usize,
+    ) -> (&'a [Node], &'a [Node], f64) {
+        let axis = depth % 3;
+        let diff = self.xyz[axis] - nodes[mid].xyz[axis];
+        let (left, right) = (&nodes[..mid], &nodes[mid + 1..]);
+        if diff < 0.0 {
+            (left, right, -diff)
+        } else {
+            (right, left, diff)
+        }
+    }
+}
+
+/// Candidate in the nearest search, ordered by distance
+struct Candidate(f64, usize);
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(self.1.cmp(&other.1))
+    }
+}
+
+fn unit_vector(latitude: f64, longitude: f64) -> [f64; 3] {
+    let (lat, lon) = (latitude.to_radians(), longitude.to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+/// Surface distance spanned by a straight-line chord on the unit sphere. A
+/// chord shorter than the one to a point bounds its great-circle distance.
+fn chord_to_km(chord: f64) -> f64 {
+    2.0 * EARTH_RADIUS_KM * (chord / 2.0).min(1.0).asin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic pseudo-random points spread over the globe
+    fn random_points(count: usize) -> Vec<(f64, f64)> {
+        let mut seed: u64 = 0x5eed_cafe;
+        let mut next = move || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 11) as f64 / (1u64 << 53) as f64
+        };
+        (0..count)
+            .map(|_| (next() * 180.0 - 90.0, next() * 360.0 - 180.0))
+            .collect()
+    }
+
+    fn brute_force(points: &[(f64, f64)], latitude: f64, longitude: f64) -> Vec<(usize, f64)> {
+        let mut distances: Vec<_> = points
+            .iter()
+            .enumerate()
+            .map(|(position, &(lat, lon))| (position, haversine_km(latitude, longitude, lat, lon)))
+            .collect();
+        distances.sort_by(|(a_pos, a), (b_pos, b)| a.total_cmp(b).then(a_pos.cmp(b_pos)));
+        distances
+    }
+
+    #[test]
+    fn test_kd_tree_matches_brute_force() {
+        let points = random_points(1000);
+        let tree = KdTree::build(&points);
+        assert_eq!(tree.len(), 1000);
+
+        let queries = [
+            (0.0, 0.0),
+            (51.5, -0.1),
+            (-17.8, 179.9),
+            (89.9, 45.0),
+            (-60.0, -120.0),
+        ];
+        for (latitude, longitude) in queries {
+            let expected = brute_force(&points, latitude, longitude);
+
+            assert_eq!(tree.nearest(latitude, longitude, 25), expected[..25]);
+
+            let within = tree.within(latitude, longitude, 1500.0);
+            let expected_within: Vec<_> = expected
+                .iter()
+                .copied()
+                .filter(|(_, distance)| *distance <= 1500.0)
+                .collect();
+            assert_eq!(within, expected_within);
+        }
+    }
+
+    #[test]
+    fn test_kd_tree_empty_and_oversized_queries() {
+        let empty = KdTree::build(&[]);
+        assert!(empty.is_empty());
+        assert!(empty.nearest(10.0, 10.0, 3).is_empty());
+
+        let tree = KdTree::build(&[(10.0, 10.0), (10.0, 10.5)]);
+        assert_eq!(tree.nearest(10.0, 10.0, 5).len(), 2);
+        assert!(tree.nearest(10.0, 10.0, 0).is_empty());
+    }
+}
@@ -1,10 +1,68 @@
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use polars::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::analytics::incremental::IncrementalAnalytics;
-use crate::error::{ErrorContextExt, Result};
-use crate::seismic::SeismicEvent;
+use crate::analytics::incremental::{AdvancedAnalytics, IncrementalAnalytics, ANALYTICS_NAMES};
+use crate::analytics::processors::{
+    mag_to_key, AnalyticsProcessor, GutenbergRichterAnalytics, RiskAssessmentAnalytics,
+};
+use crate::client::{DepthUnit, DEFAULT_MAX_RESPONSE_BYTES};
+use crate::error::validation::{validate_latitude, validate_longitude, validate_magnitude};
+use crate::error::{ErrorContextExt, QuakeTrackerError, Result};
+use crate::filter::EventFilter;
+use crate::report::{CatalogReport, REPORT_TOP_REGIONS};
+use crate::seismic::{MissingMagnitude, SeismicEvent};
+use crate::spatial::{haversine_km, KdTree, SpatialIndex, EARTH_RADIUS_KM};
+
+/// Time bucket used when aggregating event counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Hour,
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl Period {
+    /// Polars duration string for truncating timestamps to this period
+    fn every(&self) -> &'static str {
+        match self {
+            Period::Hour => "1h",
+            Period::Day => "1d",
+            Period::Week => "1w",
+            Period::Month => "1mo",
+            Period::Year => "1y",
+        }
+    }
+}
+
+/// Stretch without events, as `(gap start, gap end, gap hours)`
+pub type CoverageGap = (
+    chrono::DateTime<chrono::Utc>,
+    chrono::DateTime<chrono::Utc>,
+    f64,
+);
+
+/// Columns available for export, in their default order
+pub const EXPORT_COLUMNS: [&str; 13] = [
+    "unid",
+    "time",
+    "lat",
+    "lon",
+    "depth",
+    "mag",
+    "magtype",
+    "evtype",
+    "flynn_region",
+    "source_id",
+    "source_catalog",
+    "lastupdate",
+    "author",
+];
 
 /// Improved seismic data storage with incremental analytics
 pub struct SeismicData {
@@ -12,6 +70,63 @@ pub struct SeismicData {
     analytics: Arc<IncrementalAnalytics>,
     /// Configuration for data retention and processing
     config: DataConfig,
+    /// When this instance started collecting
+    session_start: chrono::DateTime<chrono::Utc>,
+    /// Events added live since `session_start`, excluding ones loaded from disk
+    session_event_count: usize,
+    /// Epicenter index built on first use, reset whenever events change
+    spatial_index: parking_lot::Mutex<Option<Arc<EpicenterIndex>>>,
+    /// Aftershocks removed by `CleanupStrategy::ThinAftershocks`, by the id
+    /// of their mainshock
+    thinned_aftershocks: HashMap<String, usize>,
+}
+
+/// Spatial index together with the rows its positions refer to
+struct EpicenterIndex {
+    index: Box<dyn SpatialIndex>,
+    rows: DataFrame,
+}
+
+/// How cleanup makes room once the catalog outgrows the configured limits
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum CleanupStrategy {
+    /// Drop events past the retention period, then the oldest events beyond
+    /// `max_events`
+    #[default]
+    DropOldest,
+    /// Drop events past the retention period, then keep every mainshock but
+    /// thin its aftershocks, events at most `space_km` away and `time_days`
+    /// later with a smaller magnitude, down to the largest one. Thinning runs
+    /// when the catalog exceeds `max_events`, which stays a hard limit: if
+    /// thinning is not enough, the oldest events are dropped as with
+    /// `DropOldest`. How many aftershocks each cluster lost is kept, see
+    /// `SeismicData::thinned_aftershocks`.
+    ThinAftershocks { space_km: f64, time_days: f64 },
+}
+
+/// Which version wins when a merged event has the id of one already stored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::enum_variant_names)]
+pub enum MergePolicy {
+    /// Keep the version with the later `lastupdate`, the stored one on ties
+    #[default]
+    PreferNewer,
+    /// Never replace a stored event
+    PreferExisting,
+    /// Keep the version with the larger magnitude, the stored one on ties
+    PreferHigherMagnitude,
+}
+
+impl MergePolicy {
+    /// Whether `incoming` should replace `existing`
+    pub fn prefers_incoming(self, existing: &SeismicEvent, incoming: &SeismicEvent) -> bool {
+        match self {
+            MergePolicy::PreferNewer => incoming.last_update > existing.last_update,
+            MergePolicy::PreferExisting => false,
+            MergePolicy::PreferHigherMagnitude => incoming.magnitude > existing.magnitude,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -22,6 +137,40 @@ pub struct DataConfig {
     pub auto_cleanup: bool,
     /// Days to keep events before cleanup (if auto_cleanup is enabled)
     pub retention_days: u32,
+    /// Extra hours past `retention_days` before an event is dropped, so an
+    /// event sitting on the boundary isn't removed as soon as it is stored.
+    /// Incoming events past the same cutoff are rejected, so a dropped event
+    /// isn't stored again when a fetch or backfill returns it.
+    pub retention_grace_hours: u32,
+    /// Decimal places for floats sent to the frontend (None = full precision)
+    pub precision_decimals: Option<u32>,
+    /// Drop incoming events below this magnitude (None = keep all)
+    pub min_ingest_magnitude: Option<f64>,
+    /// Answer nearest and radius queries from a KD-tree over epicenters
+    /// instead of scanning the catalog
+    pub use_spatial_index: bool,
+    /// What cleanup removes when limits are exceeded
+    pub cleanup_strategy: CleanupStrategy,
+    /// Round magnitudes of real-time events to one decimal before storing
+    /// them, matching the precision of the REST catalog
+    pub round_realtime_magnitudes: bool,
+    /// Analytics processors kept up to date, see `ANALYTICS_NAMES` (default
+    /// all). Disabled analytics return empty results.
+    pub enabled_analytics: HashSet<String>,
+    /// How `merge_events` resolves events whose id is already stored
+    pub merge_policy: MergePolicy,
+    /// Recompute the analytics processors in parallel rather than one after
+    /// another
+    pub parallel_recompute: bool,
+    /// What happens to incoming events reported without a magnitude
+    pub missing_magnitude: MissingMagnitude,
+    /// Abort REST downloads whose body grows beyond this many bytes
+    pub max_response_bytes: usize,
+    /// Unit the REST source reports depths in, converted to kilometers on
+    /// ingestion
+    pub depth_unit: DepthUnit,
+    /// Keep only the best origin of events fetched from the REST source
+    pub best_origin_only: bool,
 }
 
 impl Default for DataConfig {
@@ -30,6 +179,22 @@ impl Default for DataConfig {
             max_events: 100_000, // Reasonable default for memory management
             auto_cleanup: true,
             retention_days: 365, // Keep 1 year of data by default
+            retention_grace_hours: DEFAULT_RETENTION_GRACE_HOURS,
+            precision_decimals: None,
+            min_ingest_magnitude: None,
+            use_spatial_index: true,
+            cleanup_strategy: CleanupStrategy::DropOldest,
+            round_realtime_magnitudes: false,
+            enabled_analytics: ANALYTICS_NAMES
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            merge_policy: MergePolicy::default(),
+            parallel_recompute: true,
+            missing_magnitude: MissingMagnitude::default(),
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            depth_unit: DepthUnit::default(),
+            best_origin_only: false,
         }
     }
 }
@@ -40,17 +205,33 @@ impl SeismicData {
     }
 
     pub fn with_config(config: DataConfig) -> Self {
+        let analytics = IncrementalAnalytics::new();
+        analytics.set_enabled_analytics(&config.enabled_analytics);
+        analytics.set_parallel_recompute(config.parallel_recompute);
         Self {
-            analytics: Arc::new(IncrementalAnalytics::new()),
+            analytics: Arc::new(analytics),
             config,
+            session_start: chrono::Utc::now(),
+            session_event_count: 0,
+            spatial_index: parking_lot::Mutex::new(None),
+            thinned_aftershocks: HashMap::new(),
         }
     }
 
     /// Add or update a single seismic event
     pub fn add_or_update_event(&mut self, event: SeismicEvent) -> Result<()> {
+        let Some(event) = self.admit(vec![event]).pop() else {
+            return Ok(());
+        };
+        let is_new = !self.analytics.contains_event(&event.id);
+
         self.analytics
             .add_event(&event)
             .with_operation("add_event_to_analytics", "state")?;
+        self.invalidate_spatial_index();
+        if is_new {
+            self.session_event_count += 1;
+        }
 
         if self.config.auto_cleanup {
             self.maybe_cleanup()
@@ -62,13 +243,211 @@ impl SeismicData {
 
     /// Add multiple seismic events efficiently
     pub fn add_events(&mut self, events: Vec<SeismicEvent>) -> Result<()> {
+        let events = self.admit(events);
+        // Only events with an id not seen before count towards the session
+        let mut seen = HashSet::new();
+        let new = events
+            .iter()
+            .filter(|event| {
+                !self.analytics.contains_event(&event.id) && seen.insert(event.id.as_str())
+            })
+            .count();
+        self.store_events(&events)?;
+        self.session_event_count += new;
+        Ok(())
+    }
+
+    /// Add the events whose id is not stored yet, returning how many were
+    /// added
+    ///
+    /// Events sharing an id with a stored event, or with each other, are
+    /// resolved with the configured `MergePolicy`.
+    pub fn merge_events(&mut self, events: Vec<SeismicEvent>) -> Result<usize> {
+        let added = self.import_events(events)?;
+        self.session_event_count += added;
+        Ok(added)
+    }
+
+    /// Merge events that weren't received this session, such as imported or
+    /// demo events, like `merge_events` but without counting them towards the
+    /// session statistics
+    pub fn import_events(&mut self, events: Vec<SeismicEvent>) -> Result<usize> {
+        let policy = self.config.merge_policy;
+        let events = self.admit(events);
+
+        let mut positions = HashMap::new();
+        let mut unique: Vec<SeismicEvent> = Vec::with_capacity(events.len());
+        for event in events {
+            match positions.get(&event.id) {
+                Some(&position) => {
+                    if policy.prefers_incoming(&unique[position], &event) {
+                        unique[position] = event;
+                    }
+                }
+                None => {
+                    positions.insert(event.id.clone(), unique.len());
+                    unique.push(event);
+                }
+            }
+        }
+
+        let (conflicting, new): (Vec<_>, Vec<_>) = unique
+            .into_iter()
+            .partition(|event| self.analytics.contains_event(&event.id));
+        self.resolve_conflicts(policy, conflicting)?;
+
+        self.store_events(&new)?;
+        Ok(new.len())
+    }
+
+    /// Replace stored events by the conflicting versions `policy` prefers
+    fn resolve_conflicts(
+        &mut self,
+        policy: MergePolicy,
+        conflicting: Vec<SeismicEvent>,
+    ) -> Result<()> {
+        if conflicting.is_empty() || policy == MergePolicy::PreferExisting {
+            return Ok(());
+        }
+
+        let df = self
+            .analytics
+            .get_dataframe()
+            .collect()
+            .with_operation("collect_stored_events", "state")?;
+        let matching = |df: &DataFrame, ids: &HashSet<&str>| -> PolarsResult<BooleanChunked> {
+            Ok(df
+                .column("unid")?
+                .str()?
+                .into_iter()
+                .map(|id| id.is_some_and(|id| ids.contains(id)))
+                .collect())
+        };
+
+        let conflicting_ids = conflicting.iter().map(|event| event.id.as_str()).collect();
+        let stored: HashMap<String, SeismicEvent> = self
+            .dataframe_to_events(df.filter(&matching(&df, &conflicting_ids)?)?)?
+            .into_iter()
+            .map(|event| (event.id.clone(), event))
+            .collect();
+        let winners: Vec<SeismicEvent> = conflicting
+            .into_iter()
+            .filter(|event| {
+                stored
+                    .get(&event.id)
+                    .is_some_and(|existing| policy.prefers_incoming(existing, event))
+            })
+            .collect();
+        if winners.is_empty() {
+            return Ok(());
+        }
+
+        let winner_ids = winners.iter().map(|event| event.id.as_str()).collect();
+        let kept = df.filter(&!matching(&df, &winner_ids)?)?;
+        // Replaced events get new sequence numbers so incremental syncs see them
+        let replacements = self
+            .analytics
+            .events_to_dataframe(&winners)
+            .and_then(|rows| self.analytics.with_sequence(rows))
+            .with_operation("convert_merged_events", "state")?;
+        let merged = concat([kept.lazy(), replacements.lazy()], UnionArgs::default())
+            .with_operation("merge_conflicting_events", "state")?;
+
+        log::debug!("Replaced {} stored events while merging", winners.len());
+        self.replace_dataframe(merged)
+    }
+
+    /// Load events previously exported with `to_parquet` (all columns),
+    /// merged into the stored events like `merge_events`. Loaded events don't
+    /// count towards the session statistics.
+    pub fn load_parquet(&mut self, bytes: &[u8]) -> Result<usize> {
+        let df = ParquetReader::new(std::io::Cursor::new(bytes))
+            .finish()
+            .with_operation("read_parquet", "state")?;
+        let events = self
+            .dataframe_to_events(df)
+            .with_operation("convert_parquet_to_events", "state")?;
+        let read = events.len();
+        self.import_events(events)?;
+        Ok(read)
+    }
+
+    /// Replace every stored event with `events` in one step
+    ///
+    /// The new catalog and its analytics are built off to the side and swapped
+    /// in once complete, so readers see either the old or the new catalog and
+    /// a failure leaves the old one intact. Later duplicates of an id win, as
+    /// with repeated `add_or_update_event` calls. Replaced events don't count
+    /// towards the session statistics.
+    pub fn replace_all(&mut self, events: Vec<SeismicEvent>) -> Result<()> {
+        let mut events = self.admit(events);
+        let mut seen = std::collections::HashSet::new();
+        events.reverse();
+        events.retain(|event| seen.insert(event.id.clone()));
+        events.reverse();
+
+        let df = self
+            .analytics
+            .events_to_dataframe(&events)
+            .with_operation("convert_replacement_events", "state")?;
+        let previous = Arc::clone(&self.analytics);
+        self.replace_dataframe(df.lazy())?;
+
+        if self.config.auto_cleanup {
+            if let Err(e) = self.maybe_cleanup() {
+                self.analytics = previous;
+                self.invalidate_spatial_index();
+                return Err(e).with_operation("auto_cleanup", "state");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Swap in analytics rebuilt from `new_df`, keeping the current ones if
+    /// the rebuild fails
+    fn replace_dataframe(&mut self, new_df: LazyFrame) -> Result<()> {
+        let rebuilt = self
+            .analytics
+            .rebuilt_from(new_df)
+            .with_operation("rebuild_analytics", "state")?;
+        self.analytics = Arc::new(rebuilt);
+        self.invalidate_spatial_index();
+        Ok(())
+    }
+
+    /// The incoming events that get stored, with missing magnitudes handled
+    /// by the configured `MissingMagnitude` and events below the ingest
+    /// threshold or past the retention cutoff dropped
+    fn admit(&self, events: Vec<SeismicEvent>) -> Vec<SeismicEvent> {
+        let mut events = self.config.missing_magnitude.apply(events);
+        let cutoff_ns = self
+            .retention_cutoff_ns()
+            .filter(|_| self.config.auto_cleanup);
+        events.retain(|event| {
+            self.meets_ingest_threshold(event)
+                && cutoff_ns.is_none_or(|cutoff_ns| {
+                    event.time.timestamp_nanos_opt().unwrap_or(0) >= cutoff_ns
+                })
+        });
+        events
+    }
+
+    fn meets_ingest_threshold(&self, event: &SeismicEvent) -> bool {
+        self.config
+            .min_ingest_magnitude
+            .is_none_or(|min_magnitude| event.magnitude >= min_magnitude)
+    }
+
+    fn store_events(&mut self, events: &[SeismicEvent]) -> Result<()> {
         if events.is_empty() {
             return Ok(());
         }
 
         self.analytics
-            .add_events(&events)
+            .add_events(events)
             .with_operation("add_events_to_analytics", "state")?;
+        self.invalidate_spatial_index();
 
         if self.config.auto_cleanup {
             self.maybe_cleanup()
@@ -81,6 +460,63 @@ impl SeismicData {
     /// Clear all data
     pub fn clear(&mut self) {
         self.analytics.clear();
+        self.invalidate_spatial_index();
+        self.thinned_aftershocks.clear();
+    }
+
+    fn invalidate_spatial_index(&mut self) {
+        *self.spatial_index.get_mut() = None;
+    }
+
+    /// The epicenter index for the current events, building it if events
+    /// changed since it was last used. `None` when the index is disabled.
+    fn epicenter_index(&self) -> Result<Option<Arc<EpicenterIndex>>> {
+        if !self.config.use_spatial_index {
+            return Ok(None);
+        }
+
+        let mut cached = self.spatial_index.lock();
+        if let Some(index) = cached.as_ref() {
+            return Ok(Some(Arc::clone(index)));
+        }
+
+        let rows = self
+            .analytics
+            .get_dataframe()
+            .collect()
+            .with_operation("collect_epicenters", "state")?;
+        let points: Vec<(f64, f64)> = rows
+            .column("lat")?
+            .f64()?
+            .into_iter()
+            .zip(rows.column("lon")?.f64()?)
+            .map(|(lat, lon)| (lat.unwrap_or(f64::NAN), lon.unwrap_or(f64::NAN)))
+            .collect();
+
+        let index = Arc::new(EpicenterIndex {
+            index: Box::new(KdTree::build(&points)),
+            rows,
+        });
+        *cached = Some(Arc::clone(&index));
+        Ok(Some(index))
+    }
+
+    /// Events at the given rows of an epicenter index, keeping their order
+    fn indexed_events(
+        &self,
+        index: &EpicenterIndex,
+        positions: &[(usize, f64)],
+    ) -> Result<Vec<SeismicEvent>> {
+        let positions: Vec<IdxSize> = positions
+            .iter()
+            .map(|&(position, _)| position as IdxSize)
+            .collect();
+        let rows = index
+            .rows
+            .take(&IdxCa::from_vec("position".into(), positions))
+            .with_operation("take_indexed_events", "state")?;
+        self.dataframe_to_events(rows)
+            .with_operation("convert_indexed_events", "state")
     }
 
     /// Get all events (expensive operation, use sparingly)
@@ -95,17 +531,44 @@ impl SeismicData {
     }
 
     /// Get events in chronological order (expensive operation, use sparingly)
+    ///
+    /// Events sharing a timestamp are ordered by `unid`.
     pub fn get_chronological_events(&self) -> Result<Vec<SeismicEvent>> {
         let df = self
             .analytics
             .get_dataframe()
-            .sort(["time"], Default::default())
+            .sort(
+                ["time", "unid"],
+                SortMultipleOptions::default().with_maintain_order(true),
+            )
             .collect()
             .with_operation("collect_sorted_dataframe", "state")?;
         self.dataframe_to_events(df)
             .with_operation("convert_sorted_dataframe_to_events", "state")
     }
 
+    /// Events stored after ingestion sequence number `seq`, in the order they
+    /// were stored, paired with their sequence numbers
+    ///
+    /// Passing the largest sequence number seen so far fetches only what was
+    /// added or replaced since, for incremental syncs. Sequence numbers are
+    /// never reused, also not after cleanup or `clear`.
+    pub fn get_events_after_seq(&self, seq: u64) -> Result<Vec<(u64, SeismicEvent)>> {
+        let df = self
+            .analytics
+            .get_dataframe()
+            .filter(col("seq").gt(lit(seq)))
+            .sort(["seq"], Default::default())
+            .collect()
+            .with_operation("collect_events_after_seq", "state")?;
+
+        let seqs: Vec<u64> = df.column("seq")?.u64()?.into_no_null_iter().collect();
+        let events = self
+            .dataframe_to_events(df)
+            .with_operation("convert_events_after_seq", "state")?;
+        Ok(seqs.into_iter().zip(events).collect())
+    }
+
     /// Run a function on all events (legacy compatibility)
     pub fn run_on_events<F, T>(&self, func: F) -> Result<Vec<T>>
     where
@@ -127,6 +590,12 @@ impl SeismicData {
         &self.analytics
     }
 
+    /// Get a shared handle to the analytics that outlives the state borrow, so
+    /// callers can release the state lock before running read-only queries
+    pub fn analytics_handle(&self) -> Arc<IncrementalAnalytics> {
+        Arc::clone(&self.analytics)
+    }
+
     /// Get current data statistics
     pub fn get_stats(&self) -> DataStats {
         let cache = self.analytics.cache.read();
@@ -134,12 +603,312 @@ impl SeismicData {
             total_events: cache.total_events,
             last_updated: cache.last_updated,
             memory_usage_estimate: self.estimate_memory_usage(),
+            session_start: self.session_start,
+            session_event_count: self.session_event_count,
+        }
+    }
+
+    /// Summarize the catalog and its headline analytics in one report
+    pub fn generate_report(&self) -> Result<CatalogReport> {
+        self.flush()?;
+        let summary = self
+            .analytics
+            .get_dataframe()
+            .select([
+                col("time").min().alias("first_event"),
+                col("time").max().alias("last_event"),
+                col("mag").min().alias("min_magnitude"),
+                col("mag").max().alias("max_magnitude"),
+            ])
+            .collect()
+            .with_operation("collect_report_summary", "state")?;
+
+        let first_event = summary.column("first_event")?.datetime()?.get(0);
+        let last_event = summary.column("last_event")?.datetime()?.get(0);
+        let min_magnitude = summary.column("min_magnitude")?.f64()?.get(0);
+        let max_magnitude = summary.column("max_magnitude")?.f64()?.get(0);
+
+        let mut top_regions = self.analytics.get_region_hotspots();
+        top_regions.truncate(REPORT_TOP_REGIONS);
+
+        Ok(CatalogReport {
+            total_events: self.get_stats().total_events,
+            first_event: first_event.map(chrono::DateTime::from_timestamp_nanos),
+            last_event: last_event.map(chrono::DateTime::from_timestamp_nanos),
+            min_magnitude,
+            max_magnitude,
+            b_value: self.analytics.get_b_value(),
+            b_value_uncertainty: self.analytics.get_b_value_uncertainty(),
+            completeness_magnitude: self.analytics.get_completeness_magnitude(),
+            top_regions,
+            total_energy_joules: self.analytics.get_total_energy(),
+        })
+    }
+
+    /// Advanced analytics over only the events at or after `since`, computed
+    /// in a throwaway analytics instance so the stored catalog is untouched
+    pub fn get_analytics_since(
+        &self,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<AdvancedAnalytics> {
+        let filter = EventFilter {
+            start_time: Some(since),
+            ..Default::default()
+        };
+        Self::isolated_analytics(filter.apply(self.analytics.get_dataframe()))
+            .with_operation("get_analytics_since", "state")
+    }
+
+    /// Advanced analytics over only the events inside `polygon`, see
+    /// `get_events_in_polygon`, computed in a throwaway analytics instance so
+    /// the stored catalog is untouched
+    pub fn get_analytics_in_polygon(&self, polygon: Vec<(f64, f64)>) -> Result<AdvancedAnalytics> {
+        let events = self.get_events_in_polygon(polygon)?;
+        let rows = self
+            .analytics
+            .events_to_dataframe(&events)
+            .with_operation("convert_polygon_events", "state")?;
+        Self::isolated_analytics(rows.lazy()).with_operation("get_analytics_in_polygon", "state")
+    }
+
+    /// Advanced analytics over `rows` alone
+    fn isolated_analytics(rows: LazyFrame) -> Result<AdvancedAnalytics> {
+        let analytics = IncrementalAnalytics::new();
+        analytics
+            .replace_dataframe_and_rebuild(rows)
+            .with_operation("rebuild_isolated_analytics", "state")?;
+        Ok(analytics.get_advanced_analytics()?)
+    }
+
+    /// Export events as CSV with the given columns in the given order, or all
+    /// columns when `columns` is empty
+    pub fn to_csv(&self, columns: &[&str]) -> Result<Vec<u8>> {
+        self.to_csv_with_grid(columns, None)
+    }
+
+    /// Like `to_csv`, but with `lat` and `lon` snapped to the nearest
+    /// multiple of `grid_degrees` when given, for sharing data without exact
+    /// locations. Stored coordinates keep full precision.
+    pub fn to_csv_with_grid(&self, columns: &[&str], grid_degrees: Option<f64>) -> Result<Vec<u8>> {
+        let mut df = self.export_dataframe(columns)?;
+        if let Some(grid_degrees) = grid_degrees {
+            snap_coordinates(&mut df, grid_degrees)?;
+        }
+        let mut buffer = Vec::new();
+        CsvWriter::new(&mut buffer)
+            .include_header(true)
+            .finish(&mut df)
+            .with_operation("write_csv", "state")?;
+        Ok(buffer)
+    }
+
+    /// Export events as Parquet with the given columns in the given order, or
+    /// all columns when `columns` is empty
+    pub fn to_parquet(&self, columns: &[&str]) -> Result<Vec<u8>> {
+        let mut df = self.export_dataframe(columns)?;
+        let mut buffer = Vec::new();
+        ParquetWriter::new(&mut buffer)
+            .finish(&mut df)
+            .with_operation("write_parquet", "state")?;
+        Ok(buffer)
+    }
+
+    /// Time, magnitude and depth of every event in time order as Arrow IPC
+    /// bytes, for plotting frontends that read Arrow directly instead of
+    /// parsing JSON
+    pub fn get_mag_depth_arrow(&self) -> Result<Vec<u8>> {
+        let mut df = self
+            .analytics
+            .get_dataframe()
+            .select([col("time"), col("mag"), col("depth")])
+            .sort(
+                ["time"],
+                SortMultipleOptions::default().with_maintain_order(true),
+            )
+            .collect()
+            .with_operation("collect_mag_depth_arrow", "state")?;
+        let mut buffer = Vec::new();
+        IpcWriter::new(&mut buffer)
+            .finish(&mut df)
+            .with_operation("write_arrow_ipc", "state")?;
+        Ok(buffer)
+    }
+
+    fn export_dataframe(&self, columns: &[&str]) -> Result<DataFrame> {
+        for (index, column) in columns.iter().enumerate() {
+            if !EXPORT_COLUMNS.contains(column) {
+                return Err(QuakeTrackerError::validation(
+                    "columns",
+                    format!(
+                        "Unknown column '{}', expected one of: {}",
+                        column,
+                        EXPORT_COLUMNS.join(", ")
+                    ),
+                ));
+            }
+            if columns[..index].contains(column) {
+                return Err(QuakeTrackerError::validation(
+                    "columns",
+                    format!("Column '{}' is requested more than once", column),
+                ));
+            }
         }
+
+        let selected = if columns.is_empty() {
+            &EXPORT_COLUMNS[..]
+        } else {
+            columns
+        };
+
+        self.flush()?;
+        self.analytics
+            .get_dataframe()
+            .select(selected.iter().map(|name| col(*name)).collect::<Vec<_>>())
+            .collect()
+            .with_operation("collect_export_dataframe", "state")
     }
 
-    /// Update configuration
-    pub fn update_config(&mut self, config: DataConfig) {
+    /// Update configuration. The analytics are only cleared and recomputed
+    /// if the enabled set changed.
+    pub fn update_config(&mut self, config: DataConfig) -> Result<()> {
+        validate_analytics_names(&config.enabled_analytics)?;
+        if config.enabled_analytics != self.config.enabled_analytics {
+            self.analytics
+                .set_enabled_analytics(&config.enabled_analytics);
+        }
+        self.analytics
+            .set_parallel_recompute(config.parallel_recompute);
         self.config = config;
+        self.invalidate_spatial_index();
+        Ok(())
+    }
+
+    /// Only keep the named analytics up to date, see `ANALYTICS_NAMES`.
+    /// Disabled analytics are cleared and return empty results until they
+    /// are enabled again.
+    pub fn set_enabled_analytics(&mut self, names: HashSet<String>) -> Result<()> {
+        validate_analytics_names(&names)?;
+        self.analytics.set_enabled_analytics(&names);
+        self.config.enabled_analytics = names;
+        Ok(())
+    }
+
+    /// Decimal places used when serializing analytics, if rounding is enabled
+    pub fn precision_decimals(&self) -> Option<u32> {
+        self.config.precision_decimals
+    }
+
+    /// Set the decimal places used when serializing analytics (None disables
+    /// rounding)
+    pub fn set_precision_decimals(&mut self, decimals: Option<u32>) -> Result<()> {
+        if let Some(decimals) = decimals {
+            if decimals > MAX_PRECISION_DECIMALS {
+                return Err(QuakeTrackerError::validation(
+                    "precision_decimals",
+                    format!(
+                        "Precision must be at most {} decimals, got {}",
+                        MAX_PRECISION_DECIMALS, decimals
+                    ),
+                ));
+            }
+        }
+        self.config.precision_decimals = decimals;
+        Ok(())
+    }
+
+    /// Whether real-time magnitudes are rounded to one decimal on ingestion
+    pub fn rounds_realtime_magnitudes(&self) -> bool {
+        self.config.round_realtime_magnitudes
+    }
+
+    /// Enable or disable rounding real-time magnitudes to one decimal. Only
+    /// affects events received afterwards.
+    pub fn set_round_realtime_magnitudes(&mut self, enabled: bool) {
+        self.config.round_realtime_magnitudes = enabled;
+    }
+
+    pub fn merge_policy(&self) -> MergePolicy {
+        self.config.merge_policy
+    }
+
+    /// Set how `merge_events` resolves events whose id is already stored
+    pub fn set_merge_policy(&mut self, policy: MergePolicy) {
+        self.config.merge_policy = policy;
+    }
+
+    /// What happens to incoming events reported without a magnitude
+    pub fn missing_magnitude(&self) -> MissingMagnitude {
+        self.config.missing_magnitude
+    }
+
+    /// Set what happens to incoming events reported without a magnitude. Fill
+    /// magnitudes must be valid magnitudes.
+    pub fn set_missing_magnitude(&mut self, policy: MissingMagnitude) -> Result<()> {
+        if let MissingMagnitude::Fill(magnitude) = policy {
+            validate_magnitude(magnitude)?;
+        }
+        self.config.missing_magnitude = policy;
+        Ok(())
+    }
+
+    /// Set the longest forecast horizon in days used for risk probabilities
+    pub fn set_max_risk_horizon_days(&mut self, days: f64) -> Result<()> {
+        if !days.is_finite() || days <= 0.0 {
+            return Err(QuakeTrackerError::validation(
+                "max_horizon_days",
+                format!(
+                    "Forecast horizon must be a positive number of days, got {}",
+                    days
+                ),
+            ));
+        }
+        self.analytics.set_max_risk_horizon_days(days);
+        Ok(())
+    }
+
+    /// Largest REST response body accepted, in bytes
+    pub fn max_response_bytes(&self) -> usize {
+        self.config.max_response_bytes
+    }
+
+    /// Set the largest REST response body accepted, in bytes
+    pub fn set_max_response_bytes(&mut self, max_response_bytes: usize) -> Result<()> {
+        if max_response_bytes == 0 {
+            return Err(QuakeTrackerError::validation(
+                "max_response_bytes",
+                "Maximum response size must be greater than 0",
+            ));
+        }
+        self.config.max_response_bytes = max_response_bytes;
+        Ok(())
+    }
+
+    /// Unit the REST source reports depths in
+    pub fn depth_unit(&self) -> DepthUnit {
+        self.config.depth_unit
+    }
+
+    /// Set the unit the REST source reports depths in
+    pub fn set_depth_unit(&mut self, depth_unit: DepthUnit) {
+        self.config.depth_unit = depth_unit;
+    }
+
+    /// Whether only the best origin of fetched events is kept
+    pub fn best_origin_only(&self) -> bool {
+        self.config.best_origin_only
+    }
+
+    /// Set whether only the best origin of fetched events is kept
+    pub fn set_best_origin_only(&mut self, best_origin_only: bool) {
+        self.config.best_origin_only = best_origin_only;
+    }
+
+    /// Make pending changes visible to readers so a snapshot taken afterwards
+    /// is consistent with the analytics
+    pub fn flush(&self) -> Result<()> {
+        self.analytics
+            .flush()
+            .with_operation("flush_analytics", "state")
     }
 
     /// Force a full recomputation of all analytics
@@ -149,6 +918,14 @@ impl SeismicData {
             .with_operation("recompute_all_analytics", "state")
     }
 
+    /// Throw away all cached analytics and rebuild them from the stored
+    /// events, which unlike `clear` are kept
+    pub fn reset_analytics_cache(&self) -> Result<()> {
+        self.analytics
+            .reset_cache()
+            .with_operation("reset_analytics_cache", "state")
+    }
+
     /// Get events within a specific time range
     pub fn get_events_in_range(
         &self,
@@ -198,6 +975,48 @@ impl SeismicData {
             .with_operation("convert_bbox_filtered_dataframe_to_events", "state")
     }
 
+    /// Get events whose epicenter lies inside `polygon`, given as (latitude,
+    /// longitude) vertices in order
+    ///
+    /// The ring is closed automatically and epicenters on an edge or vertex
+    /// count as inside. Edges are straight lines in latitude/longitude, so
+    /// polygons must not cross the antimeridian.
+    pub fn get_events_in_polygon(&self, polygon: Vec<(f64, f64)>) -> Result<Vec<SeismicEvent>> {
+        if polygon.len() < 3 {
+            return Err(QuakeTrackerError::validation(
+                "polygon",
+                format!("Polygon needs at least 3 vertices, got {}", polygon.len()),
+            ));
+        }
+        for &(latitude, longitude) in &polygon {
+            validate_latitude(latitude)?;
+            validate_longitude(longitude)?;
+        }
+
+        let ring: Vec<(f64, f64)> = polygon.iter().map(|&(lat, lon)| (lon, lat)).collect();
+        let polygon = geo_types::Polygon::new(ring.into(), Vec::new());
+        let (min_lat, max_lat, min_lon, max_lon) = polygon.exterior().coords().fold(
+            (
+                f64::INFINITY,
+                f64::NEG_INFINITY,
+                f64::INFINITY,
+                f64::NEG_INFINITY,
+            ),
+            |(min_lat, max_lat, min_lon, max_lon), coord| {
+                (
+                    min_lat.min(coord.y),
+                    max_lat.max(coord.y),
+                    min_lon.min(coord.x),
+                    max_lon.max(coord.x),
+                )
+            },
+        );
+
+        let mut events = self.get_events_in_bbox(min_lat, max_lat, min_lon, max_lon)?;
+        events.retain(|event| polygon_contains(&polygon, event.geometry.0));
+        Ok(events)
+    }
+
     /// Get events with magnitude above threshold
     pub fn get_events_above_magnitude(&self, min_magnitude: f64) -> Result<Vec<SeismicEvent>> {
         let df = self
@@ -211,390 +1030,3741 @@ impl SeismicData {
             .with_operation("convert_magnitude_filtered_dataframe_to_events", "state")
     }
 
-    fn maybe_cleanup(&mut self) -> Result<()> {
-        let stats = self.get_stats();
-        let mut needs_cleanup = false;
-        let mut cleanup_reason = String::new();
-
-        if self.config.max_events > 0 && stats.total_events > self.config.max_events {
-            needs_cleanup = true;
-            cleanup_reason = format!(
-                "Event count ({}) exceeds maximum ({})",
-                stats.total_events, self.config.max_events
-            );
+    /// Get events with magnitude within `[min_magnitude, max_magnitude]`,
+    /// inclusive on both ends
+    pub fn get_events_in_magnitude_range(
+        &self,
+        min_magnitude: f64,
+        max_magnitude: f64,
+    ) -> Result<Vec<SeismicEvent>> {
+        validate_magnitude(min_magnitude)?;
+        validate_magnitude(max_magnitude)?;
+        if min_magnitude > max_magnitude {
+            return Err(QuakeTrackerError::validation(
+                "magnitude_range",
+                "Minimum magnitude must not exceed maximum magnitude",
+            ));
         }
 
-        if self.config.retention_days > 0 {
-            let cutoff_time =
-                chrono::Utc::now() - chrono::TimeDelta::days(self.config.retention_days as i64);
-            let cutoff_ns = cutoff_time.timestamp_nanos_opt().unwrap_or(0);
+        let df = self
+            .analytics
+            .get_dataframe()
+            .filter(
+                col("mag")
+                    .gt_eq(lit(min_magnitude))
+                    .and(col("mag").lt_eq(lit(max_magnitude))),
+            )
+            .collect()
+            .with_operation("collect_magnitude_range_dataframe", "state")?;
 
-            let old_events_count = self
-                .analytics
-                .get_dataframe()
-                .filter(col("time").lt(lit(cutoff_ns)))
-                .select([len().alias("count")])
-                .collect()?
-                .column("count")?
-                .u32()?
-                .get(0)
-                .unwrap_or(0);
+        self.dataframe_to_events(df)
+            .with_operation("convert_magnitude_range_dataframe_to_events", "state")
+    }
 
-            if old_events_count > 0 {
-                needs_cleanup = true;
-                if !cleanup_reason.is_empty() {
-                    cleanup_reason.push_str(" and ");
-                }
-                cleanup_reason.push_str(&format!(
-                    "{} events older than {} days",
-                    old_events_count, self.config.retention_days
-                ));
-            }
+    /// Pass events matching `filter` to `consume` in chunks of at most
+    /// `chunk_size`, returning the number of events visited
+    pub fn for_each_event_chunk<F>(
+        &self,
+        filter: &EventFilter,
+        chunk_size: usize,
+        mut consume: F,
+    ) -> Result<usize>
+    where
+        F: FnMut(Vec<SeismicEvent>) -> Result<()>,
+    {
+        if chunk_size == 0 {
+            return Err(QuakeTrackerError::validation(
+                "chunk_size",
+                "Chunk size must be greater than 0",
+            ));
         }
+        filter.validate()?;
 
-        if needs_cleanup {
-            log::info!("Performing cleanup: {}", cleanup_reason);
-            self.perform_cleanup()?;
+        let df = filter
+            .apply(self.analytics.get_dataframe())
+            .collect()
+            .with_operation("collect_filtered_dataframe", "state")?;
+
+        let total = df.height();
+        for offset in (0..total).step_by(chunk_size) {
+            let chunk = df.slice(offset as i64, chunk_size);
+            consume(self.dataframe_to_events(chunk)?)?;
         }
 
+        Ok(total)
+    }
+
+    /// Events matching `filter` in chronological order as parallel arrays,
+    /// which avoids repeating field names per event when sent to the frontend
+    pub fn get_events_columnar(&self, filter: EventFilter) -> Result<ColumnarEvents> {
+        filter.validate()?;
+
+        let df = filter
+            .apply(self.analytics.get_dataframe())
+            .sort(["time", "unid"], Default::default())
+            .collect()
+            .with_operation("collect_columnar_events", "state")?;
+
+        ColumnarEvents::from_dataframe(&df).with_operation("convert_columnar_events", "state")
+    }
+
+    /// Count events per period, keyed by the start of each period in
+    /// chronological order. Weeks start on Monday.
+    pub fn get_counts_by_period(
+        &self,
+        period: Period,
+    ) -> Result<Vec<(chrono::DateTime<chrono::Utc>, u32)>> {
+        Self::counts_by_period(self.analytics.get_dataframe(), period)
+    }
+
+    /// Stretches longer than `min_gap_hours` without any event, as
+    /// `(gap start, gap end, gap hours)` between consecutive events in
+    /// chronological order, to reveal holes left e.g. while the app was down
+    pub fn detect_coverage_gaps(&self, min_gap_hours: f64) -> Result<Vec<CoverageGap>> {
+        if !min_gap_hours.is_finite() || min_gap_hours <= 0.0 {
+            return Err(QuakeTrackerError::validation(
+                "min_gap_hours",
+                format!(
+                    "Gap threshold must be a positive number, got {}",
+                    min_gap_hours
+                ),
+            ));
+        }
+
+        let df = self
+            .analytics
+            .get_dataframe()
+            .select([col("time")])
+            .sort(["time"], Default::default())
+            .collect()
+            .with_operation("collect_sorted_times", "state")?;
+        let times: Vec<i64> = df
+            .column("time")?
+            .datetime()?
+            .into_iter()
+            .flatten()
+            .collect();
+
+        const NANOS_PER_HOUR: f64 = 3_600.0 * 1e9;
+        Ok(times
+            .windows(2)
+            .filter_map(|pair| {
+                let hours = (pair[1] - pair[0]) as f64 / NANOS_PER_HOUR;
+                (hours > min_gap_hours).then(|| {
+                    (
+                        chrono::DateTime::from_timestamp_nanos(pair[0]),
+                        chrono::DateTime::from_timestamp_nanos(pair[1]),
+                        hours,
+                    )
+                })
+            })
+            .collect())
+    }
+
+    /// Count the events of `region`, matched case-insensitively, per period,
+    /// like `get_counts_by_period`
+    pub fn get_region_timeseries(
+        &self,
+        region: &str,
+        period: Period,
+    ) -> Result<Vec<(chrono::DateTime<chrono::Utc>, u32)>> {
+        if region.trim().is_empty() {
+            return Err(QuakeTrackerError::validation(
+                "region",
+                "Region must not be empty",
+            ));
+        }
+
+        let df = self.analytics.get_dataframe();
+        let names = df
+            .clone()
+            .select([col("flynn_region").unique()])
+            .collect()
+            .with_operation("collect_region_names", "state")?;
+        let region = region.trim().to_lowercase();
+        let Some(matches_region) = names
+            .column("flynn_region")?
+            .str()?
+            .into_iter()
+            .flatten()
+            .filter(|name| name.to_lowercase() == region)
+            .map(|name| col("flynn_region").eq(lit(name.to_string())))
+            .reduce(|matches, other| matches.or(other))
+        else {
+            return Ok(Vec::new());
+        };
+
+        Self::counts_by_period(df.filter(matches_region), period)
+    }
+
+    /// Count `rows` per period, keyed by the start of each period
+    fn counts_by_period(
+        rows: LazyFrame,
+        period: Period,
+    ) -> Result<Vec<(chrono::DateTime<chrono::Utc>, u32)>> {
+        let df = rows
+            .select([col("time")
+                .dt()
+                .truncate(lit(period.every()))
+                .alias("period")])
+            .group_by([col("period")])
+            .agg([len().alias("count")])
+            .sort(["period"], Default::default())
+            .collect()
+            .with_operation("collect_counts_by_period", "state")?;
+
+        let periods = df.column("period")?.datetime()?;
+        let counts = df.column("count")?.u32()?;
+
+        Ok(periods
+            .into_iter()
+            .zip(counts)
+            .filter_map(|(start, count)| {
+                Some((chrono::DateTime::from_timestamp_nanos(start?), count?))
+            })
+            .collect())
+    }
+
+    /// Mean years between events at or above `magnitude`, the inverse of the
+    /// Gutenberg-Richter annual rate. Infinite when the rate is zero, e.g.
+    /// while no events are stored.
+    pub fn recurrence_interval_years(&self, magnitude: f64) -> Result<f64> {
+        validate_magnitude(magnitude)?;
+        self.flush()?;
+
+        if self.get_stats().total_events == 0 {
+            return Ok(f64::INFINITY);
+        }
+        let annual_rate = self.analytics.get_annual_rate_above(Some(magnitude));
+        if annual_rate > 0.0 {
+            Ok(1.0 / annual_rate)
+        } else {
+            Ok(f64::INFINITY)
+        }
+    }
+
+    /// Time elapsed since the most recent event at or above `magnitude`, or
+    /// `None` when there is no such event. Events dated in the future count
+    /// as having happened now.
+    pub fn time_since_last_above(&self, magnitude: f64) -> Result<Option<chrono::TimeDelta>> {
+        validate_magnitude(magnitude)?;
+
+        let latest = self
+            .analytics
+            .get_dataframe()
+            .filter(col("mag").gt_eq(lit(magnitude)))
+            .select([col("time").max()])
+            .collect()
+            .with_operation("collect_last_event_above", "state")?;
+        let latest = latest.column("time")?.datetime()?.get(0);
+
+        Ok(latest.map(|time_ns| {
+            let elapsed = chrono::Utc::now() - chrono::DateTime::from_timestamp_nanos(time_ns);
+            elapsed.max(chrono::TimeDelta::zero())
+        }))
+    }
+
+    /// Pearson correlation between daily event counts and daily released
+    /// energy over the last `window_days` days of the catalog, ending on the
+    /// day of the latest event. Days without events count as zero.
+    ///
+    /// Close to 1 when activity spikes come from many events, low when a few
+    /// large events dominate the energy. Returns 0 when either series is
+    /// constant or no events are stored.
+    pub fn get_count_energy_correlation(&self, window_days: u32) -> Result<f64> {
+        if window_days < 2 {
+            return Err(QuakeTrackerError::validation(
+                "window_days",
+                format!("Window must span at least 2 days, got {}", window_days),
+            ));
+        }
+
+        let df = self
+            .analytics
+            .get_dataframe()
+            .select([col("time"), col("mag")])
+            .collect()
+            .with_operation("collect_count_energy_series", "state")?;
+
+        const NANOS_PER_DAY: i64 = 86_400 * 1_000_000_000;
+        let times = df.column("time")?.datetime()?;
+        let mags = df.column("mag")?.f64()?;
+        let events: Vec<(i64, f64)> = times
+            .iter()
+            .zip(mags)
+            .filter_map(|(time, mag)| Some((time?.div_euclid(NANOS_PER_DAY), mag?)))
+            .collect();
+
+        let Some(last_day) = events.iter().map(|&(day, _)| day).max() else {
+            return Ok(0.0);
+        };
+        let first_day = last_day - i64::from(window_days) + 1;
+
+        let mut counts = vec![0.0; window_days as usize];
+        let mut energies = vec![0.0; window_days as usize];
+        for (day, mag) in events {
+            if day >= first_day {
+                let index = (day - first_day) as usize;
+                counts[index] += 1.0;
+                energies[index] += RiskAssessmentAnalytics::magnitude_to_energy(mag);
+            }
+        }
+
+        Ok(pearson_correlation(&counts, &energies))
+    }
+
+    /// Largest magnitude within the trailing `window_days` days, for every day
+    /// from the first to the last event. Days whose window holds no events
+    /// report 0.
+    pub fn get_rolling_max_magnitude(
+        &self,
+        window_days: u32,
+    ) -> Result<Vec<(chrono::NaiveDate, f64)>> {
+        if window_days == 0 {
+            return Err(QuakeTrackerError::validation(
+                "window_days",
+                "Window must span at least 1 day",
+            ));
+        }
+
+        let df = self
+            .analytics
+            .get_dataframe()
+            .select([
+                col("time").dt().truncate(lit("1d")).alias("day"),
+                col("mag"),
+            ])
+            .group_by([col("day")])
+            .agg([col("mag").max()])
+            .sort(["day"], Default::default())
+            .collect()
+            .with_operation("collect_daily_max_magnitudes", "state")?;
+
+        let days = df.column("day")?.datetime()?;
+        let mags = df.column("mag")?.f64()?;
+        let daily_max: std::collections::BTreeMap<chrono::NaiveDate, f64> = days
+            .into_iter()
+            .zip(mags)
+            .filter_map(|(day, mag)| {
+                Some((
+                    chrono::DateTime::from_timestamp_nanos(day?).date_naive(),
+                    mag?,
+                ))
+            })
+            .collect();
+
+        let (Some(&first), Some(&last)) = (daily_max.keys().next(), daily_max.keys().next_back())
+        else {
+            return Ok(Vec::new());
+        };
+
+        let window = chrono::Days::new(u64::from(window_days) - 1);
+        Ok(first
+            .iter_days()
+            .take_while(|day| *day <= last)
+            .map(|day| {
+                let window_start = day
+                    .checked_sub_days(window)
+                    .unwrap_or(chrono::NaiveDate::MIN);
+                let max = daily_max
+                    .range(window_start..=day)
+                    .map(|(_, &mag)| mag)
+                    .reduce(f64::max)
+                    .unwrap_or(0.0);
+                (day, max)
+            })
+            .collect())
+    }
+
+    /// Ids of events whose magnitude exceeds their region's mean by more than
+    /// `z_threshold` standard deviations
+    pub fn flag_magnitude_outliers(&self, z_threshold: f64) -> Result<Vec<String>> {
+        if !z_threshold.is_finite() || z_threshold <= 0.0 {
+            return Err(QuakeTrackerError::validation(
+                "z_threshold",
+                format!("Threshold must be a positive number, got {}", z_threshold),
+            ));
+        }
+
+        let region = [col("flynn_region")];
+        let region_std = col("mag").std(1).over(region.clone());
+        let z_score = (col("mag") - col("mag").mean().over(region)) / region_std.clone();
+
+        let df = self
+            .analytics
+            .get_dataframe()
+            .filter(region_std.gt(lit(0.0)).and(z_score.gt(lit(z_threshold))))
+            .select([col("unid")])
+            .collect()
+            .with_operation("collect_magnitude_outliers", "state")?;
+
+        Ok(df
+            .column("unid")?
+            .str()?
+            .into_iter()
+            .flatten()
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Get events matching `filter` with their epicentral distance (km) and
+    /// azimuth (degrees clockwise from north) from a reference point, nearest
+    /// first
+    pub fn get_events_with_distance(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        filter: &EventFilter,
+    ) -> Result<Vec<(SeismicEvent, f64, f64)>> {
+        validate_latitude(latitude)?;
+        validate_longitude(longitude)?;
+        filter.validate()?;
+
+        let df = filter
+            .apply(self.analytics.get_dataframe())
+            .collect()
+            .with_operation("collect_filtered_dataframe", "state")?;
+
+        let mut events: Vec<_> = self
+            .dataframe_to_events(df)
+            .with_operation("convert_filtered_dataframe_to_events", "state")?
+            .into_iter()
+            .map(|event| {
+                let distance = haversine_km(latitude, longitude, event.latitude, event.longitude);
+                let azimuth = initial_bearing(latitude, longitude, event.latitude, event.longitude);
+                (event, distance, azimuth)
+            })
+            .collect();
+        events.sort_by(|(_, a, _), (_, b, _)| a.total_cmp(b));
+
+        Ok(events)
+    }
+
+    /// Bounding box `(min_lat, max_lat, min_lon, max_lon)` of all stored
+    /// events, or `None` when there are none
+    ///
+    /// Longitudes are compared numerically, so events on both sides of the
+    /// antimeridian yield a box spanning most of the globe rather than the
+    /// narrow box across 180°.
+    pub fn get_bounding_box(&self) -> Result<Option<(f64, f64, f64, f64)>> {
+        let bounds = self
+            .analytics
+            .get_dataframe()
+            .select([
+                col("lat").min().alias("min_lat"),
+                col("lat").max().alias("max_lat"),
+                col("lon").min().alias("min_lon"),
+                col("lon").max().alias("max_lon"),
+            ])
+            .collect()
+            .with_operation("collect_bounding_box", "state")?;
+
+        let min_lat = bounds.column("min_lat")?.f64()?.get(0);
+        let max_lat = bounds.column("max_lat")?.f64()?.get(0);
+        let min_lon = bounds.column("min_lon")?.f64()?.get(0);
+        let max_lon = bounds.column("max_lon")?.f64()?.get(0);
+
+        Ok(match (min_lat, max_lat, min_lon, max_lon) {
+            (Some(min_lat), Some(max_lat), Some(min_lon), Some(max_lon)) => {
+                Some((min_lat, max_lat, min_lon, max_lon))
+            }
+            _ => None,
+        })
+    }
+
+    /// Mean and standard deviation of depth per magnitude type, as
+    /// `(magtype, mean_depth, std_depth)` sorted by magnitude type. The
+    /// deviation is 0 for types with a single event.
+    pub fn get_depth_by_magtype(&self) -> Result<Vec<(String, f64, f64)>> {
+        let df = self
+            .analytics
+            .get_dataframe()
+            .group_by([col("magtype")])
+            .agg([
+                col("depth").mean().alias("mean_depth"),
+                col("depth").std(1).alias("std_depth"),
+            ])
+            .sort(["magtype"], Default::default())
+            .collect()
+            .with_operation("collect_depth_by_magtype", "state")?;
+
+        let magtypes = df.column("magtype")?.str()?;
+        let means = df.column("mean_depth")?.f64()?;
+        let stds = df.column("std_depth")?.f64()?;
+
+        Ok(magtypes
+            .into_iter()
+            .zip(means)
+            .zip(stds)
+            .filter_map(|((magtype, mean), std)| {
+                Some((magtype?.to_string(), mean?, std.unwrap_or(0.0)))
+            })
+            .collect())
+    }
+
+    /// Gutenberg-Richter b-value per depth bin, as `(bin_center_km, b_value)`
+    /// from shallow to deep
+    ///
+    /// Events are grouped into bins `bin_km` thick starting at the surface and
+    /// each bin is fitted on its own. Bins with fewer than `min_events` events
+    /// are skipped.
+    pub fn get_b_value_by_depth(&self, bin_km: f64, min_events: usize) -> Result<Vec<(f64, f64)>> {
+        if !bin_km.is_finite() || bin_km <= 0.0 {
+            return Err(QuakeTrackerError::validation(
+                "bin_km",
+                format!("Depth bin size must be a positive number, got {}", bin_km),
+            ));
+        }
+
+        let df = self
+            .analytics
+            .get_dataframe()
+            .select([col("depth"), col("mag")])
+            .collect()
+            .with_operation("collect_depth_bins", "state")?;
+
+        let mut bins: std::collections::BTreeMap<i64, Vec<f64>> = Default::default();
+        for (depth, mag) in df
+            .column("depth")?
+            .f64()?
+            .into_iter()
+            .zip(df.column("mag")?.f64()?)
+        {
+            if let (Some(depth), Some(mag)) = (depth, mag) {
+                let bin = (depth / bin_km).floor() as i64;
+                bins.entry(bin).or_default().push(mag);
+            }
+        }
+
+        bins.into_iter()
+            .filter(|(_, magnitudes)| magnitudes.len() >= min_events)
+            .map(|(bin, magnitudes)| {
+                let estimator = GutenbergRichterAnalytics::new();
+                estimator
+                    .recompute(&df!["mag" => magnitudes]?.lazy())
+                    .with_operation("fit_depth_bin_b_value", "state")?;
+                Ok(((bin as f64 + 0.5) * bin_km, estimator.get_b_value()))
+            })
+            .collect()
+    }
+
+    /// Centers of the most common 0.1 magnitude bin and the most common
+    /// `MODAL_DEPTH_BIN_KM` depth bin, as `(magnitude, depth_km)`
+    ///
+    /// Ties go to the lower bin. Returns `(0.0, 0.0)` while no events are
+    /// stored.
+    pub fn get_modal_values(&self) -> Result<(f64, f64)> {
+        let df = self
+            .analytics
+            .get_dataframe()
+            .select([col("mag"), col("depth")])
+            .collect()
+            .with_operation("collect_modal_values", "state")?;
+
+        let mut magnitude_bins: std::collections::BTreeMap<u32, usize> = Default::default();
+        for mag in df.column("mag")?.f64()?.into_iter().flatten() {
+            *magnitude_bins.entry(mag_to_key(mag, 1)).or_default() += 1;
+        }
+        let mut depth_bins: std::collections::BTreeMap<i64, usize> = Default::default();
+        for depth in df.column("depth")?.f64()?.into_iter().flatten() {
+            *depth_bins
+                .entry((depth / MODAL_DEPTH_BIN_KM).floor() as i64)
+                .or_default() += 1;
+        }
+
+        let magnitude = modal_key(magnitude_bins).map_or(0.0, |key| (key as f64 + 0.5) / 10.0);
+        let depth =
+            modal_key(depth_bins).map_or(0.0, |bin| (bin as f64 + 0.5) * MODAL_DEPTH_BIN_KM);
+        Ok((magnitude, depth))
+    }
+
+    /// Most recent event time and event count per region, as
+    /// `(region, last_event, count)` with the regions that went quiet longest
+    /// first, to help spot seismic gaps
+    pub fn get_region_last_activity(
+        &self,
+    ) -> Result<Vec<(String, chrono::DateTime<chrono::Utc>, u32)>> {
+        let df = self
+            .analytics
+            .get_dataframe()
+            .group_by([col("flynn_region")])
+            .agg([col("time").max().alias("last_event"), len().alias("count")])
+            .sort(["last_event", "flynn_region"], Default::default())
+            .collect()
+            .with_operation("collect_region_last_activity", "state")?;
+
+        let regions = df.column("flynn_region")?.str()?;
+        let last_events = df.column("last_event")?.datetime()?;
+        let counts = df.column("count")?.u32()?;
+
+        Ok(regions
+            .into_iter()
+            .zip(last_events.iter())
+            .zip(counts)
+            .filter_map(|((region, last_event), count)| {
+                Some((
+                    region?.to_string(),
+                    chrono::DateTime::from_timestamp_nanos(last_event?),
+                    count?,
+                ))
+            })
+            .collect())
+    }
+
+    /// Events whose best origin used at least `min_stations` stations and
+    /// left an azimuthal gap of at most `max_gap` degrees, for research-grade
+    /// subsets. Events stored without origin detail are excluded.
+    pub fn get_quality_events(&self, min_stations: i32, max_gap: f64) -> Result<Vec<SeismicEvent>> {
+        self.get_quality_events_with(min_stations, max_gap, false)
+    }
+
+    /// Like [`Self::get_quality_events`], but keeps events without origin
+    /// detail when `include_missing` is set
+    pub fn get_quality_events_with(
+        &self,
+        min_stations: i32,
+        max_gap: f64,
+        include_missing: bool,
+    ) -> Result<Vec<SeismicEvent>> {
+        if !(0.0..=360.0).contains(&max_gap) {
+            return Err(QuakeTrackerError::validation(
+                "max_gap",
+                format!(
+                    "Azimuthal gap {} is outside valid range [0.0, 360.0]",
+                    max_gap
+                ),
+            ));
+        }
+
+        // Comparisons against a missing station count or gap are null, which
+        // decides whether events lacking origin detail are kept
+        let well_constrained = col("nsta")
+            .gt_eq(lit(min_stations))
+            .and(col("gap").lt_eq(lit(max_gap)))
+            .fill_null(lit(include_missing));
+
+        let df = self
+            .analytics
+            .get_dataframe()
+            .filter(well_constrained)
+            .sort(["time", "unid"], Default::default())
+            .collect()
+            .with_operation("collect_quality_events", "state")?;
+
+        self.dataframe_to_events(df)
+            .with_operation("convert_quality_events", "state")
+    }
+
+    /// Magnitude-weighted centroid `(latitude, longitude)` of the events
+    /// matching `filter`, or `None` when nothing matches. With
+    /// `energy_weighted` events are weighted by radiated energy instead, so
+    /// the largest shocks dominate.
+    ///
+    /// Positions are averaged as unit vectors, which keeps clusters spanning
+    /// the antimeridian centred correctly.
+    pub fn get_activity_centroid(
+        &self,
+        filter: &EventFilter,
+        energy_weighted: bool,
+    ) -> Result<Option<(f64, f64)>> {
+        filter.validate()?;
+
+        let df = filter
+            .apply(self.analytics.get_dataframe())
+            .collect()
+            .with_operation("collect_filtered_dataframe", "state")?;
+        let events = self
+            .dataframe_to_events(df)
+            .with_operation("convert_filtered_dataframe_to_events", "state")?;
+
+        if events.is_empty() {
+            return Ok(None);
+        }
+
+        let weight = |magnitude: f64| {
+            if energy_weighted {
+                // log10(E) = 11.8 + 1.5*M, the constant factor cancels out
+                10_f64.powf(1.5 * magnitude)
+            } else {
+                magnitude.max(0.0)
+            }
+        };
+        // Fall back to equal weights when every magnitude is non-positive
+        let uniform = events.iter().all(|event| weight(event.magnitude) <= 0.0);
+
+        let (mut x, mut y, mut z) = (0.0, 0.0, 0.0);
+        for event in &events {
+            let w = if uniform {
+                1.0
+            } else {
+                weight(event.magnitude)
+            };
+            let (lat, lon) = (event.latitude.to_radians(), event.longitude.to_radians());
+            x += w * lat.cos() * lon.cos();
+            y += w * lat.cos() * lon.sin();
+            z += w * lat.sin();
+        }
+
+        // Antipodal points cancel out and leave no meaningful direction
+        if x.hypot(y).hypot(z) < 1e-12 {
+            return Ok(None);
+        }
+
+        let latitude = z.atan2(x.hypot(y)).to_degrees();
+        let longitude = y.atan2(x).to_degrees();
+        Ok(Some((latitude, longitude)))
+    }
+
+    /// Share of the radiated energy of the events matching `filter` that
+    /// comes from the single largest event
+    ///
+    /// Values close to 1 indicate a mainshock-dominated sequence, values
+    /// close to `1 / n` a swarm of similar events. Returns 0 when nothing
+    /// matches.
+    pub fn largest_event_energy_fraction(&self, filter: EventFilter) -> Result<f64> {
+        filter.validate()?;
+
+        let df = filter
+            .apply(self.analytics.get_dataframe())
+            .select([col("mag")])
+            .collect()
+            .with_operation("collect_filtered_magnitudes", "state")?;
+
+        let (total, largest) = df
+            .column("mag")?
+            .f64()?
+            .into_iter()
+            .flatten()
+            .map(RiskAssessmentAnalytics::magnitude_to_energy)
+            .fold((0.0, 0.0_f64), |(total, largest), energy| {
+                (total + energy, largest.max(energy))
+            });
+
+        if total <= 0.0 {
+            return Ok(0.0);
+        }
+        Ok(largest / total)
+    }
+
+    /// Get events within `radius_km` of a point, ordered by distance
+    pub fn get_events_in_circle(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        radius_km: f64,
+    ) -> Result<Vec<SeismicEvent>> {
+        validate_latitude(latitude)?;
+        validate_longitude(longitude)?;
+        if radius_km < 0.0 {
+            return Err(QuakeTrackerError::validation(
+                "radius",
+                "Radius cannot be negative",
+            ));
+        }
+
+        if let Some(index) = self.epicenter_index()? {
+            let found = index.index.within(latitude, longitude, radius_km);
+            return self.indexed_events(&index, &found);
+        }
+
+        let mut candidates = self.events_within(latitude, longitude, radius_km)?;
+        candidates.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        Ok(candidates.into_iter().map(|(event, _)| event).collect())
+    }
+
+    /// Get the `count` events closest to a point, nearest first
+    pub fn get_nearest_events(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        count: usize,
+    ) -> Result<Vec<SeismicEvent>> {
+        validate_latitude(latitude)?;
+        validate_longitude(longitude)?;
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+
+        if let Some(index) = self.epicenter_index()? {
+            let found = index.index.nearest(latitude, longitude, count);
+            return self.indexed_events(&index, &found);
+        }
+
+        // Widen the search until it holds enough events; anything closer than
+        // the radius is guaranteed to have been considered
+        let mut radius_km = NEAREST_INITIAL_RADIUS_KM;
+        loop {
+            let mut candidates = self.events_within(latitude, longitude, radius_km)?;
+
+            if candidates.len() >= count || radius_km >= MAX_SURFACE_DISTANCE_KM {
+                candidates.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+                return Ok(candidates
+                    .into_iter()
+                    .take(count)
+                    .map(|(event, _)| event)
+                    .collect());
+            }
+
+            radius_km = (radius_km * 2.0).min(MAX_SURFACE_DISTANCE_KM);
+        }
+    }
+
+    /// Distance in kilometers from each stored event to its nearest other
+    /// event, in storage order. Empty when fewer than two events are stored.
+    pub fn get_nearest_neighbor_distances(&self) -> Result<Vec<f64>> {
+        let df = self
+            .analytics
+            .get_dataframe()
+            .select([col("lat"), col("lon")])
+            .collect()
+            .with_operation("collect_epicenters", "state")?;
+
+        let points: Vec<(f64, f64)> = df
+            .column("lat")?
+            .f64()?
+            .into_iter()
+            .zip(df.column("lon")?.f64()?)
+            .filter_map(|(lat, lon)| Some((lat?, lon?)))
+            .collect();
+
+        match self.epicenter_index()? {
+            Some(index) if points.len() >= 2 => Ok(points
+                .iter()
+                .map(|&(latitude, longitude)| {
+                    // The closest hit is the event itself, or one sharing its
+                    // epicenter at distance 0
+                    index
+                        .index
+                        .nearest(latitude, longitude, 2)
+                        .last()
+                        .map_or(f64::INFINITY, |&(_, distance)| distance)
+                })
+                .collect()),
+            _ => Ok(nearest_neighbor_distances(&points)),
+        }
+    }
+
+    /// Mean and minimum nearest-neighbor distance, or `None` when fewer than
+    /// two events are stored
+    pub fn get_nearest_neighbor_summary(&self) -> Result<Option<NearestNeighborSummary>> {
+        let distances = self.get_nearest_neighbor_distances()?;
+        if distances.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(NearestNeighborSummary {
+            mean_km: distances.iter().sum::<f64>() / distances.len() as f64,
+            min_km: distances.iter().copied().fold(f64::INFINITY, f64::min),
+            event_count: distances.len(),
+        }))
+    }
+
+    /// Events within `radius_km` of a point paired with their distance
+    ///
+    /// A conservative lat/lon bounding box is applied in Polars first so the
+    /// exact haversine distance is only computed for nearby candidates.
+    fn events_within(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        radius_km: f64,
+    ) -> Result<Vec<(SeismicEvent, f64)>> {
+        let df = self
+            .analytics
+            .get_dataframe()
+            .filter(bounding_box_filter(latitude, longitude, radius_km))
+            .collect()
+            .with_operation("collect_radius_candidates", "state")?;
+
+        let events = self
+            .dataframe_to_events(df)
+            .with_operation("convert_radius_candidates_to_events", "state")?;
+
+        Ok(events
+            .into_iter()
+            .map(|event| {
+                let distance = haversine_km(latitude, longitude, event.latitude, event.longitude);
+                (event, distance)
+            })
+            .filter(|(_, distance)| *distance <= radius_km)
+            .collect())
+    }
+
+    /// Number of aftershocks `CleanupStrategy::ThinAftershocks` removed, by
+    /// the id of their stored mainshock
+    pub fn thinned_aftershocks(&self) -> std::collections::BTreeMap<String, usize> {
+        self.thinned_aftershocks
+            .iter()
+            .filter(|(mainshock, _)| self.analytics.contains_event(mainshock))
+            .map(|(mainshock, &count)| (mainshock.clone(), count))
+            .collect()
+    }
+
+    fn maybe_cleanup(&mut self) -> Result<()> {
+        let stats = self.get_stats();
+        let mut needs_cleanup = false;
+        let mut cleanup_reason = String::new();
+
+        if self.config.max_events > 0 && stats.total_events > self.config.max_events {
+            needs_cleanup = true;
+            cleanup_reason = format!(
+                "Event count ({}) exceeds maximum ({})",
+                stats.total_events, self.config.max_events
+            );
+        }
+
+        if let Some(cutoff_ns) = self.retention_cutoff_ns() {
+            let old_events_count = self
+                .analytics
+                .get_dataframe()
+                .filter(col("time").lt(lit(cutoff_ns)))
+                .select([len().alias("count")])
+                .collect()?
+                .column("count")?
+                .u32()?
+                .get(0)
+                .unwrap_or(0);
+
+            if old_events_count > 0 {
+                needs_cleanup = true;
+                if !cleanup_reason.is_empty() {
+                    cleanup_reason.push_str(" and ");
+                }
+                cleanup_reason.push_str(&format!(
+                    "{} events older than {} days",
+                    old_events_count, self.config.retention_days
+                ));
+            }
+        }
+
+        if needs_cleanup {
+            log::info!("Performing cleanup: {}", cleanup_reason);
+            self.perform_cleanup()?;
+        }
+
+        Ok(())
+    }
+
+    /// Time before which events fall out of the retention period, including
+    /// the grace period, or `None` when retention is disabled
+    fn retention_cutoff_ns(&self) -> Option<i64> {
+        if self.config.retention_days == 0 {
+            return None;
+        }
+        let cutoff_time = chrono::Utc::now()
+            - chrono::TimeDelta::days(self.config.retention_days as i64)
+            - chrono::TimeDelta::hours(self.config.retention_grace_hours as i64);
+        Some(cutoff_time.timestamp_nanos_opt().unwrap_or(0))
+    }
+
+    /// Perform the actual cleanup by filtering the dataframe and rebuilding
+    /// analytics
+    fn perform_cleanup(&mut self) -> Result<()> {
+        let old_stats = self.get_stats();
+        let mut filtered_df = self.analytics.get_dataframe();
+        let mut thinned_counts = HashMap::new();
+        if let Some(cutoff_ns) = self.retention_cutoff_ns() {
+            filtered_df = filtered_df.filter(col("time").gt_eq(lit(cutoff_ns)));
+        }
+
+        if let CleanupStrategy::ThinAftershocks {
+            space_km,
+            time_days,
+        } = self.config.cleanup_strategy
+        {
+            let retained = filtered_df
+                .collect()
+                .with_operation("collect_events_to_thin", "state")?;
+            filtered_df =
+                if self.config.max_events > 0 && retained.height() > self.config.max_events {
+                    let (thinned, counts) = Self::thin_aftershocks(retained, space_km, time_days)?;
+                    thinned_counts = counts;
+                    thinned.lazy()
+                } else {
+                    retained.lazy()
+                };
+        }
+
+        if self.config.max_events > 0 {
+            filtered_df = filtered_df
+                .sort(
+                    ["time", "unid"],
+                    SortMultipleOptions::default()
+                        .with_order_descending_multi([true, false])
+                        .with_maintain_order(true),
+                )
+                .limit(self.config.max_events as u32);
+        }
+
+        self.replace_dataframe(filtered_df)?;
+        // Counted only once the thinned catalog is stored, so a failed
+        // rebuild leaves the counts matching the unchanged events
+        for (mainshock, count) in thinned_counts {
+            *self.thinned_aftershocks.entry(mainshock).or_default() += count;
+        }
+        let analytics = &self.analytics;
+        self.thinned_aftershocks
+            .retain(|mainshock, _| analytics.contains_event(mainshock));
+
+        let new_stats = self.get_stats();
+        log::info!(
+            "Cleanup completed: {} events remaining (was {})",
+            new_stats.total_events,
+            old_stats.total_events
+        );
+
         Ok(())
     }
 
-    /// Perform the actual cleanup by filtering the dataframe and rebuilding
-    /// analytics
-    fn perform_cleanup(&mut self) -> Result<()> {
-        let old_stats = self.get_stats();
-        let mut filtered_df = self.analytics.get_dataframe();
+    /// `df` without the aftershocks removed by
+    /// `CleanupStrategy::ThinAftershocks`
+    ///
+    /// Events are visited from the largest magnitude down. Each event not yet
+    /// part of a cluster becomes a mainshock and claims the smaller events
+    /// within `space_km` and up to `time_days` after it. Only the largest
+    /// aftershock of each cluster is kept, and the number removed is returned
+    /// per mainshock id for `thinned_aftershocks`.
+    fn thin_aftershocks(
+        df: DataFrame,
+        space_km: f64,
+        time_days: f64,
+    ) -> Result<(DataFrame, HashMap<String, usize>)> {
+        const NANOS_PER_DAY: f64 = 86_400.0 * 1_000_000_000.0;
+
+        let ids = df.column("unid")?.str()?.clone();
+        let points: Vec<(f64, f64)> = df
+            .column("lat")?
+            .f64()?
+            .into_iter()
+            .zip(df.column("lon")?.f64()?)
+            .map(|(lat, lon)| (lat.unwrap_or(f64::NAN), lon.unwrap_or(f64::NAN)))
+            .collect();
+        let times: Vec<i64> = df
+            .column("time")?
+            .datetime()?
+            .iter()
+            .map(|time| time.unwrap_or(i64::MIN))
+            .collect();
+        let mags: Vec<f64> = df
+            .column("mag")?
+            .f64()?
+            .into_iter()
+            .map(|mag| mag.unwrap_or(f64::NAN))
+            .collect();
+
+        let mut order: Vec<usize> = (0..df.height()).collect();
+        order.sort_by(|&a, &b| mags[b].total_cmp(&mags[a]).then(times[a].cmp(&times[b])));
+
+        let index = KdTree::build(&points);
+        let window_ns = (time_days * NANOS_PER_DAY) as i64;
+        let mut clustered = vec![false; df.height()];
+        let mut keep = vec![true; df.height()];
+        let mut thinned = 0;
+        let mut counts = HashMap::new();
+
+        for mainshock in order {
+            if clustered[mainshock] {
+                continue;
+            }
+            clustered[mainshock] = true;
+
+            let (latitude, longitude) = points[mainshock];
+            let aftershocks: Vec<usize> = index
+                .within(latitude, longitude, space_km)
+                .into_iter()
+                .map(|(position, _)| position)
+                .filter(|&position| {
+                    let delay = times[position].saturating_sub(times[mainshock]);
+                    !clustered[position]
+                        && (0..=window_ns).contains(&delay)
+                        && mags[position] <= mags[mainshock]
+                })
+                .collect();
+
+            let representative = aftershocks
+                .iter()
+                .copied()
+                .max_by(|&a, &b| mags[a].total_cmp(&mags[b]).then(times[b].cmp(&times[a])));
+            let mut cluster_thinned = 0;
+            for aftershock in aftershocks {
+                clustered[aftershock] = true;
+                if Some(aftershock) != representative {
+                    keep[aftershock] = false;
+                    cluster_thinned += 1;
+                }
+            }
+            if cluster_thinned > 0 {
+                if let Some(mainshock) = ids.get(mainshock) {
+                    *counts.entry(mainshock.to_string()).or_default() += cluster_thinned;
+                }
+            }
+            thinned += cluster_thinned;
+        }
+
+        log::info!("Thinned {} aftershocks", thinned);
+        let df = df
+            .filter(&BooleanChunked::from_slice("keep".into(), &keep))
+            .with_operation("filter_thinned_events", "state")?;
+        Ok((df, counts))
+    }
+
+    fn estimate_memory_usage(&self) -> usize {
+        // Rough estimate: each event is approximately 500 bytes
+        let cache = self.analytics.cache.read();
+        cache.total_events * 500
+    }
+
+    fn dataframe_to_events(&self, df: DataFrame) -> Result<Vec<SeismicEvent>> {
+        Ok(IncrementalAnalytics::dataframe_to_events(&df)?)
+    }
+}
+
+impl Default for SeismicData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Half of the Earth's circumference, the largest possible surface distance
+const MAX_SURFACE_DISTANCE_KM: f64 = std::f64::consts::PI * EARTH_RADIUS_KM;
+const NEAREST_INITIAL_RADIUS_KM: f64 = 100.0;
+/// f64 carries roughly 15 significant decimal digits
+const MAX_PRECISION_DECIMALS: u32 = 15;
+/// Coarsest grid accepted when snapping exported coordinates
+const MAX_EXPORT_GRID_DEGREES: f64 = 10.0;
+/// Depth bin thickness used by `get_modal_values`
+const MODAL_DEPTH_BIN_KM: f64 = 10.0;
+/// Hours kept past `DataConfig::retention_days` by default
+pub const DEFAULT_RETENTION_GRACE_HOURS: u32 = 1;
+
+/// Snap the `lat` and `lon` columns of `df`, where present, to multiples of
+/// `grid_degrees`
+fn snap_coordinates(df: &mut DataFrame, grid_degrees: f64) -> Result<()> {
+    if !grid_degrees.is_finite() || grid_degrees <= 0.0 || grid_degrees > MAX_EXPORT_GRID_DEGREES {
+        return Err(QuakeTrackerError::validation(
+            "grid_degrees",
+            format!(
+                "Grid size must be greater than 0 and at most {} degrees, got {}",
+                MAX_EXPORT_GRID_DEGREES, grid_degrees
+            ),
+        ));
+    }
+
+    for name in ["lat", "lon"] {
+        let Ok(column) = df.column(name) else {
+            continue;
+        };
+        let snapped: Float64Chunked = column.f64()?.apply_values(|value| {
+            let snapped = (value / grid_degrees).round() * grid_degrees;
+            // Drop the floating point noise of the multiplication, e.g. 0.30000000000000004
+            (snapped * 1e9).round() / 1e9
+        });
+        df.with_column(snapped.with_name(name.into()))?;
+    }
+    Ok(())
+}
+
+/// Key with the highest count, the lowest such key on ties
+fn modal_key<K: Ord>(counts: std::collections::BTreeMap<K, usize>) -> Option<K> {
+    counts
+        .into_iter()
+        .fold(None, |best: Option<(K, usize)>, (key, count)| match best {
+            Some((_, best_count)) if best_count >= count => best,
+            _ => Some((key, count)),
+        })
+        .map(|(key, _)| key)
+}
+
+/// Distance from each `(lat, lon)` point to its closest other point
+///
+/// Points are swept in latitude order; the north-south separation never
+/// exceeds the great-circle distance, so the scan in each direction stops as
+/// soon as it alone is larger than the best distance found.
+fn nearest_neighbor_distances(points: &[(f64, f64)]) -> Vec<f64> {
+    if points.len() < 2 {
+        return Vec::new();
+    }
+
+    let km_per_degree = EARTH_RADIUS_KM.to_radians();
+    let mut order: Vec<usize> = (0..points.len()).collect();
+    order.sort_by(|&a, &b| points[a].0.total_cmp(&points[b].0));
+
+    let mut distances = vec![f64::INFINITY; points.len()];
+    for (position, &index) in order.iter().enumerate() {
+        let (lat, lon) = points[index];
+        let mut best = f64::INFINITY;
+        let mut scan = |neighbors: &mut dyn Iterator<Item = &usize>| {
+            for &other in neighbors {
+                let (other_lat, other_lon) = points[other];
+                if (other_lat - lat).abs() * km_per_degree > best {
+                    break;
+                }
+                best = best.min(haversine_km(lat, lon, other_lat, other_lon));
+            }
+        };
+        scan(&mut order[position + 1..].iter());
+        scan(&mut order[..position].iter().rev());
+        distances[index] = best;
+    }
+
+    distances
+}
+
+/// Initial great-circle bearing from the first point to the second, in degrees
+/// clockwise from north within `[0, 360)`
+fn initial_bearing(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let d_lon = (lon2 - lon1).to_radians();
+    let y = d_lon.sin() * phi2.cos();
+    let x = phi1.cos() * phi2.sin() - phi1.sin() * phi2.cos() * d_lon.cos();
+    y.atan2(x).to_degrees().rem_euclid(360.0)
+}
+
+/// Ray casting point-in-polygon test against the exterior ring, counting
+/// points on an edge or vertex as inside
+fn polygon_contains(polygon: &geo_types::Polygon<f64>, point: geo_types::Coord<f64>) -> bool {
+    let mut inside = false;
+    for edge in polygon.exterior().lines() {
+        let (a, b) = (edge.start, edge.end);
+        let cross = (b.x - a.x) * (point.y - a.y) - (b.y - a.y) * (point.x - a.x);
+        let within_extent = point.x >= a.x.min(b.x)
+            && point.x <= a.x.max(b.x)
+            && point.y >= a.y.min(b.y)
+            && point.y <= a.y.max(b.y);
+        if cross.abs() < 1e-9 && within_extent {
+            return true;
+        }
+
+        // Half-open in y so a ray through a vertex counts it once
+        if (a.y > point.y) != (b.y > point.y) {
+            let crossing_x = a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x);
+            if point.x < crossing_x {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+/// Pearson correlation of two equally long series, or 0 when either is
+/// constant up to rounding
+fn pearson_correlation(xs: &[f64], ys: &[f64]) -> f64 {
+    let n = xs.len() as f64;
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().sum::<f64>() / n;
+
+    let (mut sxy, mut sxx, mut syy) = (0.0, 0.0, 0.0);
+    for (x, y) in xs.iter().zip(ys) {
+        let (dx, dy) = (x - mean_x, y - mean_y);
+        sxy += dx * dy;
+        sxx += dx * dx;
+        syy += dy * dy;
+    }
+
+    let scale = |values: &[f64]| values.iter().map(|v| v * v).sum::<f64>() * f64::EPSILON;
+    if sxx <= scale(xs) || syy <= scale(ys) {
+        return 0.0;
+    }
+    (sxy / (sxx * syy).sqrt()).clamp(-1.0, 1.0)
+}
+
+/// Filter keeping every event that could lie within `radius_km` of a point
+///
+/// The longitude span widens with latitude and falls back to the full range
+/// when the circle reaches a pole. Spans crossing the antimeridian wrap.
+fn bounding_box_filter(latitude: f64, longitude: f64, radius_km: f64) -> Expr {
+    let angular_radius = radius_km / EARTH_RADIUS_KM;
+    let lat_delta = angular_radius.to_degrees();
+    let min_lat = latitude - lat_delta;
+    let max_lat = latitude + lat_delta;
+    let lat_filter = col("lat")
+        .gt_eq(lit(min_lat))
+        .and(col("lat").lt_eq(lit(max_lat)));
+
+    if min_lat <= -90.0 || max_lat >= 90.0 {
+        return lat_filter;
+    }
+
+    let lon_ratio = angular_radius.sin() / latitude.to_radians().cos();
+    if lon_ratio >= 1.0 {
+        return lat_filter;
+    }
+
+    let lon_delta = lon_ratio.asin().to_degrees();
+    let min_lon = longitude - lon_delta;
+    let max_lon = longitude + lon_delta;
+
+    let lon_filter = if min_lon < -180.0 {
+        col("lon")
+            .gt_eq(lit(min_lon + 360.0))
+            .or(col("lon").lt_eq(lit(max_lon)))
+    } else if max_lon > 180.0 {
+        col("lon")
+            .gt_eq(lit(min_lon))
+            .or(col("lon").lt_eq(lit(max_lon - 360.0)))
+    } else {
+        col("lon")
+            .gt_eq(lit(min_lon))
+            .and(col("lon").lt_eq(lit(max_lon)))
+    };
+
+    lat_filter.and(lon_filter)
+}
+
+/// Reject analytics names that are not in `ANALYTICS_NAMES`
+fn validate_analytics_names(names: &HashSet<String>) -> Result<()> {
+    match names
+        .iter()
+        .find(|name| !ANALYTICS_NAMES.contains(&name.as_str()))
+    {
+        Some(unknown) => Err(QuakeTrackerError::validation(
+            "enabled_analytics",
+            format!(
+                "Unknown analytics '{}', expected one of {}",
+                unknown,
+                ANALYTICS_NAMES.join(", ")
+            ),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Summary of the distances between events and their nearest neighbors
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct NearestNeighborSummary {
+    pub mean_km: f64,
+    pub min_km: f64,
+    pub event_count: usize,
+}
+
+/// Events as parallel arrays, where index `i` of every array belongs to the
+/// same event
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ColumnarEvents {
+    pub ids: Vec<String>,
+    pub times: Vec<chrono::DateTime<chrono::Utc>>,
+    pub lats: Vec<f64>,
+    pub lons: Vec<f64>,
+    pub depths: Vec<f64>,
+    pub mags: Vec<f64>,
+    pub magtypes: Vec<String>,
+    pub evtypes: Vec<String>,
+    pub regions: Vec<String>,
+    pub source_ids: Vec<String>,
+    pub source_catalogs: Vec<String>,
+    pub last_updates: Vec<chrono::DateTime<chrono::Utc>>,
+    pub authors: Vec<String>,
+}
+
+impl ColumnarEvents {
+    /// The columns of `df` in row order, reading nulls the way
+    /// `IncrementalAnalytics::dataframe_to_events` does
+    fn from_dataframe(df: &DataFrame) -> PolarsResult<Self> {
+        let strings = |name: &str| -> PolarsResult<Vec<String>> {
+            Ok(df
+                .column(name)?
+                .str()?
+                .into_iter()
+                .map(|value| value.unwrap_or_default().to_string())
+                .collect())
+        };
+        let floats = |name: &str| -> PolarsResult<Vec<f64>> {
+            Ok(df
+                .column(name)?
+                .f64()?
+                .into_iter()
+                .map(|value| value.unwrap_or(0.0))
+                .collect())
+        };
+        let times = |name: &str| -> PolarsResult<Vec<chrono::DateTime<chrono::Utc>>> {
+            Ok(df
+                .column(name)?
+                .datetime()?
+                .iter()
+                .map(|ns| chrono::DateTime::from_timestamp_nanos(ns.unwrap_or(0)))
+                .collect())
+        };
+
+        Ok(Self {
+            ids: strings("unid")?,
+            times: times("time")?,
+            lats: floats("lat")?,
+            lons: floats("lon")?,
+            depths: floats("depth")?,
+            mags: floats("mag")?,
+            magtypes: strings("magtype")?,
+            evtypes: strings("evtype")?,
+            regions: strings("flynn_region")?,
+            source_ids: strings("source_id")?,
+            source_catalogs: strings("source_catalog")?,
+            last_updates: times("lastupdate")?,
+            authors: strings("author")?,
+        })
+    }
+}
+
+/// Statistics about the current data state
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DataStats {
+    pub total_events: usize,
+    pub last_updated: chrono::DateTime<chrono::Utc>,
+    pub memory_usage_estimate: usize,
+    pub session_start: chrono::DateTime<chrono::Utc>,
+    pub session_event_count: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seismic::SeismicEvent;
+
+    #[test]
+    fn test_seismic_data_creation() {
+        let data = SeismicData::new();
+        let stats = data.get_stats();
+        assert_eq!(stats.total_events, 0);
+    }
+
+    #[test]
+    fn test_data_stats_keys_are_snake_case() {
+        let json = serde_json::to_value(SeismicData::new().get_stats()).unwrap();
+
+        let mut keys: Vec<&String> = json.as_object().unwrap().keys().collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec![
+                "last_updated",
+                "memory_usage_estimate",
+                "session_event_count",
+                "session_start",
+                "total_events",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_add_single_event() {
+        let mut data = SeismicData::new();
+        let event = SeismicEvent::test_event();
+
+        data.add_or_update_event(event).unwrap();
+
+        let stats = data.get_stats();
+        assert_eq!(stats.total_events, 1);
+    }
+
+    #[test]
+    fn test_add_multiple_events() {
+        let mut data = SeismicData::new();
+        let events = vec![SeismicEvent::test_event(), {
+            let mut event = SeismicEvent::test_event();
+            event.id = "test2".to_string();
+            event
+        }];
+
+        data.add_events(events).unwrap();
+
+        let stats = data.get_stats();
+        assert_eq!(stats.total_events, 2);
+    }
+
+    #[test]
+    fn test_clear_data() {
+        let mut data = SeismicData::new();
+        data.add_or_update_event(SeismicEvent::test_event())
+            .unwrap();
+
+        data.clear();
+
+        let stats = data.get_stats();
+        assert_eq!(stats.total_events, 0);
+    }
+
+    #[test]
+    fn test_cleanup_by_event_count() {
+        let config = DataConfig {
+            max_events: 3,
+            auto_cleanup: true,
+            retention_days: 0, // Disable retention cleanup
+            retention_grace_hours: DEFAULT_RETENTION_GRACE_HOURS,
+            precision_decimals: None,
+            min_ingest_magnitude: None,
+            use_spatial_index: true,
+            cleanup_strategy: CleanupStrategy::DropOldest,
+            round_realtime_magnitudes: false,
+            enabled_analytics: DataConfig::default().enabled_analytics,
+            merge_policy: MergePolicy::default(),
+            parallel_recompute: true,
+            missing_magnitude: MissingMagnitude::default(),
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            depth_unit: DepthUnit::default(),
+            best_origin_only: false,
+        };
+        let mut data = SeismicData::with_config(config);
+
+        let mut events = Vec::new();
+        for i in 0..5 {
+            let mut event = SeismicEvent::test_event();
+            event.id = format!("test_{}", i);
+            let time_delta = chrono::TimeDelta::seconds(i as i64);
+            event.time = event.time + time_delta;
+            event.last_update = event.last_update + time_delta;
+            events.push(event);
+        }
+
+        data.add_events(events).unwrap();
+
+        let stats = data.get_stats();
+        assert_eq!(stats.total_events, 3);
+
+        let remaining_events = data.get_chronological_events().unwrap();
+        assert_eq!(remaining_events.len(), 3);
+        assert_eq!(remaining_events[0].id, "test_2");
+        assert_eq!(remaining_events[1].id, "test_3");
+        assert_eq!(remaining_events[2].id, "test_4");
+    }
+
+    #[test]
+    fn test_cleanup_by_retention_period() {
+        let config = DataConfig {
+            max_events: 0, // Disable count-based cleanup
+            auto_cleanup: true,
+            retention_days: 1, // Keep only 1 day of data
+            retention_grace_hours: DEFAULT_RETENTION_GRACE_HOURS,
+            precision_decimals: None,
+            min_ingest_magnitude: None,
+            use_spatial_index: true,
+            cleanup_strategy: CleanupStrategy::DropOldest,
+            round_realtime_magnitudes: false,
+            enabled_analytics: DataConfig::default().enabled_analytics,
+            merge_policy: MergePolicy::default(),
+            parallel_recompute: true,
+            missing_magnitude: MissingMagnitude::default(),
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            depth_unit: DepthUnit::default(),
+            best_origin_only: false,
+        };
+        let mut data = SeismicData::with_config(config);
+
+        let now = chrono::Utc::now();
+        let old_time = now - chrono::TimeDelta::days(2); // 2 days ago (should be cleaned)
+        let recent_time = now - chrono::TimeDelta::hours(12); // 12 hours ago (should be kept)
+
+        let mut old_event = SeismicEvent::test_event();
+        old_event.id = "old_event".to_string();
+        old_event.time = old_time;
+        old_event.last_update = old_time;
+
+        let mut recent_event = SeismicEvent::test_event();
+        recent_event.id = "recent_event".to_string();
+        recent_event.time = recent_time;
+        recent_event.last_update = recent_time;
+
+        data.add_events(vec![old_event, recent_event]).unwrap();
+
+        let stats = data.get_stats();
+        assert_eq!(stats.total_events, 1);
+
+        let remaining_events = data.get_events().unwrap();
+        assert_eq!(remaining_events.len(), 1);
+        assert_eq!(remaining_events[0].id, "recent_event");
+    }
+
+    #[test]
+    fn test_retention_grace_keeps_boundary_event() {
+        let mut data = SeismicData::with_config(DataConfig {
+            max_events: 0,
+            retention_days: 1,
+            retention_grace_hours: 1,
+            ..Default::default()
+        });
+
+        let now = chrono::Utc::now();
+        let event = |id: &str, age: chrono::TimeDelta| {
+            let mut event = SeismicEvent::test_event();
+            event.id = id.to_string();
+            event.time = now - age;
+            event.last_update = event.time;
+            event
+        };
+        // Just past the retention period but within the grace period
+        let boundary = event(
+            "boundary",
+            chrono::TimeDelta::days(1) + chrono::TimeDelta::minutes(1),
+        );
+        let expired = event(
+            "expired",
+            chrono::TimeDelta::days(1) + chrono::TimeDelta::hours(2),
+        );
+        data.add_events(vec![boundary, expired]).unwrap();
+        let ids: Vec<String> = data
+            .get_events()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(ids, ["boundary"]);
+
+        // Later cleanups don't drop it while the cutoff creeps forward
+        for i in 0..3 {
+            data.add_or_update_event(event(&format!("new_{}", i), chrono::TimeDelta::zero()))
+                .unwrap();
+        }
+        assert!(data
+            .get_events()
+            .unwrap()
+            .iter()
+            .any(|e| e.id == "boundary"));
+        assert_eq!(data.get_stats().total_events, 4);
+
+        // Once dropped, fetching the event again doesn't bring it back
+        data.config.retention_grace_hours = 0;
+        data.perform_cleanup().unwrap();
+        assert_eq!(data.get_stats().total_events, 3);
+        let boundary = event(
+            "boundary",
+            chrono::TimeDelta::days(1) + chrono::TimeDelta::minutes(1),
+        );
+        assert_eq!(data.merge_events(vec![boundary.clone()]).unwrap(), 0);
+        data.add_or_update_event(boundary).unwrap();
+        assert_eq!(data.get_stats().total_events, 3);
+    }
+
+    #[test]
+    fn test_cleanup_disabled() {
+        let config = DataConfig {
+            max_events: 2,
+            auto_cleanup: false, // Cleanup disabled
+            retention_days: 1,
+            retention_grace_hours: DEFAULT_RETENTION_GRACE_HOURS,
+            precision_decimals: None,
+            min_ingest_magnitude: None,
+            use_spatial_index: true,
+            cleanup_strategy: CleanupStrategy::DropOldest,
+            round_realtime_magnitudes: false,
+            enabled_analytics: DataConfig::default().enabled_analytics,
+            merge_policy: MergePolicy::default(),
+            parallel_recompute: true,
+            missing_magnitude: MissingMagnitude::default(),
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            depth_unit: DepthUnit::default(),
+            best_origin_only: false,
+        };
+        let mut data = SeismicData::with_config(config);
+
+        let mut events = Vec::new();
+        for i in 0..5 {
+            let mut event = SeismicEvent::test_event();
+            event.id = format!("test_{}", i);
+            events.push(event);
+        }
+
+        data.add_events(events).unwrap();
+
+        let stats = data.get_stats();
+        assert_eq!(stats.total_events, 5);
+    }
+
+    #[test]
+    fn test_manual_cleanup() {
+        let config = DataConfig {
+            max_events: 3,
+            auto_cleanup: false,
+            retention_days: 0,
+            retention_grace_hours: DEFAULT_RETENTION_GRACE_HOURS,
+            precision_decimals: None,
+            min_ingest_magnitude: None,
+            use_spatial_index: true,
+            cleanup_strategy: CleanupStrategy::DropOldest,
+            round_realtime_magnitudes: false,
+            enabled_analytics: DataConfig::default().enabled_analytics,
+            merge_policy: MergePolicy::default(),
+            parallel_recompute: true,
+            missing_magnitude: MissingMagnitude::default(),
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            depth_unit: DepthUnit::default(),
+            best_origin_only: false,
+        };
+        let mut data = SeismicData::with_config(config);
+
+        let mut events = Vec::new();
+        for i in 0..5 {
+            let mut event = SeismicEvent::test_event();
+            event.id = format!("test_{}", i);
+            let time_delta = chrono::TimeDelta::seconds(i as i64);
+            event.time = event.time + time_delta;
+            event.last_update = event.last_update + time_delta;
+            events.push(event);
+        }
+
+        data.add_events(events).unwrap();
+
+        let stats = data.get_stats();
+        assert_eq!(stats.total_events, 5);
+
+        data.maybe_cleanup().unwrap();
+
+        let stats = data.get_stats();
+        assert_eq!(stats.total_events, 3);
+    }
+
+    #[test]
+    fn test_config_update() {
+        let mut data = SeismicData::new();
+
+        let mut events = Vec::new();
+        for i in 0..5 {
+            let mut event = SeismicEvent::test_event();
+            event.id = format!("test_{}", i);
+            events.push(event);
+        }
+        data.add_events(events).unwrap();
+
+        let stats = data.get_stats();
+        assert_eq!(stats.total_events, 5);
+
+        let new_config = DataConfig {
+            max_events: 3,
+            auto_cleanup: false, // Don't auto-cleanup on config change
+            retention_days: 0,
+            retention_grace_hours: DEFAULT_RETENTION_GRACE_HOURS,
+            precision_decimals: None,
+            min_ingest_magnitude: None,
+            use_spatial_index: true,
+            cleanup_strategy: CleanupStrategy::DropOldest,
+            round_realtime_magnitudes: false,
+            enabled_analytics: DataConfig::default().enabled_analytics,
+            merge_policy: MergePolicy::default(),
+            parallel_recompute: true,
+            missing_magnitude: MissingMagnitude::default(),
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            depth_unit: DepthUnit::default(),
+            best_origin_only: false,
+        };
+        data.update_config(new_config).unwrap();
+
+        let stats = data.get_stats();
+        assert_eq!(stats.total_events, 5);
+
+        data.maybe_cleanup().unwrap();
+        let stats = data.get_stats();
+        assert_eq!(stats.total_events, 3);
+    }
+
+    #[test]
+    fn test_memory_usage_estimate() {
+        let mut data = SeismicData::new();
+
+        let stats = data.get_stats();
+        assert_eq!(stats.memory_usage_estimate, 0);
+
+        data.add_or_update_event(SeismicEvent::test_event())
+            .unwrap();
+
+        let stats = data.get_stats();
+        assert!(stats.memory_usage_estimate > 0);
+        assert_eq!(stats.memory_usage_estimate, 500); // 1 event * 500 bytes
+    }
+
+    #[test]
+    fn test_magnitude_range_is_inclusive() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+
+        let events: Vec<_> = [1.9, 2.0, 2.5, 3.0, 3.1]
+            .iter()
+            .enumerate()
+            .map(|(i, magnitude)| {
+                let mut event = SeismicEvent::test_event();
+                event.id = format!("test_{}", i);
+                event.magnitude = *magnitude;
+                event
+            })
+            .collect();
+        data.add_events(events).unwrap();
+
+        let mut magnitudes: Vec<f64> = data
+            .get_events_in_magnitude_range(2.0, 3.0)
+            .unwrap()
+            .iter()
+            .map(|event| event.magnitude)
+            .collect();
+        magnitudes.sort_by(f64::total_cmp);
+
+        assert_eq!(magnitudes, vec![2.0, 2.5, 3.0]);
+
+        let exact = data.get_events_in_magnitude_range(2.5, 2.5).unwrap();
+        assert_eq!(exact.len(), 1);
+    }
+
+    #[test]
+    fn test_analytics_since_only_counts_recent_events() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        let now = chrono::Utc::now();
+
+        let events: Vec<_> = [
+            ("old_1", 6.0, 10, "OLD REGION"),
+            ("old_2", 5.5, 5, "OLD REGION"),
+            ("new_1", 3.0, 0, "NEW REGION"),
+            ("new_2", 4.0, 0, "NEW REGION"),
+        ]
+        .into_iter()
+        .map(|(id, magnitude, days_ago, region)| {
+            let mut event = SeismicEvent::test_event();
+            event.id = id.to_string();
+            event.magnitude = magnitude;
+            event.time = now - chrono::TimeDelta::days(days_ago);
+            event.flynn_region = region.to_string();
+            event
+        })
+        .collect();
+        data.add_events(events).unwrap();
+
+        let since = now - chrono::TimeDelta::hours(1);
+        let analytics = data.get_analytics_since(since).unwrap();
+        let section = |title: &str| {
+            analytics
+                .stats
+                .iter()
+                .find(|stat| stat.title == title)
+                .unwrap()
+                .data
+                .clone()
+        };
+
+        // Single-row sections are returned as one object
+        let regional = section("Regional Analysis");
+        assert_eq!(regional["flynn_region"], "NEW REGION");
+        assert_eq!(regional["event_count"], 2);
+
+        let magnitudes = section("Magnitude Statistics");
+        assert_eq!(magnitudes["max_magnitude"], 4.0);
+        assert_eq!(magnitudes["mean_magnitude"], 3.5);
+
+        // Model fits are computed over the recent events too, in the same
+        // shape as the polygon analytics
+        let gutenberg_richter = section("Gutenberg-Richter Analysis");
+        assert_eq!(gutenberg_richter["total_events"], 2);
+
+        // The stored catalog keeps every event
+        assert_eq!(data.get_stats().total_events, 4);
+    }
+
+    #[test]
+    fn test_thin_aftershocks_keeps_mainshocks_and_isolated_events() {
+        let mut data = SeismicData::with_config(DataConfig {
+            max_events: 40,
+            auto_cleanup: true,
+            cleanup_strategy: CleanupStrategy::ThinAftershocks {
+                space_km: 50.0,
+                time_days: 10.0,
+            },
+            ..Default::default()
+        });
+        let start = chrono::Utc::now() - chrono::TimeDelta::days(30);
+
+        let mut mainshock = SeismicEvent::test_event();
+        mainshock.id = "mainshock".to_string();
+        mainshock.latitude = 38.0;
+        mainshock.longitude = 142.0;
+        mainshock.magnitude = 6.5;
+        mainshock.time = start;
+
+        // Aftershocks within ~15 km and a few days, the largest being M4.9
+        let mut events: Vec<_> = (0..60)
+            .map(|i| {
+                let mut event = mainshock.clone();
+                event.id = format!("aftershock_{}", i);
+                event.latitude += (i % 10) as f64 * 0.01;
+                event.longitude += (i / 10) as f64 * 0.02;
+                event.magnitude = 3.0 + (i % 15) as f64 * 0.1;
+                event.time = start + chrono::TimeDelta::hours(i + 1);
+                event
+            })
+            .collect();
+        events[7].magnitude = 4.9;
+
+        // Isolated events: far away, or nearby but long after the window
+        let isolated = [(-33.0, -71.0, 0), (40.0, 22.0, 1), (38.0, 142.0, 20)];
+        for (i, &(latitude, longitude, days_later)) in isolated.iter().enumerate() {
+            let mut event = mainshock.clone();
+            event.id = format!("isolated_{}", i);
+            event.latitude = latitude;
+            event.longitude = longitude;
+            event.magnitude = 3.5;
+            event.time = start + chrono::TimeDelta::days(days_later);
+            events.push(event);
+        }
+        events.push(mainshock);
+        data.add_events(events).unwrap();
+
+        let mut ids: Vec<String> = data
+            .get_events()
+            .unwrap()
+            .into_iter()
+            .map(|event| event.id)
+            .collect();
+        ids.sort();
+        assert_eq!(
+            ids,
+            vec![
+                "aftershock_7",
+                "isolated_0",
+                "isolated_1",
+                "isolated_2",
+                "mainshock"
+            ]
+        );
+        assert_eq!(
+            data.thinned_aftershocks(),
+            [("mainshock".to_string(), 59)].into()
+        );
+
+        // The retention period still applies
+        let mut expired = SeismicEvent::test_event();
+        expired.id = "expired".to_string();
+        expired.time = chrono::Utc::now() - chrono::TimeDelta::days(400);
+        data.add_or_update_event(expired).unwrap();
+        assert_eq!(data.get_stats().total_events, 5);
+    }
+
+    #[test]
+    fn test_max_risk_horizon_validation() {
+        let mut data = SeismicData::new();
+
+        data.set_max_risk_horizon_days(100.0).unwrap();
+        assert_eq!(data.get_analytics().max_risk_horizon_days(), 100.0);
+
+        for days in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let error = data.set_max_risk_horizon_days(days).unwrap_err();
+            assert_eq!(error.category(), "validation");
+        }
+        assert_eq!(data.get_analytics().max_risk_horizon_days(), 100.0);
+    }
+
+    #[test]
+    fn test_magnitude_range_validation() {
+        let data = SeismicData::new();
+
+        let inverted = data.get_events_in_magnitude_range(5.0, 3.0).unwrap_err();
+        assert_eq!(inverted.category(), "validation");
+
+        let out_of_range = data.get_events_in_magnitude_range(3.0, 12.0).unwrap_err();
+        assert_eq!(out_of_range.category(), "validation");
+    }
+
+    fn global_grid_data(use_spatial_index: bool) -> SeismicData {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            use_spatial_index,
+            ..Default::default()
+        });
+
+        let mut events = Vec::new();
+        for lat in (-85..=85).step_by(5) {
+            for lon in (-180..180).step_by(5) {
+                let mut event = SeismicEvent::test_event();
+                event.id = format!("grid_{}_{}", lat, lon);
+                // Offset slightly so no two events are equidistant from the
+                // query points
+                event.latitude = lat as f64 + 0.013 * (lon as f64 / 180.0);
+                event.longitude = lon as f64 + 0.007 * (lat as f64 / 90.0);
+                events.push(event);
+            }
+        }
+        data.add_events(events).unwrap();
+        data
+    }
+
+    fn brute_force_distances(data: &SeismicData, lat: f64, lon: f64) -> Vec<(String, f64)> {
+        let mut distances: Vec<(String, f64)> = data
+            .get_events()
+            .unwrap()
+            .into_iter()
+            .map(|event| {
+                let distance = haversine_km(lat, lon, event.latitude, event.longitude);
+                (event.id, distance)
+            })
+            .collect();
+        distances.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        distances
+    }
+
+    fn polygon_data(points: &[(&str, f64, f64)]) -> SeismicData {
+        let mut data = SeismicData::new();
+        let events = points
+            .iter()
+            .map(|&(id, lat, lon)| {
+                crate::test_utils::create_test_event_with_params(
+                    id,
+                    3.0,
+                    10.0,
+                    lat,
+                    lon,
+                    chrono::Utc::now(),
+                    "Test",
+                )
+            })
+            .collect();
+        data.add_events(events).unwrap();
+        data
+    }
+
+    fn polygon_ids(data: &SeismicData, polygon: &[(f64, f64)]) -> Vec<String> {
+        let mut ids: Vec<_> = data
+            .get_events_in_polygon(polygon.to_vec())
+            .unwrap()
+            .into_iter()
+            .map(|event| event.id)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_events_in_convex_polygon() {
+        let data = polygon_data(&[
+            ("center", 5.0, 5.0),
+            ("edge", 0.0, 5.0),
+            ("vertex", 10.0, 10.0),
+            ("outside", 11.0, 5.0),
+            ("beyond_corner", 10.5, 10.5),
+        ]);
+        let square = [(0.0, 0.0), (0.0, 10.0), (10.0, 10.0), (10.0, 0.0)];
+
+        assert_eq!(
+            polygon_ids(&data, &square),
+            vec!["center", "edge", "vertex"]
+        );
+    }
+
+    #[test]
+    fn test_events_in_concave_polygon() {
+        // An L shape: a 10 x 10 square without its north-east 6 x 6 corner
+        let data = polygon_data(&[
+            ("west_arm", 7.0, 2.0),
+            ("south_arm", 2.0, 7.0),
+            ("notch", 7.0, 7.0),
+            ("notch_edge_north", 7.0, 4.0),
+            ("notch_edge_east", 4.0, 7.0),
+            ("ray_through_vertex", 4.0, 2.0),
+            ("outside_west", 4.0, -1.0),
+        ]);
+        let l_shape = [
+            (0.0, 0.0),
+            (0.0, 10.0),
+            (4.0, 10.0),
+            (4.0, 4.0),
+            (10.0, 4.0),
+            (10.0, 0.0),
+        ];
+
+        assert_eq!(
+            polygon_ids(&data, &l_shape),
+            vec![
+                "notch_edge_east",
+                "notch_edge_north",
+                "ray_through_vertex",
+                "south_arm",
+                "west_arm",
+            ]
+        );
+
+        let error = data
+            .get_events_in_polygon(vec![(0.0, 0.0), (1.0, 1.0)])
+            .unwrap_err();
+        assert_eq!(error.category(), "validation");
+        assert!(data
+            .get_events_in_polygon(vec![(0.0, 0.0), (95.0, 1.0), (1.0, 0.0)])
+            .is_err());
+    }
+
+    #[test]
+    fn test_analytics_in_polygon_match_filtered_events() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        let events = crate::demo::generate_synthetic_catalog(11, 2000, 1.0);
+        data.add_events(events.clone()).unwrap();
+
+        let inside: Vec<_> = events
+            .into_iter()
+            .filter(|event| {
+                (-30.0..=40.0).contains(&event.latitude)
+                    && (-60.0..=90.0).contains(&event.longitude)
+            })
+            .collect();
+        assert!(inside.len() > 100);
+        let expected = IncrementalAnalytics::new();
+        expected.add_events(&inside).unwrap();
+        expected.recompute_all().unwrap();
+        let expected = expected.get_advanced_analytics().unwrap();
+
+        let polygon = vec![(-30.0, -60.0), (40.0, -60.0), (40.0, 90.0), (-30.0, 90.0)];
+        let actual = data.get_analytics_in_polygon(polygon).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&actual).unwrap(),
+            serde_json::to_value(&expected).unwrap()
+        );
+        // The stored catalog keeps all events
+        assert_eq!(data.get_stats().total_events, 2000);
+    }
+
+    #[test]
+    fn test_time_since_last_above_uses_latest_qualifying_event() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        let now = chrono::Utc::now();
+        let events: Vec<_> = [
+            ("old_large", 5.5, 10),
+            ("largest", 6.0, 5),
+            ("just_below", 4.9, 3),
+            ("small", 3.0, 1),
+        ]
+        .into_iter()
+        .map(|(id, magnitude, days_ago)| {
+            let mut event = SeismicEvent::test_event();
+            event.id = id.to_string();
+            event.magnitude = magnitude;
+            event.time = now - chrono::TimeDelta::days(days_ago);
+            event
+        })
+        .collect();
+        data.add_events(events).unwrap();
+
+        let days_since = |magnitude| {
+            let elapsed = data.time_since_last_above(magnitude).unwrap().unwrap();
+            elapsed.num_seconds() as f64 / 86_400.0
+        };
+        assert!((days_since(5.0) - 5.0).abs() < 0.01);
+        // The threshold is inclusive
+        assert!((days_since(4.9) - 3.0).abs() < 0.01);
+        assert!((days_since(-1.0) - 1.0).abs() < 0.01);
+        assert_eq!(data.time_since_last_above(6.5).unwrap(), None);
+
+        let error = data.time_since_last_above(15.0).unwrap_err();
+        assert_eq!(error.category(), "validation");
+    }
+
+    #[test]
+    fn test_disabled_analytics_stay_empty() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            enabled_analytics: HashSet::from(["magnitude_distribution".to_string()]),
+            ..Default::default()
+        });
+        data.add_events(crate::demo::generate_synthetic_catalog(3, 200, 1.0))
+            .unwrap();
+        data.add_or_update_event(SeismicEvent::test_event())
+            .unwrap();
 
-        if self.config.retention_days > 0 {
-            let cutoff_time =
-                chrono::Utc::now() - chrono::TimeDelta::days(self.config.retention_days as i64);
-            let cutoff_ns = cutoff_time.timestamp_nanos_opt().unwrap_or(0);
-            filtered_df = filtered_df.filter(col("time").gt_eq(lit(cutoff_ns)));
+        let analytics = data.get_analytics();
+        assert!(!analytics.get_magnitude_distribution().unwrap().is_empty());
+        assert!(analytics.get_region_hotspots().is_empty());
+        assert!(analytics.get_coordinate_clusters().is_empty());
+        assert!(analytics.get_energy_grid().is_empty());
+        assert!(analytics.get_count_by_date().is_empty());
+        let titles: Vec<_> = analytics
+            .get_advanced_analytics()
+            .unwrap()
+            .stats
+            .into_iter()
+            .map(|stat| stat.title)
+            .collect();
+        assert!(titles.contains(&"Magnitude Statistics".to_string()));
+        assert!(!titles.contains(&"Geographic Hotspots".to_string()));
+
+        // Re-enabling catches up on the events ingested while disabled
+        data.set_enabled_analytics(HashSet::from([
+            "magnitude_distribution".to_string(),
+            "geographic_hotspots".to_string(),
+        ]))
+        .unwrap();
+        let regions: u32 = data
+            .get_analytics()
+            .get_region_hotspots()
+            .iter()
+            .map(|(_, count)| count)
+            .sum();
+        assert_eq!(regions, 201);
+
+        let error = data
+            .set_enabled_analytics(HashSet::from(["heatmap".to_string()]))
+            .unwrap_err();
+        assert_eq!(error.category(), "validation");
+    }
+
+    #[test]
+    fn test_update_config_keeps_analytics_when_set_unchanged() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        data.add_or_update_event(SeismicEvent::test_event())
+            .unwrap();
+        data.recompute_analytics().unwrap();
+
+        data.update_config(DataConfig {
+            auto_cleanup: false,
+            max_events: 50,
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(!data.get_analytics().needs_recompute());
+
+        data.update_config(DataConfig {
+            auto_cleanup: false,
+            enabled_analytics: HashSet::from(["magnitude_distribution".to_string()]),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(data.get_analytics().needs_recompute());
+        assert!(data.get_analytics().get_region_hotspots().is_empty());
+
+        let error = data
+            .update_config(DataConfig {
+                enabled_analytics: HashSet::from(["heatmap".to_string()]),
+                ..Default::default()
+            })
+            .unwrap_err();
+        assert_eq!(error.category(), "validation");
+        assert_eq!(
+            data.config.enabled_analytics,
+            HashSet::from(["magnitude_distribution".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_circle_query_matches_brute_force() {
+        for use_spatial_index in [true, false] {
+            check_circle_queries(&global_grid_data(use_spatial_index));
         }
+    }
 
-        if self.config.max_events > 0 {
-            filtered_df = filtered_df
-                .sort(
-                    ["time"],
-                    SortMultipleOptions::default().with_order_descending(true),
+    fn check_circle_queries(data: &SeismicData) {
+        // Mid-latitude, antimeridian crossing and near-pole circles
+        let queries = [
+            (35.0, -120.0, 1500.0),
+            (-12.0, 179.0, 900.0),
+            (82.0, 40.0, 1200.0),
+            (0.0, 0.0, 0.0),
+        ];
+
+        for (lat, lon, radius) in queries {
+            let expected: Vec<String> = brute_force_distances(data, lat, lon)
+                .into_iter()
+                .filter(|(_, distance)| *distance <= radius)
+                .map(|(id, _)| id)
+                .collect();
+
+            let actual: Vec<String> = data
+                .get_events_in_circle(lat, lon, radius)
+                .unwrap()
+                .into_iter()
+                .map(|event| event.id)
+                .collect();
+
+            assert_eq!(
+                actual, expected,
+                "circle at ({}, {}) r={}",
+                lat, lon, radius
+            );
+        }
+    }
+
+    #[test]
+    fn test_nearest_events_match_brute_force() {
+        for use_spatial_index in [true, false] {
+            check_nearest_queries(&global_grid_data(use_spatial_index));
+        }
+    }
+
+    fn check_nearest_queries(data: &SeismicData) {
+        for (lat, lon) in [(35.3, -120.2), (-44.1, -179.9), (89.0, 10.0)] {
+            let expected: Vec<String> = brute_force_distances(data, lat, lon)
+                .into_iter()
+                .take(8)
+                .map(|(id, _)| id)
+                .collect();
+
+            let actual: Vec<String> = data
+                .get_nearest_events(lat, lon, 8)
+                .unwrap()
+                .into_iter()
+                .map(|event| event.id)
+                .collect();
+
+            assert_eq!(actual, expected, "nearest to ({}, {})", lat, lon);
+        }
+    }
+
+    #[test]
+    fn test_spatial_index_sees_new_events() {
+        let mut data = global_grid_data(true);
+        assert_ne!(data.get_nearest_events(1.0, 1.0, 1).unwrap()[0].id, "fresh");
+
+        let mut event = SeismicEvent::test_event();
+        event.id = "fresh".to_string();
+        event.latitude = 1.0;
+        event.longitude = 1.0;
+        data.add_or_update_event(event).unwrap();
+
+        assert_eq!(data.get_nearest_events(1.0, 1.0, 1).unwrap()[0].id, "fresh");
+    }
+
+    #[test]
+    fn test_events_with_distance_and_azimuth() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        let place = |id: &str, latitude: f64, longitude: f64| {
+            let mut event = SeismicEvent::test_event();
+            event.id = id.to_string();
+            event.latitude = latitude;
+            event.longitude = longitude;
+            event
+        };
+        data.add_events(vec![
+            place("east", 0.0, 1.0),
+            place("north", 2.0, 0.0),
+            place("west", 0.0, -3.0),
+        ])
+        .unwrap();
+
+        let results = data
+            .get_events_with_distance(0.0, 0.0, &EventFilter::default())
+            .unwrap();
+
+        let ids: Vec<&str> = results.iter().map(|(e, _, _)| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["east", "north", "west"]);
+
+        // One degree of longitude along the equator
+        let (_, east_distance, east_azimuth) = &results[0];
+        assert!((east_distance - 111.195).abs() < 0.01);
+        assert!((east_azimuth - 90.0).abs() < 1e-6);
+
+        let (_, _, north_azimuth) = &results[1];
+        assert!(north_azimuth.abs() < 1e-6);
+
+        let (_, _, west_azimuth) = &results[2];
+        assert!((west_azimuth - 270.0).abs() < 1e-6);
+
+        let filter = EventFilter {
+            min_latitude: Some(1.0),
+            ..Default::default()
+        };
+        let filtered = data.get_events_with_distance(0.0, 0.0, &filter).unwrap();
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].0.id, "north");
+    }
+
+    #[test]
+    fn test_activity_centroid_inside_cluster() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        assert_eq!(
+            data.get_activity_centroid(&EventFilter::default(), false)
+                .unwrap(),
+            None
+        );
+
+        let place = |id: &str, latitude: f64, longitude: f64, magnitude: f64| {
+            let mut event = SeismicEvent::test_event();
+            event.id = id.to_string();
+            event.latitude = latitude;
+            event.longitude = longitude;
+            event.magnitude = magnitude;
+            event
+        };
+        // Cluster straddling the antimeridian around (-17, 180)
+        data.add_events(vec![
+            place("a", -16.0, 179.0, 3.0),
+            place("b", -18.0, -179.0, 3.0),
+            place("c", -17.0, 179.5, 3.0),
+            place("d", -17.0, -179.5, 3.0),
+            place("e", -16.5, 179.8, 6.0),
+        ])
+        .unwrap();
+
+        let (lat, lon) = data
+            .get_activity_centroid(&EventFilter::default(), false)
+            .unwrap()
+            .unwrap();
+        assert!((-18.0..=-16.0).contains(&lat), "latitude {lat}");
+        assert!(lon.abs() >= 179.0, "longitude {lon}");
+
+        // Energy weighting is dominated by the M6 event
+        let (lat, lon) = data
+            .get_activity_centroid(&EventFilter::default(), true)
+            .unwrap()
+            .unwrap();
+        assert!((lat + 16.5).abs() < 0.01, "latitude {lat}");
+        assert!((lon - 179.8).abs() < 0.01, "longitude {lon}");
+    }
+
+    #[test]
+    fn test_largest_event_energy_fraction() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        assert_eq!(
+            data.largest_event_energy_fraction(EventFilter::default())
+                .unwrap(),
+            0.0
+        );
+
+        let mut events: Vec<_> = (0..50)
+            .map(|index| {
+                let mut event = SeismicEvent::test_event();
+                event.id = format!("small_{index}");
+                event.magnitude = 3.0;
+                event
+            })
+            .collect();
+        let mut mainshock = SeismicEvent::test_event();
+        mainshock.id = "mainshock".to_string();
+        mainshock.magnitude = 7.0;
+        events.push(mainshock);
+        data.add_events(events).unwrap();
+
+        // An M7 releases 10^6 times the energy of an M3
+        let fraction = data
+            .largest_event_energy_fraction(EventFilter::default())
+            .unwrap();
+        assert!((fraction - 1e6 / (1e6 + 50.0)).abs() < 1e-9, "{fraction}");
+
+        let aftershocks_only = EventFilter {
+            max_magnitude: Some(5.0),
+            ..Default::default()
+        };
+        let fraction = data
+            .largest_event_energy_fraction(aftershocks_only)
+            .unwrap();
+        assert!((fraction - 1.0 / 50.0).abs() < 1e-9, "{fraction}");
+    }
+
+    #[test]
+    fn test_report_matches_analytics() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+
+        let empty = data.generate_report().unwrap();
+        assert_eq!(empty.total_events, 0);
+        assert_eq!(empty.first_event, None);
+        assert_eq!(empty.min_magnitude, None);
+        assert!(empty.top_regions.is_empty());
+        assert!(empty.to_markdown().contains("No events recorded."));
+
+        let regions = ["ALPHA", "BETA", "GAMMA", "DELTA", "EPSILON", "ZETA", "ETA"];
+        let start = SeismicEvent::test_event().time;
+        let events: Vec<_> = (0..120)
+            .map(|index| {
+                let mut event = SeismicEvent::test_event();
+                event.id = format!("report_{}", index);
+                event.magnitude = 2.0 + (index % 7) as f64 * 0.3 / (1 + index % 3) as f64;
+                event.time = start + chrono::TimeDelta::hours(index as i64);
+                // Uneven region counts so the ranking is unambiguous
+                event.flynn_region = regions[(index * index) % 11 % regions.len()].to_string();
+                event
+            })
+            .collect();
+        data.add_events(events).unwrap();
+        data.recompute_analytics().unwrap();
+
+        let report = data.generate_report().unwrap();
+        let analytics = data.get_analytics();
+
+        assert_eq!(report.total_events, 120);
+        assert_eq!(report.first_event, Some(start));
+        assert_eq!(
+            report.last_event,
+            Some(start + chrono::TimeDelta::hours(119))
+        );
+        assert_eq!(report.min_magnitude, Some(2.0));
+        assert_eq!(report.max_magnitude, Some(2.0 + 6.0 * 0.3));
+        assert_eq!(report.b_value, analytics.get_b_value());
+        assert!(report.b_value_uncertainty > 0.0);
+        assert_eq!(
+            report.completeness_magnitude,
+            analytics.get_completeness_magnitude()
+        );
+        assert_eq!(report.total_energy_joules, analytics.get_total_energy());
+        assert_eq!(report.top_regions.len(), REPORT_TOP_REGIONS);
+        assert_eq!(
+            report.top_regions,
+            analytics.get_region_hotspots()[..REPORT_TOP_REGIONS]
+        );
+
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("| Total events | 120 |"));
+        assert!(markdown.contains(&format!("1. {} (", report.top_regions[0].0)));
+    }
+
+    #[test]
+    fn test_counts_by_day_and_month() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        assert!(data.get_counts_by_period(Period::Day).unwrap().is_empty());
+
+        let at = |y: i32, m: u32, d: u32, h: u32| {
+            chrono::NaiveDate::from_ymd_opt(y, m, d)
+                .unwrap()
+                .and_hms_opt(h, 30, 0)
+                .unwrap()
+                .and_utc()
+        };
+        let times = [
+            at(2024, 10, 3, 1),
+            at(2024, 10, 3, 22),
+            at(2024, 10, 17, 5),
+            at(2024, 11, 1, 0),
+            at(2024, 12, 24, 12),
+            at(2024, 12, 24, 13),
+            at(2024, 12, 31, 23),
+        ];
+        let events: Vec<_> = times
+            .iter()
+            .enumerate()
+            .map(|(index, &time)| {
+                let mut event = SeismicEvent::test_event();
+                event.id = format!("period_{}", index);
+                event.time = time;
+                event
+            })
+            .collect();
+        data.add_events(events).unwrap();
+
+        let day = |y: i32, m: u32, d: u32| {
+            chrono::NaiveDate::from_ymd_opt(y, m, d)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+                .and_utc()
+        };
+
+        let daily = data.get_counts_by_period(Period::Day).unwrap();
+        assert_eq!(
+            daily,
+            vec![
+                (day(2024, 10, 3), 2),
+                (day(2024, 10, 17), 1),
+                (day(2024, 11, 1), 1),
+                (day(2024, 12, 24), 2),
+                (day(2024, 12, 31), 1),
+            ]
+        );
+
+        let monthly = data.get_counts_by_period(Period::Month).unwrap();
+        assert_eq!(
+            monthly,
+            vec![
+                (day(2024, 10, 1), 3),
+                (day(2024, 11, 1), 1),
+                (day(2024, 12, 1), 3),
+            ]
+        );
+
+        let yearly = data.get_counts_by_period(Period::Year).unwrap();
+        assert_eq!(yearly, vec![(day(2024, 1, 1), 7)]);
+    }
+
+    #[test]
+    fn test_region_timeseries_by_month() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        let month = |m: u32, d: u32| {
+            chrono::NaiveDate::from_ymd_opt(2024, m, d)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+                .and_utc()
+        };
+        let placed = [
+            ("CENTRAL ITALY", month(10, 3)),
+            ("CENTRAL ITALY", month(10, 20)),
+            ("CENTRAL ITALY", month(11, 2)),
+            ("GREECE", month(10, 5)),
+            ("GREECE", month(11, 9)),
+        ];
+        let events: Vec<_> = placed
+            .iter()
+            .enumerate()
+            .map(|(index, &(region, time))| {
+                let mut event = SeismicEvent::test_event();
+                event.id = format!("region_{}", index);
+                event.flynn_region = region.to_string();
+                event.time = time + chrono::TimeDelta::hours(6);
+                event
+            })
+            .collect();
+        data.add_events(events).unwrap();
+
+        let monthly = data
+            .get_region_timeseries("central italy", Period::Month)
+            .unwrap();
+        assert_eq!(monthly, vec![(month(10, 1), 2), (month(11, 1), 1)]);
+
+        assert!(data
+            .get_region_timeseries("JAPAN", Period::Month)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            data.get_region_timeseries(" ", Period::Month)
+                .unwrap_err()
+                .category(),
+            "validation"
+        );
+    }
+
+    #[test]
+    fn test_detect_coverage_gaps() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        assert!(data.detect_coverage_gaps(6.0).unwrap().is_empty());
+
+        let start = chrono::Utc::now() - chrono::TimeDelta::days(3);
+        // Hourly events with a ten hour outage after the fifth one
+        let offsets = [0, 1, 2, 3, 4, 14, 15, 16];
+        let events: Vec<_> = offsets
+            .iter()
+            .map(|&hours| {
+                let mut event = SeismicEvent::test_event();
+                event.id = format!("hour_{}", hours);
+                event.time = start + chrono::TimeDelta::hours(hours);
+                event
+            })
+            .collect();
+        data.add_events(events).unwrap();
+
+        let gaps = data.detect_coverage_gaps(6.0).unwrap();
+        assert_eq!(gaps.len(), 1);
+        let (gap_start, gap_end, hours) = gaps[0];
+        assert_eq!(gap_start, start + chrono::TimeDelta::hours(4));
+        assert_eq!(gap_end, start + chrono::TimeDelta::hours(14));
+        assert!((hours - 10.0).abs() < 1e-9);
+
+        assert!(data.detect_coverage_gaps(12.0).unwrap().is_empty());
+        assert_eq!(data.detect_coverage_gaps(0.5).unwrap().len(), 8 - 1);
+        assert_eq!(
+            data.detect_coverage_gaps(0.0).unwrap_err().category(),
+            "validation"
+        );
+    }
+
+    #[test]
+    fn test_flag_magnitude_outliers() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        let event = |id: &str, region: &str, magnitude: f64| {
+            let mut event = SeismicEvent::test_event();
+            event.id = id.to_string();
+            event.flynn_region = region.to_string();
+            event.magnitude = magnitude;
+            event
+        };
+
+        let mut events: Vec<_> = (0..9)
+            .map(|index| {
+                event(
+                    &format!("small_{}", index),
+                    "QUIET",
+                    2.0 + 0.1 * (index % 2) as f64,
                 )
-                .limit(self.config.max_events as u32);
+            })
+            .collect();
+        events.push(event("large", "QUIET", 5.5));
+        // A region with an even spread of magnitudes has no outliers
+        events.extend(
+            (0..6).map(|index| event(&format!("spread_{}", index), "BUSY", 2.0 + index as f64)),
+        );
+        // A single-event region has no spread to compare against
+        events.push(event("lonely", "REMOTE", 7.0));
+        data.add_events(events).unwrap();
+
+        assert_eq!(data.flag_magnitude_outliers(2.0).unwrap(), vec!["large"]);
+        assert!(data.flag_magnitude_outliers(4.0).unwrap().is_empty());
+        assert!(data.flag_magnitude_outliers(0.0).is_err());
+        assert!(data.flag_magnitude_outliers(f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_export_selected_columns() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        data.add_or_update_event(SeismicEvent::test_event())
+            .unwrap();
+
+        let csv = String::from_utf8(data.to_csv(&["time", "lat", "lon", "mag"]).unwrap()).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("time,lat,lon,mag"));
+        let row: Vec<&str> = lines.next().unwrap().split(',').collect();
+        assert!(row[0].starts_with("2024-12-10T22:28:31.49"));
+        assert_eq!(row[1..], ["18.8232", "-155.4875", "2.0"]);
+        assert_eq!(lines.next(), None);
+
+        // Output follows the requested order
+        let csv = String::from_utf8(data.to_csv(&["mag", "unid"]).unwrap()).unwrap();
+        assert_eq!(csv.lines().next(), Some("mag,unid"));
+
+        let csv = String::from_utf8(data.to_csv(&[]).unwrap()).unwrap();
+        assert_eq!(csv.lines().next().unwrap(), EXPORT_COLUMNS.join(","));
+
+        let parquet = data.to_parquet(&["lon", "lat"]).unwrap();
+        let df = ParquetReader::new(std::io::Cursor::new(parquet))
+            .finish()
+            .unwrap();
+        assert_eq!(df.get_column_names(), ["lon", "lat"]);
+        assert_eq!(df.height(), 1);
+
+        let unknown = data.to_csv(&["time", "magnitude"]).unwrap_err();
+        assert_eq!(unknown.category(), "validation");
+        assert!(data.to_parquet(&["lat", "lat"]).is_err());
+    }
+
+    #[test]
+    fn test_mag_depth_arrow_round_trip() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        let events = [(4.2, 33.0, 2), (2.1, 5.5, 0), (3.0, 12.0, 1)]
+            .into_iter()
+            .map(|(magnitude, depth, hours)| {
+                let mut event = SeismicEvent::test_event();
+                event.id = format!("arrow_{}", hours);
+                event.magnitude = magnitude;
+                event.depth = depth;
+                event.time += chrono::TimeDelta::hours(hours);
+                event
+            })
+            .collect();
+        data.add_events(events).unwrap();
+
+        let bytes = data.get_mag_depth_arrow().unwrap();
+        let df = IpcReader::new(std::io::Cursor::new(bytes))
+            .finish()
+            .unwrap();
+        assert_eq!(df.get_column_names(), ["time", "mag", "depth"]);
+        let mags: Vec<f64> = df
+            .column("mag")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        let depths: Vec<f64> = df
+            .column("depth")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(mags, [2.1, 3.0, 4.2]);
+        assert_eq!(depths, [5.5, 12.0, 33.0]);
+
+        let first = df
+            .column("time")
+            .unwrap()
+            .datetime()
+            .unwrap()
+            .get(0)
+            .unwrap();
+        let expected = SeismicEvent::test_event().time;
+        assert_eq!(chrono::DateTime::from_timestamp_nanos(first), expected);
+    }
+
+    #[test]
+    fn test_export_snaps_coordinates_to_grid() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        let events = [
+            (18.8232, -155.4875),
+            (18.8461, -155.4512),
+            (-33.4489, -70.6693),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(index, (latitude, longitude))| {
+            let mut event = SeismicEvent::test_event();
+            event.id = format!("grid_{}", index);
+            event.latitude = latitude;
+            event.longitude = longitude;
+            event
+        })
+        .collect();
+        data.add_events(events).unwrap();
+
+        let csv = data
+            .to_csv_with_grid(&["unid", "lat", "lon"], Some(0.1))
+            .unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let rows: Vec<Vec<&str>> = csv
+            .lines()
+            .skip(1)
+            .map(|line| line.split(',').collect())
+            .collect();
+        assert_eq!(rows.len(), 3);
+        let mut coordinates: Vec<(&str, &str)> = rows.iter().map(|row| (row[1], row[2])).collect();
+        coordinates.sort();
+        assert_eq!(
+            coordinates,
+            [("-33.4", "-70.7"), ("18.8", "-155.5"), ("18.8", "-155.5")]
+        );
+
+        // Stored coordinates keep full precision
+        let csv = String::from_utf8(data.to_csv(&["lat"]).unwrap()).unwrap();
+        assert!(csv.lines().any(|line| line == "18.8461"));
+
+        // Exports without coordinate columns are left alone
+        assert!(data.to_csv_with_grid(&["unid"], Some(0.1)).is_ok());
+
+        for grid in [0.0, -0.1, f64::NAN, 45.0] {
+            let err = data.to_csv_with_grid(&[], Some(grid)).unwrap_err();
+            assert_eq!(err.category(), "validation");
         }
+    }
+
+    #[test]
+    fn test_modal_values() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        assert_eq!(data.get_modal_values().unwrap(), (0.0, 0.0));
+
+        let events = [
+            (2.1, 5.0),
+            (2.13, 12.0),
+            (2.17, 14.0),
+            (3.5, 15.0),
+            (4.8, 33.0),
+            (2.6, 38.0),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(index, (magnitude, depth))| {
+            let mut event = SeismicEvent::test_event();
+            event.id = format!("modal_{}", index);
+            event.magnitude = magnitude;
+            event.depth = depth;
+            event
+        })
+        .collect();
+        data.add_events(events).unwrap();
+
+        let (magnitude, depth) = data.get_modal_values().unwrap();
+        assert!(
+            (magnitude - 2.15).abs() < 1e-9,
+            "M2.1 bin dominates, got {}",
+            magnitude
+        );
+        assert_eq!(depth, 15.0);
+
+        // The 30-40 km bin now ties the 10-20 km bin, the shallower one wins
+        let mut event = SeismicEvent::test_event();
+        event.id = "modal_tie".to_string();
+        event.depth = 35.0;
+        data.add_or_update_event(event).unwrap();
+        assert_eq!(data.get_modal_values().unwrap().1, 15.0);
+    }
+
+    #[test]
+    fn test_bounding_box_corners() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        assert_eq!(data.get_bounding_box().unwrap(), None);
+
+        let events = [(-33.4, -70.6), (35.7, 139.7), (64.1, -21.9), (-8.3, 115.1)]
+            .into_iter()
+            .enumerate()
+            .map(|(index, (latitude, longitude))| {
+                let mut event = SeismicEvent::test_event();
+                event.id = format!("spread_{}", index);
+                event.latitude = latitude;
+                event.longitude = longitude;
+                event
+            })
+            .collect();
+        data.add_events(events).unwrap();
+
+        assert_eq!(
+            data.get_bounding_box().unwrap(),
+            Some((-33.4, 64.1, -70.6, 139.7))
+        );
+    }
+
+    #[test]
+    fn test_depth_by_magtype() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+
+        let events = [("ml", 5.0), ("ml", 9.0), ("mb", 90.0), ("mb", 110.0)]
+            .into_iter()
+            .enumerate()
+            .map(|(index, (magtype, depth))| {
+                let mut event = SeismicEvent::test_event();
+                event.id = format!("depth_{}", index);
+                event.magnitude_type = magtype.to_string();
+                event.depth = depth;
+                event
+            })
+            .collect();
+        data.add_events(events).unwrap();
+
+        let depths = data.get_depth_by_magtype().unwrap();
+
+        assert_eq!(depths.len(), 2);
+        let (mb_type, mb_mean, mb_std) = &depths[0];
+        let (ml_type, ml_mean, ml_std) = &depths[1];
+        assert_eq!((mb_type.as_str(), ml_type.as_str()), ("mb", "ml"));
+        assert!((mb_mean - 100.0).abs() < 1e-9);
+        assert!((ml_mean - 7.0).abs() < 1e-9);
+        assert!(mb_std > ml_std);
+    }
+
+    #[test]
+    fn test_b_value_by_depth_separates_populations() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+
+        let shallow = crate::demo::generate_synthetic_catalog(1, 3000, 0.7);
+        let deep = crate::demo::generate_synthetic_catalog(2, 3000, 1.5);
+        let sparse = crate::demo::generate_synthetic_catalog(3, 10, 1.0);
+        let events = [
+            (shallow, 0.0, "shallow"),
+            (deep, 300.0, "deep"),
+            (sparse, 600.0, "sparse"),
+        ]
+        .into_iter()
+        .flat_map(|(events, base_depth, label)| {
+            events.into_iter().map(move |mut event| {
+                event.id = format!("{}_{}", label, event.id);
+                event.depth = base_depth + event.depth % 50.0;
+                event
+            })
+        })
+        .collect();
+        data.add_events(events).unwrap();
+
+        let by_depth = data.get_b_value_by_depth(100.0, 50).unwrap();
+
+        assert_eq!(by_depth.len(), 2, "the sparse bin is skipped");
+        let (shallow_center, shallow_b) = by_depth[0];
+        let (deep_center, deep_b) = by_depth[1];
+        assert_eq!((shallow_center, deep_center), (50.0, 350.0));
+        assert!(
+            (shallow_b - 0.7).abs() < 0.2 && (deep_b - 1.5).abs() < 0.3,
+            "shallow b = {}, deep b = {}",
+            shallow_b,
+            deep_b
+        );
+
+        let invalid = data.get_b_value_by_depth(0.0, 50).unwrap_err();
+        assert_eq!(invalid.category(), "validation");
+    }
+
+    #[test]
+    fn test_region_last_activity_stalest_first() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        let now = chrono::Utc::now();
+
+        let events = [
+            ("JAPAN", 2),
+            ("JAPAN", 40),
+            ("GREECE", 90),
+            ("CHILE", 10),
+            ("CHILE", 200),
+            ("CHILE", 12),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(index, (region, days_ago))| {
+            let mut event = SeismicEvent::test_event();
+            event.id = format!("activity_{}", index);
+            event.flynn_region = region.to_string();
+            event.time = now - chrono::TimeDelta::days(days_ago);
+            event
+        })
+        .collect();
+        data.add_events(events).unwrap();
+
+        let activity = data.get_region_last_activity().unwrap();
+        let summary: Vec<(&str, u32)> = activity
+            .iter()
+            .map(|(region, _, count)| (region.as_str(), *count))
+            .collect();
+        assert_eq!(summary, vec![("GREECE", 1), ("CHILE", 3), ("JAPAN", 2)]);
+
+        let japan_last = activity[2].1;
+        let expected = now - chrono::TimeDelta::days(2);
+        assert!((japan_last - expected).num_milliseconds().abs() < 1);
+    }
+
+    fn daily_events(magnitudes_per_day: &[Vec<f64>]) -> Vec<SeismicEvent> {
+        let start = chrono::Utc::now() - chrono::TimeDelta::days(60);
+        let start = start.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc();
+        magnitudes_per_day
+            .iter()
+            .enumerate()
+            .flat_map(|(day, magnitudes)| {
+                magnitudes
+                    .iter()
+                    .enumerate()
+                    .map(move |(index, &magnitude)| {
+                        let mut event = SeismicEvent::test_event();
+                        event.id = format!("day_{}_{}", day, index);
+                        event.magnitude = magnitude;
+                        event.time = start
+                            + chrono::TimeDelta::days(day as i64)
+                            + chrono::TimeDelta::minutes(index as i64 + 1);
+                        event
+                    })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_recurrence_interval_inverts_annual_rate() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        assert_eq!(data.recurrence_interval_years(6.0).unwrap(), f64::INFINITY);
+
+        data.add_events(crate::demo::generate_synthetic_catalog(5, 3000, 1.0))
+            .unwrap();
+
+        let m4 = data.recurrence_interval_years(4.0).unwrap();
+        let m5 = data.recurrence_interval_years(5.0).unwrap();
+        assert!(m5 > m4, "M4 every {} years, M5 every {} years", m4, m5);
+
+        let rate = data.analytics.get_annual_rate_above(Some(5.0));
+        assert!((m5 * rate - 1.0).abs() < 1e-9);
+
+        let invalid = data.recurrence_interval_years(12.0).unwrap_err();
+        assert_eq!(invalid.category(), "validation");
+    }
+
+    #[test]
+    fn test_count_energy_correlation() {
+        let counts = [3, 1, 4, 1, 5, 2, 6, 2, 3, 5];
+        let uniform: Vec<Vec<f64>> = counts.iter().map(|&count| vec![3.0; count]).collect();
+        let mut with_large_event = uniform.clone();
+        with_large_event[1] = vec![7.0];
+
+        let correlation = |days: &[Vec<f64>]| {
+            let mut data = SeismicData::with_config(DataConfig {
+                auto_cleanup: false,
+                ..Default::default()
+            });
+            data.add_events(daily_events(days)).unwrap();
+            data.get_count_energy_correlation(days.len() as u32)
+                .unwrap()
+        };
+
+        let uniform_correlation = correlation(&uniform);
+        let large_event_correlation = correlation(&with_large_event);
+        assert!((uniform_correlation - 1.0).abs() < 1e-9);
+        assert!(
+            large_event_correlation < 0.0,
+            "a single large event should dominate, got {}",
+            large_event_correlation
+        );
 
-        self.analytics.replace_dataframe_and_rebuild(filtered_df)?;
+        let constant: Vec<Vec<f64>> = vec![vec![4.0]; 5];
+        assert_eq!(correlation(&constant), 0.0);
 
-        let new_stats = self.get_stats();
-        log::info!(
-            "Cleanup completed: {} events remaining (was {})",
-            new_stats.total_events,
-            old_stats.total_events
+        let data = SeismicData::new();
+        assert_eq!(
+            data.get_count_energy_correlation(1).unwrap_err().category(),
+            "validation"
         );
-
-        Ok(())
     }
 
-    fn estimate_memory_usage(&self) -> usize {
-        // Rough estimate: each event is approximately 500 bytes
-        let cache = self.analytics.cache.read();
-        cache.total_events * 500
-    }
+    #[test]
+    fn test_rolling_max_magnitude() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        assert!(data.get_rolling_max_magnitude(3).unwrap().is_empty());
 
-    fn dataframe_to_events(&self, df: DataFrame) -> Result<Vec<SeismicEvent>> {
-        let mut events = Vec::new();
-        let height = df.height();
+        let days = [
+            vec![2.0, 3.5],
+            vec![2.5],
+            vec![],
+            vec![1.5],
+            vec![],
+            vec![],
+            vec![],
+            vec![4.0, 1.0],
+        ];
+        let events = daily_events(&days);
+        let first_day = events[0].time.date_naive();
+        data.add_events(events).unwrap();
 
-        if height == 0 {
-            return Ok(events);
-        }
+        let rolling = data.get_rolling_max_magnitude(3).unwrap();
+        let dates: Vec<_> = rolling.iter().map(|&(date, _)| date).collect();
+        let expected_dates: Vec<_> = first_day.iter_days().take(days.len()).collect();
+        assert_eq!(dates, expected_dates);
 
-        let ids = df.column("unid")?.str()?;
-        let lats = df.column("lat")?.f64()?;
-        let lons = df.column("lon")?.f64()?;
-        let times = df.column("time")?.datetime()?;
-        let mags = df.column("mag")?.f64()?;
-        let magtypes = df.column("magtype")?.str()?;
-        let depths = df.column("depth")?.f64()?;
-        let evtypes = df.column("evtype")?.str()?;
-        let flynn_regions = df.column("flynn_region")?.str()?;
-        let source_ids = df.column("source_id")?.str()?;
-        let source_catalogs = df.column("source_catalog")?.str()?;
-        let lastupdates = df.column("lastupdate")?.datetime()?;
-        let authors = df.column("author")?.str()?;
-
-        for i in 0..height {
-            let id = ids.get(i).map(|s| s.to_string()).unwrap_or_default();
-            let latitude = lats.get(i).unwrap_or(0.0);
-            let longitude = lons.get(i).unwrap_or(0.0);
-            let time_ns = times.get(i).unwrap_or(0);
-            let magnitude = mags.get(i).unwrap_or(0.0);
-            let magnitude_type = magtypes.get(i).map(|s| s.to_string()).unwrap_or_default();
-            let depth = depths.get(i).unwrap_or(0.0);
-            let event_type = evtypes.get(i).map(|s| s.to_string()).unwrap_or_default();
-            let flynn_region = flynn_regions
-                .get(i)
-                .map(|s| s.to_string())
-                .unwrap_or_default();
-            let source_id = source_ids.get(i).map(|s| s.to_string()).unwrap_or_default();
-            let source_catalog = source_catalogs
-                .get(i)
-                .map(|s| s.to_string())
-                .unwrap_or_default();
-            let lastupdate_ns = lastupdates.get(i).unwrap_or(0);
-            let author = authors.get(i).map(|s| s.to_string()).unwrap_or_default();
-
-            let time = chrono::DateTime::from_timestamp_nanos(time_ns);
-            let last_update = chrono::DateTime::from_timestamp_nanos(lastupdate_ns);
-
-            let event = SeismicEvent {
-                geometry: geo_types::Point::new(longitude, latitude),
-                source_id,
-                source_catalog,
-                last_update,
-                time,
-                latitude,
-                longitude,
-                depth,
-                event_type,
-                author,
-                magnitude,
-                magnitude_type,
-                flynn_region,
-                id,
-                origins: None,
-                arrivals: None,
-            };
+        let maxima: Vec<f64> = rolling.iter().map(|&(_, max)| max).collect();
+        assert_eq!(maxima, [3.5, 3.5, 3.5, 2.5, 1.5, 1.5, 0.0, 4.0]);
 
-            events.push(event);
-        }
+        let daily: Vec<f64> = data
+            .get_rolling_max_magnitude(1)
+            .unwrap()
+            .iter()
+            .map(|&(_, max)| max)
+            .collect();
+        assert_eq!(daily, [3.5, 2.5, 0.0, 1.5, 0.0, 0.0, 0.0, 4.0]);
 
-        Ok(events)
+        assert_eq!(
+            data.get_rolling_max_magnitude(0).unwrap_err().category(),
+            "validation"
+        );
     }
-}
 
-impl Default for SeismicData {
-    fn default() -> Self {
-        Self::new()
+    fn event_with_station_quality(id: &str, stations: i32, gap: f64) -> SeismicEvent {
+        let mut event = SeismicEvent::test_event();
+        event.id = id.to_string();
+        event.origins = Some(
+            serde_json::from_value(serde_json::json!({
+                "geometry": { "type": "Point", "coordinates": [event.longitude, event.latitude] },
+                "origins": [{
+                    "Source_id": event.source_id,
+                    "Source_catalog": event.source_catalog,
+                    "Lastupdate": "2024-12-10T22:30:25Z",
+                    "Time": "2024-12-10T22:28:31Z",
+                    "Lat": event.latitude,
+                    "Lon": event.longitude,
+                    "Depth": event.depth,
+                    "Nsta": stations,
+                    "Gap": gap
+                }]
+            }))
+            .unwrap(),
+        );
+        event
     }
-}
 
-/// Statistics about the current data state
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct DataStats {
-    pub total_events: usize,
-    pub last_updated: chrono::DateTime<chrono::Utc>,
-    pub memory_usage_estimate: usize,
-}
+    #[test]
+    fn test_quality_events_keep_well_constrained_only() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        let mut unconstrained = SeismicEvent::test_event();
+        unconstrained.id = "no_origins".to_string();
+        data.add_events(vec![
+            event_with_station_quality("well_constrained", 42, 65.0),
+            event_with_station_quality("few_stations", 4, 65.0),
+            event_with_station_quality("wide_gap", 42, 250.0),
+            unconstrained,
+        ])
+        .unwrap();
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::seismic::SeismicEvent;
+        let ids = |events: Vec<SeismicEvent>| -> Vec<String> {
+            let mut ids: Vec<String> = events.into_iter().map(|event| event.id).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(
+            ids(data.get_quality_events(10, 180.0).unwrap()),
+            vec!["well_constrained"]
+        );
+        assert_eq!(
+            ids(data.get_quality_events_with(10, 180.0, true).unwrap()),
+            vec!["no_origins", "well_constrained"]
+        );
 
-    #[test]
-    fn test_seismic_data_creation() {
-        let data = SeismicData::new();
-        let stats = data.get_stats();
-        assert_eq!(stats.total_events, 0);
+        let invalid = data.get_quality_events(10, 400.0).unwrap_err();
+        assert_eq!(invalid.category(), "validation");
     }
 
     #[test]
-    fn test_add_single_event() {
-        let mut data = SeismicData::new();
-        let event = SeismicEvent::test_event();
+    fn test_columnar_events_match_row_events() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        let now = chrono::Utc::now();
+        let events = (0..6)
+            .map(|index| {
+                let mut event = SeismicEvent::test_event();
+                event.id = format!("columnar_{}", index);
+                event.magnitude = 2.0 + index as f64 * 0.5;
+                event.depth = 5.0 * index as f64;
+                event.latitude = 10.0 + index as f64;
+                event.flynn_region = format!("REGION {}", index % 2);
+                event.time = now - chrono::TimeDelta::hours(index);
+                event
+            })
+            .collect();
+        data.add_events(events).unwrap();
 
-        data.add_or_update_event(event).unwrap();
+        let filter = EventFilter {
+            min_magnitude: Some(3.0),
+            ..Default::default()
+        };
+        let columns = data.get_events_columnar(filter).unwrap();
+        let mut rows = data.get_events_in_magnitude_range(3.0, 10.0).unwrap();
+        rows.sort_by_key(|event| event.time);
 
-        let stats = data.get_stats();
-        assert_eq!(stats.total_events, 1);
+        assert_eq!(columns.ids.len(), 4);
+        for (index, event) in rows.iter().enumerate() {
+            assert_eq!(columns.ids[index], event.id);
+            assert_eq!(columns.times[index], event.time);
+            assert_eq!(columns.lats[index], event.latitude);
+            assert_eq!(columns.lons[index], event.longitude);
+            assert_eq!(columns.depths[index], event.depth);
+            assert_eq!(columns.mags[index], event.magnitude);
+            assert_eq!(columns.magtypes[index], event.magnitude_type);
+            assert_eq!(columns.evtypes[index], event.event_type);
+            assert_eq!(columns.regions[index], event.flynn_region);
+            assert_eq!(columns.source_ids[index], event.source_id);
+            assert_eq!(columns.source_catalogs[index], event.source_catalog);
+            assert_eq!(columns.last_updates[index], event.last_update);
+            assert_eq!(columns.authors[index], event.author);
+        }
     }
 
     #[test]
-    fn test_add_multiple_events() {
-        let mut data = SeismicData::new();
-        let events = vec![SeismicEvent::test_event(), {
-            let mut event = SeismicEvent::test_event();
-            event.id = "test2".to_string();
-            event
-        }];
+    fn test_nearest_neighbor_distances_pair_up() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        assert!(data.get_nearest_neighbor_distances().unwrap().is_empty());
 
+        let points = [
+            (35.0, 139.0),
+            (-33.0, -70.0),
+            (35.01, 139.0),
+            (-33.0, -70.02),
+        ];
+        let events = points
+            .into_iter()
+            .enumerate()
+            .map(|(index, (latitude, longitude))| {
+                let mut event = SeismicEvent::test_event();
+                event.id = format!("pair_{}", index);
+                event.latitude = latitude;
+                event.longitude = longitude;
+                event
+            })
+            .collect();
         data.add_events(events).unwrap();
 
-        let stats = data.get_stats();
-        assert_eq!(stats.total_events, 2);
+        let japan = haversine_km(35.0, 139.0, 35.01, 139.0);
+        let chile = haversine_km(-33.0, -70.0, -33.0, -70.02);
+        assert_eq!(
+            data.get_nearest_neighbor_distances().unwrap(),
+            vec![japan, chile, japan, chile]
+        );
+
+        let summary = data.get_nearest_neighbor_summary().unwrap().unwrap();
+        assert_eq!(summary.event_count, 4);
+        assert!((summary.min_km - japan.min(chile)).abs() < 1e-9);
+        assert!((summary.mean_km - (japan + chile) / 2.0).abs() < 1e-9);
     }
 
     #[test]
-    fn test_clear_data() {
-        let mut data = SeismicData::new();
-        data.add_or_update_event(SeismicEvent::test_event())
-            .unwrap();
+    fn test_min_ingest_magnitude_drops_small_events() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            min_ingest_magnitude: Some(3.0),
+            ..Default::default()
+        });
+        let event = |id: &str, magnitude: f64| {
+            let mut event = SeismicEvent::test_event();
+            event.id = id.to_string();
+            event.magnitude = magnitude;
+            event
+        };
 
-        data.clear();
+        data.add_events(vec![
+            event("m1", 1.2),
+            event("m3", 3.0),
+            event("m2", 2.9),
+            event("m5", 5.4),
+        ])
+        .unwrap();
+        data.add_or_update_event(event("m0", 0.5)).unwrap();
+        data.add_or_update_event(event("m4", 4.1)).unwrap();
 
-        let stats = data.get_stats();
-        assert_eq!(stats.total_events, 0);
+        let mut ids: Vec<String> = data
+            .get_events()
+            .unwrap()
+            .into_iter()
+            .map(|event| event.id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, ["m3", "m4", "m5"]);
+        assert_eq!(data.get_stats().session_event_count, 3);
     }
 
     #[test]
-    fn test_cleanup_by_event_count() {
-        let config = DataConfig {
+    fn test_same_timestamp_events_ordered_by_id() {
+        let mut data = SeismicData::with_config(DataConfig {
             max_events: 3,
-            auto_cleanup: true,
-            retention_days: 0, // Disable retention cleanup
-        };
-        let mut data = SeismicData::with_config(config);
-
-        let mut events = Vec::new();
-        for i in 0..5 {
+            auto_cleanup: false,
+            retention_days: 0,
+            retention_grace_hours: DEFAULT_RETENTION_GRACE_HOURS,
+            precision_decimals: None,
+            min_ingest_magnitude: None,
+            use_spatial_index: true,
+            cleanup_strategy: CleanupStrategy::DropOldest,
+            round_realtime_magnitudes: false,
+            enabled_analytics: DataConfig::default().enabled_analytics,
+            merge_policy: MergePolicy::default(),
+            parallel_recompute: true,
+            missing_magnitude: MissingMagnitude::default(),
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            depth_unit: DepthUnit::default(),
+            best_origin_only: false,
+        });
+        let mut earliest = SeismicEvent::test_event();
+        earliest.id = "z_earliest".to_string();
+        earliest.time -= chrono::TimeDelta::seconds(1);
+        let mut events = vec![earliest];
+        for id in ["d", "b", "e", "a", "c"] {
             let mut event = SeismicEvent::test_event();
-            event.id = format!("test_{}", i);
-            let time_delta = chrono::TimeDelta::seconds(i as i64);
-            event.time = event.time + time_delta;
-            event.last_update = event.last_update + time_delta;
+            event.id = id.to_string();
             events.push(event);
         }
-
         data.add_events(events).unwrap();
 
-        let stats = data.get_stats();
-        assert_eq!(stats.total_events, 3);
+        for _ in 0..5 {
+            let ids: Vec<String> = data
+                .get_chronological_events()
+                .unwrap()
+                .into_iter()
+                .map(|event| event.id)
+                .collect();
+            assert_eq!(ids, ["z_earliest", "a", "b", "c", "d", "e"]);
+        }
 
-        let remaining_events = data.get_chronological_events().unwrap();
-        assert_eq!(remaining_events.len(), 3);
-        assert_eq!(remaining_events[0].id, "test_2");
-        assert_eq!(remaining_events[1].id, "test_3");
-        assert_eq!(remaining_events[2].id, "test_4");
+        data.perform_cleanup().unwrap();
+        let ids: Vec<String> = data
+            .get_chronological_events()
+            .unwrap()
+            .into_iter()
+            .map(|event| event.id)
+            .collect();
+        assert_eq!(ids, ["a", "b", "c"]);
     }
 
     #[test]
-    fn test_cleanup_by_retention_period() {
-        let config = DataConfig {
-            max_events: 0, // Disable count-based cleanup
-            auto_cleanup: true,
-            retention_days: 1, // Keep only 1 day of data
-        };
-        let mut data = SeismicData::with_config(config);
-
-        let now = chrono::Utc::now();
-        let old_time = now - chrono::TimeDelta::days(2); // 2 days ago (should be cleaned)
-        let recent_time = now - chrono::TimeDelta::hours(12); // 12 hours ago (should be kept)
-
-        let mut old_event = SeismicEvent::test_event();
-        old_event.id = "old_event".to_string();
-        old_event.time = old_time;
-        old_event.last_update = old_time;
+    fn test_session_count_excludes_loaded_events() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        let session_start = data.get_stats().session_start;
 
-        let mut recent_event = SeismicEvent::test_event();
-        recent_event.id = "recent_event".to_string();
-        recent_event.time = recent_time;
-        recent_event.last_update = recent_time;
+        data.add_or_update_event(SeismicEvent::test_event())
+            .unwrap();
+        let mut second = SeismicEvent::test_event();
+        second.id = "live2".to_string();
+        data.add_events(vec![second]).unwrap();
+        let parquet = data.to_parquet(&[]).unwrap();
 
-        data.add_events(vec![old_event, recent_event]).unwrap();
+        let mut restored = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        assert_eq!(restored.load_parquet(&parquet).unwrap(), 2);
+        // Importing the same file again doesn't duplicate events
+        assert_eq!(restored.load_parquet(&parquet).unwrap(), 2);
+        let mut third = SeismicEvent::test_event();
+        third.id = "live3".to_string();
+        restored.add_or_update_event(third.clone()).unwrap();
+        // Updates and repeated ids aren't new events
+        third.magnitude += 0.1;
+        restored.add_or_update_event(third).unwrap();
+        let mut fourth = SeismicEvent::test_event();
+        fourth.id = "live4".to_string();
+        data.add_events(vec![fourth.clone(), fourth]).unwrap();
 
         let stats = data.get_stats();
-        assert_eq!(stats.total_events, 1);
+        assert_eq!(stats.session_event_count, 3);
+        assert_eq!(stats.session_start, session_start);
 
-        let remaining_events = data.get_events().unwrap();
-        assert_eq!(remaining_events.len(), 1);
-        assert_eq!(remaining_events[0].id, "recent_event");
+        let stats = restored.get_stats();
+        assert_eq!(stats.total_events, 3);
+        assert_eq!(stats.session_event_count, 1);
+        assert!(stats.session_start >= session_start);
     }
 
     #[test]
-    fn test_cleanup_disabled() {
-        let config = DataConfig {
-            max_events: 2,
-            auto_cleanup: false, // Cleanup disabled
-            retention_days: 1,
+    fn test_event_chunks_cover_catalog() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        let events: Vec<_> = (0..23)
+            .map(|i| {
+                let mut event = SeismicEvent::test_event();
+                event.id = format!("test_{}", i);
+                event.magnitude = 1.0 + (i % 5) as f64;
+                event
+            })
+            .collect();
+        data.add_events(events).unwrap();
+
+        let mut chunks = Vec::new();
+        let total = data
+            .for_each_event_chunk(&EventFilter::default(), 5, |chunk| {
+                chunks.push(chunk);
+                Ok(())
+            })
+            .unwrap();
+
+        let sizes: Vec<usize> = chunks.iter().map(Vec::len).collect();
+        assert_eq!(total, 23);
+        assert_eq!(sizes, vec![5, 5, 5, 5, 3]);
+
+        let streamed: Vec<String> = chunks.into_iter().flatten().map(|e| e.id).collect();
+        let catalog: Vec<String> = data
+            .get_events()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(streamed, catalog);
+
+        let filter = EventFilter {
+            min_magnitude: Some(4.0),
+            ..Default::default()
         };
-        let mut data = SeismicData::with_config(config);
+        let mut filtered = Vec::new();
+        data.for_each_event_chunk(&filter, 4, |chunk| {
+            assert!(chunk.len() <= 4);
+            filtered.extend(chunk);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(filtered.len(), 8);
+        assert!(filtered.iter().all(|event| event.magnitude >= 4.0));
 
-        let mut events = Vec::new();
-        for i in 0..5 {
-            let mut event = SeismicEvent::test_event();
-            event.id = format!("test_{}", i);
-            events.push(event);
-        }
+        assert!(data
+            .for_each_event_chunk(&EventFilter::default(), 0, |_| Ok(()))
+            .is_err());
+    }
 
+    #[test]
+    fn test_export_flushes_pending_recompute() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+
+        let events: Vec<_> = (0..3)
+            .map(|i| {
+                let mut event = SeismicEvent::test_event();
+                event.id = format!("test_{}", i);
+                event
+            })
+            .collect();
         data.add_events(events).unwrap();
 
-        let stats = data.get_stats();
-        assert_eq!(stats.total_events, 5);
+        // Re-adding a known event marks analytics stale
+        let mut updated = SeismicEvent::test_event();
+        updated.id = "test_1".to_string();
+        data.add_or_update_event(updated).unwrap();
+        assert!(data.get_analytics().needs_recompute());
+
+        let csv = String::from_utf8(data.to_csv(&["unid"]).unwrap()).unwrap();
+
+        assert_eq!(csv.lines().count(), 4);
+        assert!(!data.get_analytics().needs_recompute());
     }
 
     #[test]
-    fn test_manual_cleanup() {
-        let config = DataConfig {
-            max_events: 3,
+    fn test_missing_magnitude_policy_applies_to_every_ingest_path() {
+        let mut data = SeismicData::with_config(DataConfig {
             auto_cleanup: false,
-            retention_days: 0,
+            ..Default::default()
+        });
+        let unmeasured = |id: &str| {
+            let mut event = SeismicEvent::test_event();
+            event.id = id.to_string();
+            event.magnitude = f64::NAN;
+            event.magnitude_missing = true;
+            event
         };
-        let mut data = SeismicData::with_config(config);
 
-        let mut events = Vec::new();
-        for i in 0..5 {
+        data.add_or_update_event(unmeasured("single")).unwrap();
+        data.add_events(vec![unmeasured("batch")]).unwrap();
+        assert_eq!(data.merge_events(vec![unmeasured("merged")]).unwrap(), 0);
+        assert_eq!(data.get_stats().total_events, 0);
+
+        data.set_missing_magnitude(MissingMagnitude::Fill(0.5))
+            .unwrap();
+        data.add_or_update_event(unmeasured("single")).unwrap();
+        data.add_events(vec![unmeasured("batch")]).unwrap();
+        assert_eq!(data.merge_events(vec![unmeasured("merged")]).unwrap(), 1);
+        let events = data.get_events().unwrap();
+        assert_eq!(events.len(), 3);
+        assert!(events.iter().all(|event| event.magnitude == 0.5));
+
+        let invalid = data
+            .set_missing_magnitude(MissingMagnitude::Fill(12.0))
+            .unwrap_err();
+        assert_eq!(invalid.category(), "validation");
+        assert_eq!(data.missing_magnitude(), MissingMagnitude::Fill(0.5));
+    }
+
+    #[test]
+    fn test_merge_policies_resolve_conflicts() {
+        let version = |magnitude: f64, updated_hours_ago: i64| {
             let mut event = SeismicEvent::test_event();
-            event.id = format!("test_{}", i);
-            let time_delta = chrono::TimeDelta::seconds(i as i64);
-            event.time = event.time + time_delta;
-            event.last_update = event.last_update + time_delta;
-            events.push(event);
-        }
+            event.id = "conflict".to_string();
+            event.magnitude = magnitude;
+            event.last_update = chrono::Utc::now() - chrono::TimeDelta::hours(updated_hours_ago);
+            event
+        };
+        // The stored version is larger, the incoming one more recent
+        let stored = version(5.0, 2);
+        let incoming = version(4.5, 1);
+        let mut other = SeismicEvent::test_event();
+        other.id = "other".to_string();
 
-        data.add_events(events).unwrap();
+        let merged_magnitude = |policy: MergePolicy| {
+            let mut data = SeismicData::with_config(DataConfig {
+                auto_cleanup: false,
+                merge_policy: policy,
+                ..Default::default()
+            });
+            data.add_events(vec![stored.clone()]).unwrap();
 
-        let stats = data.get_stats();
-        assert_eq!(stats.total_events, 5);
+            let added = data
+                .merge_events(vec![incoming.clone(), other.clone()])
+                .unwrap();
+            assert_eq!(added, 1);
 
-        data.maybe_cleanup().unwrap();
+            let events = data.get_events().unwrap();
+            assert_eq!(events.len(), 2);
+            let conflict = events.iter().find(|event| event.id == "conflict").unwrap();
+            conflict.magnitude
+        };
 
-        let stats = data.get_stats();
-        assert_eq!(stats.total_events, 3);
+        assert_eq!(merged_magnitude(MergePolicy::PreferNewer), 4.5);
+        assert_eq!(merged_magnitude(MergePolicy::PreferExisting), 5.0);
+        assert_eq!(merged_magnitude(MergePolicy::PreferHigherMagnitude), 5.0);
+
+        // Duplicates within one batch are resolved the same way
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        let added = data
+            .merge_events(vec![incoming.clone(), stored.clone()])
+            .unwrap();
+        assert_eq!(added, 1);
+        assert_eq!(data.get_events().unwrap()[0].magnitude, 4.5);
+
+        data.set_merge_policy(MergePolicy::PreferHigherMagnitude);
+        data.merge_events(vec![version(6.1, 3)]).unwrap();
+        let events = data.get_events().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].magnitude, 6.1);
     }
 
     #[test]
-    fn test_config_update() {
-        let mut data = SeismicData::new();
-
-        let mut events = Vec::new();
-        for i in 0..5 {
+    fn test_events_after_seq() {
+        let mut data = SeismicData::with_config(DataConfig {
+            max_events: 4,
+            retention_days: 0,
+            ..Default::default()
+        });
+        // Stored out of chronological order
+        let event = |id: &str, hours: i64| {
             let mut event = SeismicEvent::test_event();
-            event.id = format!("test_{}", i);
-            events.push(event);
-        }
+            event.id = id.to_string();
+            event.time += chrono::TimeDelta::hours(hours);
+            event
+        };
+        data.add_events(vec![event("a", 3), event("b", 1)]).unwrap();
+        data.add_or_update_event(event("c", 2)).unwrap();
+        data.add_events(vec![event("d", 0)]).unwrap();
+
+        let ids_after = |data: &SeismicData, seq: u64| -> Vec<(u64, String)> {
+            data.get_events_after_seq(seq)
+                .unwrap()
+                .into_iter()
+                .map(|(seq, event)| (seq, event.id))
+                .collect()
+        };
+        let expected = |ids: &[(u64, &str)]| -> Vec<(u64, String)> {
+            ids.iter().map(|&(seq, id)| (seq, id.to_string())).collect()
+        };
+        assert_eq!(ids_after(&data, 0).len(), 4);
+        assert_eq!(ids_after(&data, 2), expected(&[(3, "c"), (4, "d")]));
+        assert!(ids_after(&data, 4).is_empty());
+
+        // Cleanup drops the oldest event but keeps the numbering of the rest
+        data.add_events(vec![event("e", 4)]).unwrap();
+        assert_eq!(data.get_stats().total_events, 4);
+        assert_eq!(
+            ids_after(&data, 0),
+            expected(&[(1, "a"), (2, "b"), (3, "c"), (5, "e")])
+        );
+    }
+
+    #[test]
+    fn test_reset_analytics_cache_repairs_processors() {
+        let mut data = SeismicData::with_config(DataConfig {
+            auto_cleanup: false,
+            ..Default::default()
+        });
+        let events: Vec<_> = (0..5)
+            .map(|index| {
+                let mut event = SeismicEvent::test_event();
+                event.id = format!("stored_{index}");
+                event.magnitude = 2.0 + index as f64 * 0.5;
+                event
+            })
+            .collect();
         data.add_events(events).unwrap();
 
-        let stats = data.get_stats();
-        assert_eq!(stats.total_events, 5);
+        let analytics = data.analytics_handle();
+        let energy = analytics.get_total_energy();
+        let distribution = analytics.get_magnitude_distribution().unwrap();
 
-        let new_config = DataConfig {
-            max_events: 3,
-            auto_cleanup: false, // Don't auto-cleanup on config change
-            retention_days: 0,
-        };
-        data.update_config(new_config);
+        let mut phantom = SeismicEvent::test_event();
+        phantom.id = "phantom".to_string();
+        phantom.magnitude = 8.0;
+        analytics.corrupt_processors(&phantom).unwrap();
+        assert_ne!(analytics.get_total_energy(), energy);
 
-        let stats = data.get_stats();
-        assert_eq!(stats.total_events, 5);
+        data.reset_analytics_cache().unwrap();
 
-        data.maybe_cleanup().unwrap();
-        let stats = data.get_stats();
-        assert_eq!(stats.total_events, 3);
+        let analytics = data.analytics_handle();
+        assert_eq!(analytics.get_total_energy(), energy);
+        assert_eq!(
+            analytics.get_magnitude_distribution().unwrap(),
+            distribution
+        );
+        assert_eq!(data.get_stats().total_events, 5);
     }
 
     #[test]
-    fn test_memory_usage_estimate() {
+    fn test_replace_all_swaps_catalog() {
         let mut data = SeismicData::new();
+        let mut old = SeismicEvent::test_event();
+        old.id = "old".to_string();
+        old.time = chrono::Utc::now();
+        data.add_or_update_event(old).unwrap();
+        data.get_analytics()
+            .set_grid_binning(crate::analytics::processors::GridBinning::EqualArea);
 
-        let stats = data.get_stats();
-        assert_eq!(stats.memory_usage_estimate, 0);
+        let events: Vec<_> = (0..3)
+            .map(|i| {
+                let mut event = SeismicEvent::test_event();
+                event.id = format!("new_{}", i % 2);
+                event.magnitude = 3.0 + i as f64;
+                event.time = chrono::Utc::now();
+                event
+            })
+            .collect();
+        data.replace_all(events).unwrap();
 
-        data.add_or_update_event(SeismicEvent::test_event())
-            .unwrap();
+        let mut stored = data.get_events().unwrap();
+        stored.sort_by(|a, b| a.id.cmp(&b.id));
+        let stored: Vec<_> = stored
+            .iter()
+            .map(|e| (e.id.as_str(), e.magnitude))
+            .collect();
+        // The later duplicate of new_0 wins
+        assert_eq!(stored, vec![("new_0", 5.0), ("new_1", 4.0)]);
+        assert_eq!(data.get_stats().total_events, 2);
+        assert_eq!(
+            data.get_analytics()
+                .get_magnitude_distribution()
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(
+            data.get_analytics().grid_binning(),
+            crate::analytics::processors::GridBinning::EqualArea
+        );
+    }
 
-        let stats = data.get_stats();
-        assert!(stats.memory_usage_estimate > 0);
-        assert_eq!(stats.memory_usage_estimate, 500); // 1 event * 500 bytes
+    #[test]
+    fn test_replace_all_preserves_data_when_rebuild_fails() {
+        let mut data = SeismicData::new();
+        let events: Vec<_> = (0..4)
+            .map(|i| {
+                let mut event = SeismicEvent::test_event();
+                event.id = format!("kept_{}", i);
+                event.magnitude = 2.0 + i as f64;
+                event.time = chrono::Utc::now();
+                event
+            })
+            .collect();
+        data.add_events(events).unwrap();
+        let distribution = data.get_analytics().get_magnitude_distribution().unwrap();
+
+        // The frame collects, so the rebuild starts before failing on the
+        // missing columns
+        let broken = df!["unid" => ["replacement"], "lat" => [1.0], "lon" => [2.0]]
+            .unwrap()
+            .lazy();
+        assert!(data.replace_dataframe(broken).is_err());
+
+        let mut ids: Vec<_> = data
+            .get_events()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec!["kept_0", "kept_1", "kept_2", "kept_3"]);
+        assert_eq!(data.get_stats().total_events, 4);
+        assert_eq!(
+            data.get_analytics().get_magnitude_distribution().unwrap(),
+            distribution
+        );
     }
 
     #[test]